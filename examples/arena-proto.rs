@@ -3,6 +3,8 @@
     Try running this in playground
 */
 
+#![allow(clippy::needless_return, clippy::redundant_field_names, clippy::disallowed_names)]
+
 use std::ops::{Index, IndexMut};
 
 trait Valid {
@@ -90,7 +92,7 @@ fn main() {
 
     arena.add(Foo { data: 420, valid: true });
     {   // test mutation
-        let mut item = &mut arena[idx];
+        let item = &mut arena[idx];
         println!("{:?}", item);
         item.data = 42;
     }
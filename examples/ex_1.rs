@@ -1,5 +1,7 @@
+extern crate libdcel;
 
-extern crate dcel;
+use libdcel::dcel::DCEL;
+use libdcel::geometry::Point2;
 
 // Example super simple use case of DCEL
 // currently using as test case becaue `cargo test` will fail if
@@ -7,19 +9,17 @@ extern crate dcel;
 
 fn main() {
 
-    let mut my_dcel = dcel::api::DCEL::new();
-    let face1 = [(0,0), (1,1), (1,0)]; // a right triangle
-    let face2 = [(0,0), (1,1), (0,1)]; // another right triangle
-    let face3 = [(0,1), (0,2), (1,1)]; // a third right triangle
+    let points: Vec<Point2> = [(0,0), (1,0), (1,1), (0,1), (0,2)].iter().map(Point2::from).collect();
+    let face1 = [0, 1, 2]; // a right triangle
+    let face2 = [0, 2, 3]; // another right triangle
+    let face3 = [3, 2, 4]; // a third right triangle
 
     // add faces
-    let f1_id = my_dcel.add_face(&face1);
-    let f2_id = my_dcel.add_face(&face2);
-    let f3_id = my_dcel.add_face(&face3);
+    let my_dcel = DCEL::from_polygons(&points, &[face1, face2, face3]).unwrap();
 
-    assert_eq!(3, my_dcel.num_faces());
+    assert_eq!(4, my_dcel.faces.len()); // including the outer face
 
     // merge faces
     //my_dcel.merge_faces(f1_id, f2_id); 
 
-}
\ No newline at end of file
+}
@@ -1,5 +1,9 @@
+use std::cmp;
 use std::ops::{Index, IndexMut};
 use std::convert::From;
+use std::marker::PhantomData;
+use std::slice;
+use std::sync::Arc;
 
 /// Auto-implementing trait for types that are wrappers around
 /// the usize type. Used for ensuring that IDs for one type of
@@ -16,12 +20,12 @@ where
 /// that satisfies the ArenaId trait.
 macro_rules! arena_id {
     ($T:ident) => {
-        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
         pub struct $T(usize);
 
-        impl Into<usize> for $T {
-            fn into(self) -> usize {
-                let $T(idx) = self;
+        impl From<$T> for usize {
+            fn from(id: $T) -> usize {
+                let $T(idx) = id;
                 idx
             }
         }
@@ -57,14 +61,28 @@ macro_rules! make_deleteable {
     }
 }
 
+/// The number of slots in each chunk of an arena. Clones of an arena share
+/// their chunks, copying one only when it's written to.
+const CHUNK_SIZE: usize = 64;
+
+/// A chunk of arena slots, shared between clones until written to.
+type Chunk<T> = Arc<Vec<T>>;
+
+/// Storage for items addressed by typed ids. Removed items stay in their
+/// slots, marked deleted, and their ids are reused last-removed first.
+///
+/// Slots are kept in fixed-size chunks, copy-on-write: cloning an arena is
+/// O(1), and writing to a clone copies only the chunks it touches (and the
+/// table of chunks, once). Writing needs `T: Clone` for that reason.
 #[derive(Debug)]
 pub struct Arena<T, Idx>
 where
     T: Deleteable,
     Idx: ArenaId,
 {
-    data: Vec<T>,
-    idx_reuse_stack: Vec<Idx>,
+    chunks: Arc<Vec<Chunk<T>>>,
+    slot_count: usize,
+    idx_reuse_stack: Arc<Vec<Idx>>,
 }
 
 impl<T, Idx> Arena<T, Idx>
@@ -73,32 +91,308 @@ where
     Idx: ArenaId,
 {
     pub fn new() -> Arena<T, Idx> {
-        let data = Vec::new();
-        let idx_stack = Vec::new();
         Arena {
-            data: data,
-            idx_reuse_stack: idx_stack,
+            chunks: Arc::new(Vec::new()),
+            slot_count: 0,
+            idx_reuse_stack: Arc::new(Vec::new()),
         }
     }
 
+    /// Determines if `idx` refers to a live item in this arena.
+    pub fn contains(&self, idx: Idx) -> bool {
+        let index: usize = idx.into();
+        return index < self.slot_count && !self.slot(index).is_deleted();
+    }
+
+    /// Returns the number of live items in this arena.
+    pub fn len(&self) -> usize {
+        return self.slot_count - self.idx_reuse_stack.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    /// Iterates over the live items in this arena along with their ids.
+    pub fn iter(&self) -> Iter<'_, T, Idx> {
+        return Iter {
+            slots: self.slots(),
+            marker: PhantomData,
+        };
+    }
+
+    /// Returns the ids of all live items, in increasing order.
+    pub fn ids(&self) -> Vec<Idx> {
+        return self.iter().map(|(id, _)| id).collect();
+    }
+
+    /// Iterates over every slot in this arena, deleted or not, in order of id.
+    pub(crate) fn slots(&self) -> Slots<'_, T> {
+        return Slots {
+            chunks: self.chunks.iter(),
+            current: [].iter(),
+            index: 0,
+        };
+    }
+
+    /// The number of slots in this arena, deleted or not.
+    pub(crate) fn slot_count(&self) -> usize {
+        return self.slot_count;
+    }
+
+    /// The slot at `index`, deleted or not.
+    pub(crate) fn slot(&self, index: usize) -> &T {
+        return &self.chunks[index / CHUNK_SIZE][index % CHUNK_SIZE];
+    }
+
+    /// The ids of deleted slots, in the order they will be reused (last first).
+    pub(crate) fn reuse_stack(&self) -> &[Idx] {
+        return &self.idx_reuse_stack;
+    }
+
+}
+
+impl<T, Idx> Arena<T, Idx>
+where
+    T: Deleteable + Clone,
+    Idx: ArenaId,
+{
     pub fn add(&mut self, item: T) -> Idx {
         let idx: Idx;
         if self.idx_reuse_stack.is_empty() {
-            idx = self.data.len().into();
-            self.data.push(item);
+            idx = self.slot_count.into();
+            self.push_slot(item);
         } else {
-            idx = self.idx_reuse_stack.pop().unwrap();
-            self.data[idx.into()] = item;
+            idx = Arc::make_mut(&mut self.idx_reuse_stack).pop().unwrap();
+            *self.slot_mut(idx.into()) = item;
         }
         return idx;
     }
 
     pub fn remove(&mut self, idx: Idx) {
         let index: usize = idx.into();
-        if index < self.data.len() && self.data[index].is_deleted() {
-            self.data[index].set_deleted();
-            self.idx_reuse_stack.push(idx);
+        if index < self.slot_count && !self.slot(index).is_deleted() {
+            self.slot_mut(index).set_deleted();
+            Arc::make_mut(&mut self.idx_reuse_stack).push(idx);
+        }
+    }
+
+    /// The slot at `index`, copying its chunk first if it's shared.
+    fn slot_mut(&mut self, index: usize) -> &mut T {
+        let chunk = &mut Arc::make_mut(&mut self.chunks)[index / CHUNK_SIZE];
+        return &mut Arc::make_mut(chunk)[index % CHUNK_SIZE];
+    }
+
+    /// Appends a slot, starting a new chunk if the last one is full.
+    fn push_slot(&mut self, item: T) {
+        let chunks = Arc::make_mut(&mut self.chunks);
+        if self.slot_count % CHUNK_SIZE == 0 {
+            chunks.push(Arc::new(Vec::with_capacity(CHUNK_SIZE)));
+        }
+        Arc::make_mut(chunks.last_mut().unwrap()).push(item);
+        self.slot_count += 1;
+    }
+
+    /// Drops every slot from `len` on.
+    fn truncate(&mut self, len: usize) {
+        if len >= self.slot_count {
+            return;
+        }
+        let chunks = Arc::make_mut(&mut self.chunks);
+        chunks.truncate((len + CHUNK_SIZE - 1) / CHUNK_SIZE);
+        if let Some(chunk) = chunks.last_mut() {
+            Arc::make_mut(chunk).truncate(len - (len - 1) / CHUNK_SIZE * CHUNK_SIZE);
+        }
+        self.slot_count = len;
+    }
+
+    /// Converts every slot, deleted or not, keeping all ids.
+    pub(crate) fn map<U: Deleteable, F: FnMut(T) -> U>(self, mut f: F) -> Arena<U, Idx> {
+        let chunks = Arc::try_unwrap(self.chunks).unwrap_or_else(|chunks| (*chunks).clone());
+        let chunks = chunks.into_iter()
+            .map(|chunk| {
+                let chunk = Arc::try_unwrap(chunk).unwrap_or_else(|chunk| (*chunk).clone());
+                Arc::new(chunk.into_iter().map(&mut f).collect())
+            })
+            .collect();
+        return Arena {
+            chunks: Arc::new(chunks),
+            slot_count: self.slot_count,
+            idx_reuse_stack: self.idx_reuse_stack,
+        };
+    }
+
+    /// Consumes this arena, returning its live items along with their ids.
+    pub(crate) fn into_items(self) -> Vec<(Idx, T)> {
+        let chunks = Arc::try_unwrap(self.chunks).unwrap_or_else(|chunks| (*chunks).clone());
+        return chunks.into_iter()
+            .flat_map(|chunk| Arc::try_unwrap(chunk).unwrap_or_else(|chunk| (*chunk).clone()))
+            .enumerate()
+            .filter(|(_, item)| !item.is_deleted())
+            .map(|(index, item)| (index.into(), item))
+            .collect();
+    }
+
+    /// Rebuilds an arena from its slots and reuse stack, as returned by
+    /// `slots` and `reuse_stack`.
+    pub(crate) fn from_parts(data: Vec<T>, idx_reuse_stack: Vec<Idx>) -> Arena<T, Idx> {
+        let mut arena = Arena::new();
+        for item in data {
+            arena.push_slot(item);
+        }
+        arena.idx_reuse_stack = Arc::new(idx_reuse_stack);
+        return arena;
+    }
+}
+
+impl<T, Idx> Clone for Arena<T, Idx>
+where
+    T: Deleteable + Clone,
+    Idx: ArenaId,
+{
+    /// Returns a copy sharing all of this arena's chunks, in O(1).
+    fn clone(&self) -> Arena<T, Idx> {
+        return Arena {
+            chunks: self.chunks.clone(),
+            slot_count: self.slot_count,
+            idx_reuse_stack: self.idx_reuse_stack.clone(),
+        };
+    }
+}
+
+/// The slots that differ between two states of an arena, as found by
+/// `Arena::diff`, with what each held in either state.
+#[derive(Debug, Clone)]
+pub(crate) struct ArenaDiff<T, Idx> {
+    before: Vec<(usize, T)>,
+    after: Vec<(usize, T)>,
+    before_len: usize,
+    after_len: usize,
+    before_stack: Vec<Idx>,
+    after_stack: Vec<Idx>,
+}
+
+impl<T, Idx> ArenaDiff<T, Idx> {
+    /// Determines if the two states are the same.
+    pub(crate) fn is_empty(&self) -> bool {
+        return self.before.is_empty() && self.after.is_empty();
+    }
+}
+
+impl<T, Idx> Arena<T, Idx>
+where
+    T: Deleteable + Clone + PartialEq,
+    Idx: ArenaId + PartialEq,
+{
+    /// Finds the slots that differ between this arena and `after`, a later
+    /// state of it. Deleted slots and the reuse stack count too, so stepping
+    /// across the diff restores every id exactly. Chunks the two still
+    /// share are skipped.
+    pub(crate) fn diff(&self, after: &Arena<T, Idx>) -> ArenaDiff<T, Idx> {
+        let mut before_slots = Vec::new();
+        let mut after_slots = Vec::new();
+        let len = cmp::max(self.slot_count, after.slot_count);
+        for c in 0..(len + CHUNK_SIZE - 1) / CHUNK_SIZE {
+            if let (Some(a), Some(b)) = (self.chunks.get(c), after.chunks.get(c)) {
+                if Arc::ptr_eq(a, b) {
+                    continue;
+                }
+            }
+            for i in c * CHUNK_SIZE..cmp::min((c + 1) * CHUNK_SIZE, len) {
+                let old = if i < self.slot_count { Some(self.slot(i)) } else { None };
+                let new = if i < after.slot_count { Some(after.slot(i)) } else { None };
+                if old != new {
+                    before_slots.extend(old.map(|item| (i, item.clone())));
+                    after_slots.extend(new.map(|item| (i, item.clone())));
+                }
+            }
+        }
+        let stacks_differ = self.idx_reuse_stack != after.idx_reuse_stack;
+        return ArenaDiff {
+            before: before_slots,
+            after: after_slots,
+            before_len: self.slot_count,
+            after_len: after.slot_count,
+            before_stack: if stacks_differ { self.idx_reuse_stack.to_vec() } else { Vec::new() },
+            after_stack: if stacks_differ { after.idx_reuse_stack.to_vec() } else { Vec::new() },
+        };
+    }
+
+    /// Moves this arena across a diff it's at either end of: forwards to the
+    /// later state, or backwards to the earlier one.
+    pub(crate) fn step(&mut self, diff: &ArenaDiff<T, Idx>, forwards: bool) {
+        let (slots, len, stack, other_stack) = if forwards {
+            (&diff.after, diff.after_len, &diff.after_stack, &diff.before_stack)
+        } else {
+            (&diff.before, diff.before_len, &diff.before_stack, &diff.after_stack)
+        };
+        self.truncate(len);
+        // Slots past the shorter state's length always differ, so they come
+        // in order and can be pushed
+        for &(i, ref item) in slots {
+            if i < self.slot_count {
+                *self.slot_mut(i) = item.clone();
+            } else {
+                self.push_slot(item.clone());
+            }
+        }
+        if !stack.is_empty() || !other_stack.is_empty() {
+            self.idx_reuse_stack = Arc::new(stack.clone());
+        }
+    }
+}
+
+/// Iterator over every slot of an Arena, deleted or not.
+pub(crate) struct Slots<'a, T: 'a> {
+    chunks: slice::Iter<'a, Chunk<T>>,
+    current: slice::Iter<'a, T>,
+    index: usize,
+}
+
+impl<'a, T> Iterator for Slots<'a, T> {
+    type Item = (usize, &'a T);
+
+    fn next(&mut self) -> Option<(usize, &'a T)> {
+        loop {
+            if let Some(item) = self.current.next() {
+                self.index += 1;
+                return Some((self.index - 1, item));
+            }
+            self.current = self.chunks.next()?.iter();
+        }
+    }
+}
+
+/// Iterator over the live items of an Arena.
+pub struct Iter<'a, T: 'a, Idx> {
+    slots: Slots<'a, T>,
+    marker: PhantomData<Idx>,
+}
+
+impl<'a, T, Idx> Iterator for Iter<'a, T, Idx>
+where
+    T: Deleteable,
+    Idx: ArenaId,
+{
+    type Item = (Idx, &'a T);
+
+    fn next(&mut self) -> Option<(Idx, &'a T)> {
+        for (index, item) in self.slots.by_ref() {
+            if !item.is_deleted() {
+                return Some((index.into(), item));
+            }
         }
+        return None;
+    }
+}
+
+impl<T, Idx> Default for Arena<T, Idx>
+where
+    T: Deleteable,
+    Idx: ArenaId,
+{
+    fn default() -> Arena<T, Idx> {
+        return Arena::new();
     }
 }
 
@@ -110,7 +404,7 @@ where
     type Output = T;
 
     fn index(&self, node: Idx) -> &T {
-        let value = &self.data[node.into()];
+        let value = self.slot(node.into());
 
         // Hacky code
         if value.is_deleted() {
@@ -123,11 +417,11 @@ where
 
 impl<T, Idx> IndexMut<Idx> for Arena<T, Idx>
 where
-    T: Deleteable,
+    T: Deleteable + Clone,
     Idx: ArenaId,
 {
     fn index_mut(&mut self, node: Idx) -> &mut T {
-        let value = &mut self.data[node.into()];
+        let value = self.slot_mut(node.into());
 
         // More hacky code
         if value.is_deleted() {
@@ -136,4 +430,74 @@ where
 
         return value;
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    arena_id!(ItemId);
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Item {
+        value: usize,
+        deleted: bool,
+    }
+    make_deleteable!(Item);
+
+    fn item(value: usize) -> Item {
+        return Item { value: value, deleted: false };
+    }
+
+    #[test]
+    fn clones_copy_on_write() {
+        let mut arena: Arena<Item, ItemId> = Arena::new();
+        for i in 0..200 {
+            arena.add(item(i));
+        }
+        arena.remove(ItemId::from(3));
+        let mut copy = arena.clone();
+        assert!(Arc::ptr_eq(&arena.chunks, &copy.chunks));
+
+        // Writing to the copy leaves the original and its other chunks alone
+        copy[ItemId::from(130)].value = 1000;
+        assert_eq!(copy.add(item(7)), ItemId::from(3));
+        copy.remove(ItemId::from(199));
+        assert_eq!(arena[ItemId::from(130)].value, 130);
+        assert!(!arena.contains(ItemId::from(3)));
+        assert!(arena.contains(ItemId::from(199)));
+        assert_eq!(arena.reuse_stack(), &[ItemId::from(3)]);
+        assert!(Arc::ptr_eq(&arena.chunks[1], &copy.chunks[1]));
+        assert!(!Arc::ptr_eq(&arena.chunks[0], &copy.chunks[0]));
+        assert!(!Arc::ptr_eq(&arena.chunks[2], &copy.chunks[2]));
+        assert_eq!(copy.len(), 199);
+        assert_eq!(copy.iter().map(|(_, item)| item.value).sum::<usize>(), (0..199).sum::<usize>() - 3 + 7 + 870);
+
+        // Writing to the original after the copy is dropped copies nothing
+        drop(copy);
+        let chunk = Arc::as_ptr(&arena.chunks[2]);
+        arena[ItemId::from(130)].value = 1;
+        assert_eq!(Arc::as_ptr(&arena.chunks[2]), chunk);
+
+        // Diffs skip shared chunks and step back and forth across a shrink
+        let before = arena.clone();
+        let mut after = Arena::from_parts(arena.slots().take(70).map(|(_, item)| item.clone()).collect(), Vec::new());
+        after[ItemId::from(5)].value = 50;
+        let diff = before.diff(&after);
+        assert_eq!(diff.before.len(), 131);
+        arena.step(&diff, true);
+        assert_eq!(arena.slot_count(), 70);
+        assert!(arena.diff(&after).is_empty());
+        arena.step(&diff, false);
+        assert!(arena.diff(&before).is_empty());
+        assert_eq!(arena.reuse_stack(), &[ItemId::from(3)]);
+
+        // Clones can be handed to other threads and written there
+        let mut copy = arena.clone();
+        let copy = ::std::thread::spawn(move || {
+            copy[ItemId::from(0)].value = 9;
+            return copy;
+        }).join().unwrap();
+        assert_eq!((arena[ItemId::from(0)].value, copy[ItemId::from(0)].value), (0, 9));
+    }
+}
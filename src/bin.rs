@@ -1,51 +1,278 @@
+//! The `dcel` command-line tool, for inspecting, converting and fixing up
+//! planar subdivisions without writing code.
+
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
+
 extern crate libdcel;
 
-use libdcel::geometry::*;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::process;
+
 use libdcel::dcel::*;
+use libdcel::formats::json::Value;
+use libdcel::formats::svg::SvgOptions;
+use libdcel::formats::{geojson, obj, off, text, wkt};
+use libdcel::geometry::Point2;
 
-pub fn edge_split() {
-    let mut dcel = DCEL::new();
+const USAGE: &str = "usage: dcel <command> [options]
 
-    let p1 = Point2::new(0.0, 0.0);
-    let p2 = Point2::new(1.0, 0.0);
-    let p3 = Point2::new(0.0, 1.0);
+commands:
+  validate <file>                   check the DCEL's invariants, listing every violation
+  convert <in> <out>                convert between formats
+  stats <file>                      print element counts and area
+  render --svg <file> [out.svg]     draw the subdivision as SVG
+        [--labels] [--half-edges]
+  triangulate <in> <out>            triangulate every face
+        [--delaunay]
 
-    let v1 = dcel.add_vertex(p1);
-    let v2 = dcel.add_vertex(p2);
-    let v3 = dcel.add_vertex(p3);
+formats are chosen by extension: .obj, .off, .geojson/.json, .wkt,
+.dcel (text dump), .dcelb (native binary), and for output only .svg
+and .dot (Graphviz)";
 
-    let inner_face = dcel.add_face();
-    let outer_face = dcel.outer_face;
+/// The subdivision every command works on: planar, with the properties of
+/// each face where the input format has any. The outer face and gaps, such
+/// as the insides of holes, carry `None`.
+type Map = DCEL<Point2, Option<Value>>;
+
+/// Parsed command-line arguments: positional arguments and `--flags`.
+#[derive(Debug, PartialEq)]
+struct Args {
+    command: String,
+    positional: Vec<String>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Args, String> {
+        let mut args = args.into_iter();
+        let command = args.next().ok_or("missing command")?;
+        let mut positional = Vec::new();
+        let mut flags = Vec::new();
+        for arg in args {
+            if let Some(flag) = arg.strip_prefix("--") {
+                flags.push(flag.to_string());
+            } else {
+                positional.push(arg);
+            }
+        }
+        return Ok(Args { command: command, positional: positional, flags: flags });
+    }
 
-    let e12 = dcel.add_half_edge(v1, inner_face);
-    let e23 = dcel.add_half_edge(v2, inner_face);
-    let e31 = dcel.add_half_edge(v3, inner_face);
+    fn flag(&self, name: &str) -> bool {
+        return self.flags.iter().any(|f| f == name);
+    }
 
-    let e13 = dcel.add_half_edge(v1, outer_face);
-    let e32 = dcel.add_half_edge(v3, outer_face);
-    let e21 = dcel.add_half_edge(v2, outer_face);
+    /// Fails on any flag not in `known`.
+    fn check_flags(&self, known: &[&str]) -> Result<(), String> {
+        for flag in &self.flags {
+            if !known.contains(&flag.as_str()) {
+                return Err(format!("unknown option `--{}` for `{}`", flag, self.command));
+            }
+        }
+        return Ok(());
+    }
 
-    dcel.make_twins(e12, e21);
-    dcel.make_twins(e23, e32);
-    dcel.make_twins(e31, e13);
+    /// Returns the positional arguments, checking there are between `min`
+    /// and `max` of them.
+    fn files(&self, min: usize, max: usize) -> Result<&[String], String> {
+        if self.positional.len() < min || self.positional.len() > max {
+            return Err(format!("wrong number of arguments for `{}`", self.command));
+        }
+        return Ok(&self.positional);
+    }
+}
 
-    dcel.make_next(e12, e23);
-    dcel.make_next(e23, e31);
-    dcel.make_next(e31, e12);
+fn extension(path: &str) -> String {
+    return Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+}
 
-    dcel.make_next(e13, e32);
-    dcel.make_next(e32, e21);
-    dcel.make_next(e21, e13);
+fn load(path: &str) -> Result<Map, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    let reader = BufReader::new(file);
+    let result = match extension(path).as_str() {
+        "obj" => obj::read::<Point2, _>(reader).map(unlabelled),
+        "off" => off::read::<Point2, _>(reader).map(unlabelled),
+        "geojson" | "json" => geojson::read(reader),
+        "wkt" => wkt::read(reader).map(|d| d.map_face_data(|source| {
+            source.map(|i| Value::Object(vec![("source".to_string(), Value::Number(i.to_string()))]))
+        })),
+        "dcel" => text::read(reader),
+        "dcelb" => DCEL::read_from(reader),
+        other => return Err(format!("{}: unknown input format `{}`", path, other)),
+    };
+    return result.map_err(|e| format!("{}: {}", path, e));
+}
 
-    println!("{:?}\n", dcel);
-    println!("{:?}", dcel.get_cycle_from(e12));
-    println!("{:?}", dcel.get_cycle_from(e13));
+/// Gives every inner face of a mesh `null` properties.
+fn unlabelled(dcel: DCEL<Point2>) -> Map {
+    let outer_face = dcel.outer_face;
+    let mut dcel = dcel.map_face_data(|_| Some(Value::Null));
+    *dcel.face_data_mut(outer_face) = None;
+    return dcel;
+}
+
+fn save(dcel: &Map, path: &str) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut writer = BufWriter::new(file);
+    let result = match extension(path).as_str() {
+        "obj" => obj::write(dcel, &mut writer),
+        "off" => off::write(dcel, &mut writer),
+        "geojson" | "json" => geojson::write(dcel, |data| data.clone().map(Some), &mut writer),
+        "wkt" => wkt::write(dcel, Option::is_some, &mut writer),
+        "dcel" => text::write(dcel, &mut writer),
+        "dcelb" => dcel.write_to(&mut writer),
+        "svg" => writer.write_all(dcel.to_svg(&SvgOptions::default()).as_bytes()).map_err(From::from),
+        "dot" => writer.write_all(dcel.to_dot().as_bytes()).map_err(From::from),
+        other => return Err(format!("{}: unknown output format `{}`", path, other)),
+    };
+    return result.and_then(|_| writer.flush().map_err(From::from)).map_err(|e| format!("{}: {}", path, e));
+}
 
-    dcel.split_edge_in_half(e12);
-    println!("{:?}", dcel.get_cycle_from(e12));
-    println!("{:?}", dcel.get_cycle_from(e13));
+/// Writes `text` to `path`, or to standard output if there is none.
+fn output(text: &str, path: Option<&String>) -> Result<(), String> {
+    match path {
+        Some(path) => {
+            let mut file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+            return file.write_all(text.as_bytes()).map_err(|e| format!("{}: {}", path, e));
+        }
+        None => return io::stdout().write_all(text.as_bytes()).map_err(|e| e.to_string()),
+    }
+}
+
+/// Returns the faces that came from the input, leaving out the outer face
+/// and gaps such as the insides of holes.
+fn input_faces(dcel: &Map) -> Vec<FaceId> {
+    return dcel.faces.iter().filter(|&(_, face)| face.data().is_some()).map(|(f, _)| f).collect();
+}
+
+fn stats(dcel: &Map) -> String {
+    let v = dcel.vertices.len() as i64;
+    let e = dcel.edges.len() as i64 / 2;
+    let f = dcel.faces.len() as i64;
+    let faces = input_faces(dcel);
+    let holes: usize = faces.iter().map(|&f| dcel.faces[f].inner_components().len()).sum();
+    let area: f64 = faces.iter().map(|&f| dcel.face_area(f)).sum();
+    return format!("vertices: {}\nedges: {}\nfaces: {} (leaving out the outer face and gaps)\nholes: {}\n\
+                    euler characteristic: {}\narea: {}\n",
+                   v, e, faces.len(), holes, v - e + f, area);
+}
+
+/// Runs a command, returning its exit code.
+fn run(args: Args) -> Result<i32, String> {
+    match args.command.as_str() {
+        "validate" => {
+            args.check_flags(&[])?;
+            let path = &args.files(1, 1)?[0];
+            // Readers refuse inconsistent input, so report that as a violation
+            let dcel = match load(path) {
+                Ok(dcel) => dcel,
+                Err(message) => {
+                    println!("{}", message);
+                    return Ok(1);
+                }
+            };
+            let errors = dcel.validate_all();
+            if errors.is_empty() {
+                println!("{}: ok", path);
+                return Ok(0);
+            }
+            for err in &errors {
+                println!("{}: {}", path, err);
+            }
+            return Ok(1);
+        }
+        "convert" => {
+            args.check_flags(&[])?;
+            let files = args.files(2, 2)?;
+            save(&load(&files[0])?, &files[1])?;
+        }
+        "stats" => {
+            args.check_flags(&[])?;
+            let dcel = load(&args.files(1, 1)?[0])?;
+            output(&stats(&dcel), None)?;
+        }
+        "render" => {
+            args.check_flags(&["svg", "labels", "half-edges"])?;
+            if !args.flag("svg") {
+                return Err("`render` needs an output kind, such as `--svg`".to_string());
+            }
+            let files = args.files(1, 2)?;
+            let options = SvgOptions {
+                label_ids: args.flag("labels"),
+                half_edges: args.flag("half-edges"),
+                ..SvgOptions::default()
+            };
+            output(&load(&files[0])?.to_svg(&options), files.get(1))?;
+        }
+        "triangulate" => {
+            args.check_flags(&["delaunay"])?;
+            let files = args.files(2, 2)?;
+            let mut dcel = load(&files[0])?;
+            let diagonals = dcel.triangulate_all().map_err(|e| format!("{}: {}", files[0], e))?;
+            if args.flag("delaunay") {
+                dcel.flip_to_delaunay(&diagonals);
+            }
+            save(&dcel, &files[1])?;
+        }
+        "help" | "--help" | "-h" => println!("{}", USAGE),
+        other => return Err(format!("unknown command `{}`", other)),
+    }
+    return Ok(0);
 }
 
 pub fn main() {
-    edge_split();
-}
\ No newline at end of file
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("dcel: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+    match run(args) {
+        Ok(code) => process::exit(code),
+        Err(message) => {
+            eprintln!("dcel: {}", message);
+            process::exit(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Args {
+        return Args::parse(line.split_whitespace().map(String::from)).unwrap();
+    }
+
+    #[test]
+    fn parses_arguments() {
+        let parsed = args("render --svg in.obj out.svg --labels");
+        assert_eq!(parsed.command, "render");
+        assert_eq!(parsed.positional, vec!["in.obj", "out.svg"]);
+        assert!(parsed.flag("svg") && parsed.flag("labels") && !parsed.flag("half-edges"));
+        assert!(parsed.check_flags(&["svg", "labels"]).is_ok());
+        assert!(parsed.check_flags(&["svg"]).is_err());
+        assert!(parsed.files(1, 1).is_err());
+        assert!(Args::parse(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn reports_stats() {
+        let text = r#"{"type": "Polygon", "coordinates": [[[0, 0], [4, 0], [4, 4], [0, 4], [0, 0]],
+                                                       [[1, 1], [1, 2], [2, 2], [2, 1], [1, 1]]]}"#;
+        let dcel: Map = geojson::read(text.as_bytes()).unwrap();
+        let report = stats(&dcel);
+        assert!(report.contains("vertices: 8\nedges: 8\nfaces: 1 (leaving out the outer face and gaps)\nholes: 1\n"));
+        assert!(report.contains("euler characteristic: 3\narea: 15\n"));
+
+        // Mesh faces carry no properties but still count
+        let mesh: Map = unlabelled(obj::read("v 0 0 0\nv 2 0 0\nv 0 2 0\nf 1 2 3\n".as_bytes()).unwrap());
+        assert!(stats(&mesh).contains("faces: 1 (leaving out the outer face and gaps)\nholes: 0\n"));
+        assert!(stats(&mesh).contains("area: 2\n"));
+        assert_eq!(extension("maps/Parcels.GeoJSON"), "geojson");
+    }
+}
@@ -0,0 +1,799 @@
+//! Construction of DCELs from polygon soup: a list of positions and a list
+//! of polygons given as indices into it.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
+use std::error::Error;
+use std::fmt;
+
+use dcel::*;
+use geometry::*;
+use scalar::Scalar;
+use weld::cluster_points;
+
+/// The half-edge running between each ordered pair of point indices.
+pub(crate) type HalfEdgeMap = HashMap<(usize, usize), HalfEdgeId>;
+
+/// Reasons a polygon soup can't be turned into a DCEL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// A polygon refers to a position that doesn't exist.
+    IndexOutOfRange { polygon: usize, index: usize },
+    /// A polygon has fewer than three distinct corners, or repeats a corner.
+    DegeneratePolygon(usize),
+    /// The directed edge `from -> to` is used by more than one polygon, so the
+    /// edge is shared by more than two polygons or they disagree on orientation.
+    NonManifoldEdge { from: usize, to: usize },
+    /// More than one stretch of boundary passes through the vertex.
+    NonManifoldVertex(usize),
+    /// The boundaries of two polygons (or of one polygon, twice) cross.
+    CrossingRings(usize, usize),
+    /// The ring given to `add_polygon` has fewer than three distinct corners,
+    /// repeats a corner, or has no area.
+    DegenerateRing,
+    /// Sides `i` and `j` of the ring given to `add_polygon` cross, where side
+    /// `i` runs from corner `i` to corner `i + 1`.
+    SelfCrossing(usize, usize),
+    /// The polygon overlaps or improperly touches the faces already in the
+    /// DCEL it's being added to.
+    Occupied,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BuildError::IndexOutOfRange { polygon, index } => {
+                write!(f, "polygon {} refers to missing vertex {}", polygon, index)
+            }
+            BuildError::DegeneratePolygon(polygon) => write!(f, "polygon {} is degenerate", polygon),
+            BuildError::NonManifoldEdge { from, to } => write!(
+                f,
+                "edge {} -> {} is used twice (non-manifold or inconsistently oriented)",
+                from, to
+            ),
+            BuildError::NonManifoldVertex(v) => write!(f, "vertex {} is non-manifold", v),
+            BuildError::CrossingRings(a, b) => write!(f, "boundaries of polygons {} and {} cross", a, b),
+            BuildError::DegenerateRing => write!(f, "ring is degenerate"),
+            BuildError::SelfCrossing(i, j) => write!(f, "sides {} and {} of the ring cross", i, j),
+            BuildError::Occupied => write!(f, "polygon overlaps existing faces"),
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+impl<P: Position> DCEL<P> {
+    /// Builds a DCEL from polygon soup. Polygons must be consistently oriented
+    /// (counter-clockwise for planar subdivisions). Vertex `i` of the result is
+    /// `points[i]`, and polygon `i` becomes the face after the outer face.
+    /// Edges without a matching twin get one on the outer face.
+    pub fn from_polygons<Q: AsRef<[usize]>>(points: &[P], polygons: &[Q]) -> Result<DCEL<P>, BuildError> {
+        // Validate everything before touching the DCEL
+        let mut by_ends: HashSet<(usize, usize)> = HashSet::new();
+        for (p, polygon) in polygons.iter().enumerate() {
+            let polygon = polygon.as_ref();
+            if polygon.len() < 3 {
+                return Err(BuildError::DegeneratePolygon(p));
+            }
+            for (i, &from) in polygon.iter().enumerate() {
+                if from >= points.len() {
+                    return Err(BuildError::IndexOutOfRange { polygon: p, index: from });
+                } else if polygon[..i].contains(&from) {
+                    return Err(BuildError::DegeneratePolygon(p));
+                }
+                let to = polygon[(i + 1) % polygon.len()];
+                if !by_ends.insert((from, to)) {
+                    return Err(BuildError::NonManifoldEdge { from: from, to: to });
+                }
+            }
+        }
+
+        // Boundary half-edges run opposite to unmatched polygon edges, and
+        // each vertex may start at most one of them.
+        let mut boundary_from: HashMap<usize, usize> = HashMap::new();
+        for &(from, to) in &by_ends {
+            if !by_ends.contains(&(to, from)) && boundary_from.insert(to, from).is_some() {
+                return Err(BuildError::NonManifoldVertex(to));
+            }
+        }
+
+        let mut dcel = DCEL::new();
+        let verts: Vec<_> = points.iter().map(|p| dcel.add_vertex(*p)).collect();
+        let mut edge_ids: HashMap<(usize, usize), HalfEdgeId> = HashMap::new();
+
+        for polygon in polygons {
+            let polygon = polygon.as_ref();
+            let face = dcel.add_face();
+            let edges: Vec<_> = polygon.iter().map(|&i| dcel.add_half_edge(verts[i], face)).collect();
+            for i in 0..edges.len() {
+                dcel.make_next(edges[i], edges[(i + 1) % edges.len()]);
+                edge_ids.insert((polygon[i], polygon[(i + 1) % polygon.len()]), edges[i]);
+            }
+            dcel.faces[face].set_incident_edge(Some(edges[0]));
+        }
+
+        // Visit edges in a fixed order so ids don't depend on hashing
+        let mut ends: Vec<_> = by_ends.iter().cloned().collect();
+        ends.sort();
+        let outer_face = dcel.outer_face;
+        for &(from, to) in &ends {
+            let edge = edge_ids[&(from, to)];
+            match edge_ids.get(&(to, from)) {
+                Some(&twin) => dcel.make_twins(edge, twin),
+                None => {
+                    let twin = dcel.add_half_edge(verts[to], outer_face);
+                    dcel.make_twins(edge, twin);
+                    edge_ids.insert((to, from), twin);
+                }
+            }
+        }
+        for (&to, &from) in &boundary_from {
+            let edge = edge_ids[&(to, from)];
+            let next_to = boundary_from[&from];
+            dcel.make_next(edge, edge_ids[&(from, next_to)]);
+        }
+
+        // The outer face has no outer boundary; each boundary loop is a hole in it
+        dcel.faces[outer_face].set_incident_edge(None);
+        let mut traced = HashSet::new();
+        for &(from, to) in &ends {
+            if boundary_from.get(&to) == Some(&from) && !traced.contains(&edge_ids[&(to, from)]) {
+                let start = edge_ids[&(to, from)];
+                traced.extend(dcel.get_cycle_from(start));
+                dcel.add_inner_component(outer_face, start);
+            }
+        }
+
+        // Prefer inner edges as the vertices' outgoing edges
+        for &(from, to) in ends.iter().rev() {
+            dcel.vertices[verts[from]].set_outgoing_edge(edge_ids[&(from, to)]);
+        }
+
+        return Ok(dcel);
+    }
+}
+
+impl<P: Position> DCEL<P> {
+    /// Builds a DCEL from polygon soup like `from_polygons`, first welding
+    /// points closer than `epsilon` to one another into a single vertex, as
+    /// `weld_vertices` does. Vertices keep the position of the first point
+    /// welded into them, in order. Corners welded onto their neighbours are
+    /// dropped, and so are polygons left with fewer than three, so faces
+    /// follow the polygons remaining. Errors give polygons by their index in
+    /// `polygons`, but vertices by their index among the welded vertices.
+    pub fn from_polygons_welded<Q: AsRef<[usize]>>(points: &[P], polygons: &[Q], epsilon: P::Scalar)
+        -> Result<DCEL<P>, BuildError>
+    {
+        let mut index = vec![0; points.len()];
+        let mut welded_points = Vec::new();
+        for (i, root) in cluster_points(points, epsilon).into_iter().enumerate() {
+            if root == i {
+                index[i] = welded_points.len();
+                welded_points.push(points[i]);
+            } else {
+                index[i] = index[root];
+            }
+        }
+
+        let mut kept = Vec::new();
+        let mut rings = Vec::new();
+        for (p, polygon) in polygons.iter().enumerate() {
+            let mut ring: Vec<usize> = Vec::new();
+            for &i in polygon.as_ref() {
+                if i >= points.len() {
+                    return Err(BuildError::IndexOutOfRange { polygon: p, index: i });
+                } else if ring.last() != Some(&index[i]) {
+                    ring.push(index[i]);
+                }
+            }
+            while ring.len() > 1 && ring.first() == ring.last() {
+                ring.pop();
+            }
+            let distinct: HashSet<&usize> = ring.iter().collect();
+            if distinct.len() >= 3 {
+                kept.push(p);
+                rings.push(ring);
+            }
+        }
+        return DCEL::from_polygons(&welded_points, &rings).map_err(|err| match err {
+            BuildError::DegeneratePolygon(p) => BuildError::DegeneratePolygon(kept[p]),
+            err => err,
+        });
+    }
+}
+
+impl<T: Scalar> DCEL<Point2<T>> {
+    /// Builds a DCEL with a single face bounded by the convex hull of
+    /// `points`, whose twin half-edges bound the outer face. Only the hull's
+    /// corners become vertices, counter-clockwise from vertex 0 in the order
+    /// `convex_hull` returns them.
+    pub fn from_convex_hull(points: &[Point2<T>]) -> Result<DCEL<Point2<T>>, BuildError> {
+        let hull = convex_hull(points);
+        if hull.len() < 3 {
+            return Err(BuildError::DegeneratePolygon(0));
+        }
+        let ring: Vec<usize> = (0..hull.len()).collect();
+        return DCEL::from_polygons(&hull, &[ring]);
+    }
+}
+
+impl<T: Scalar, F: Clone> DCEL<Point2<T>, F> {
+    /// Builds a planar subdivision from polygons given as rings of points:
+    /// an exterior ring followed by any number of holes, in either
+    /// orientation and with or without the closing point repeated.
+    ///
+    /// Coincident points become one vertex, so edges shared by neighbouring
+    /// polygons become twins, including where a vertex of one polygon lies
+    /// in the middle of an edge of another. Holes become inner components.
+    /// Each face's payload is made by `face_data` from the index of the
+    /// polygon it came from, or `None` for the outer face and for any gaps
+    /// enclosed by the polygons.
+    pub fn from_polygon_rings<D>(polygons: &[Vec<Vec<Point2<T>>>], mut face_data: D) -> Result<DCEL<Point2<T>, F>, BuildError>
+        where D: FnMut(Option<usize>) -> F
+    {
+        // Merge coincident points, numbering vertices in order of appearance
+        let mut points: Vec<Point2<T>> = Vec::new();
+        let mut rings: Vec<(usize, Vec<usize>)> = Vec::new();
+        let all: Vec<Point2<T>> = polygons.iter().flat_map(|p| p.iter().flat_map(|r| r.iter().cloned())).collect();
+        let mut order: Vec<usize> = (0..all.len()).collect();
+        order.sort_by(|&a, &b| all[a].partial_cmp(&all[b]).unwrap_or(Ordering::Equal).then(a.cmp(&b)));
+        let mut first_of = vec![0; all.len()];
+        for w in 0..order.len() {
+            first_of[order[w]] = if w > 0 && all[order[w - 1]] == all[order[w]] { first_of[order[w - 1]] } else { order[w] };
+        }
+        let mut vertex_of: HashMap<usize, usize> = HashMap::new();
+        let mut index = 0;
+        for (p, polygon) in polygons.iter().enumerate() {
+            for (r, ring) in polygon.iter().enumerate() {
+                let mut ids: Vec<usize> = Vec::new();
+                for _ in ring {
+                    let next_id = vertex_of.len();
+                    let v = *vertex_of.entry(first_of[index]).or_insert(next_id);
+                    if v == points.len() {
+                        points.push(all[index]);
+                    }
+                    if ids.last() != Some(&v) {
+                        ids.push(v);
+                    }
+                    index += 1;
+                }
+                if ids.len() > 1 && ids.first() == ids.last() {
+                    ids.pop();
+                }
+                let area = ring_area2(&points, &ids);
+                if ids.len() < 3 || area == T::zero() {
+                    return Err(BuildError::DegeneratePolygon(p));
+                }
+                // Exteriors run counter-clockwise and holes clockwise, so
+                // the polygon is always on the left
+                if (r == 0) != (area > T::zero()) {
+                    ids.reverse();
+                }
+                rings.push((p, ids));
+            }
+        }
+
+        // Split edges at any vertex lying inside them, looking only at the
+        // vertices within their span in x, and record which polygon lies to
+        // the left of each directed edge
+        let mut by_x: Vec<usize> = (0..points.len()).collect();
+        by_x.sort_by(|&u, &v| points[u].x.partial_cmp(&points[v].x).unwrap_or(Ordering::Equal));
+        let mut left: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        let mut source: HashMap<(usize, usize), usize> = HashMap::new();
+        for &(p, ref ids) in &rings {
+            for i in 0..ids.len() {
+                let (a, b) = (ids[i], ids[(i + 1) % ids.len()]);
+                let segment = Segment2::new(points[a], points[b]);
+                let bbox = segment.bbox();
+                let first = by_x.partition_point(|&v| points[v].x < bbox.min.x);
+                let mut chain: Vec<usize> = by_x[first..].iter().cloned()
+                    .take_while(|&v| points[v].x <= bbox.max.x)
+                    .filter(|&v| v != a && v != b && bbox.contains(&points[v]) && segment.contains(&points[v]))
+                    .collect();
+                chain.sort_by(|&u, &v| points[a].dist_sq(&points[u]).partial_cmp(&points[a].dist_sq(&points[v])).unwrap_or(Ordering::Equal));
+                chain.insert(0, a);
+                chain.push(b);
+                for w in chain.windows(2) {
+                    if left.insert((w[0], w[1]), p).is_some() {
+                        return Err(BuildError::NonManifoldEdge { from: w[0], to: w[1] });
+                    }
+                    let key = (w[0].min(w[1]), w[0].max(w[1]));
+                    if let Entry::Vacant(entry) = source.entry(key) {
+                        entry.insert(p);
+                        edges.push(key);
+                    }
+                }
+            }
+        }
+
+        // Look for crossings, testing only pairs whose boxes overlap
+        let segments: Vec<Segment2<T>> = edges.iter().map(|&(a, b)| Segment2::new(points[a], points[b])).collect();
+        let boxes: Vec<Bbox2<T>> = segments.iter().map(|s| s.bbox()).collect();
+        let mut order: Vec<usize> = (0..edges.len()).collect();
+        order.sort_by(|&i, &j| boxes[i].min.x.partial_cmp(&boxes[j].min.x).unwrap_or(Ordering::Equal));
+        for (k, &i) in order.iter().enumerate() {
+            for &j in &order[k + 1..] {
+                if boxes[j].min.x > boxes[i].max.x {
+                    break;
+                }
+                if !boxes[i].intersects(&boxes[j]) {
+                    continue;
+                }
+                if let Intersection::Proper { .. } = segment_intersection(&segments[i], &segments[j]) {
+                    let (p, q) = (source[&edges[i]], source[&edges[j]]);
+                    return Err(BuildError::CrossingRings(p.min(q), p.max(q)));
+                }
+            }
+        }
+
+        let label = |key: (usize, usize)| {
+            let p = left.get(&key).cloned();
+            (p.is_none(), p)
+        };
+        let (dcel, _) = DCEL::from_edges(&points, &edges, face_data(None), label, |(_, p)| face_data(p));
+        return Ok(dcel);
+    }
+
+    /// Builds a planar subdivision from straight edges between `points`,
+    /// which may only meet at their ends. Vertex `i` is `points[i]`. Each
+    /// counter-clockwise cycle bounds a face, whose payload `face_data` makes
+    /// from the `label` of one of its directed edges; faces are numbered in
+    /// order of their labels. Every other cycle is a hole in the smallest
+    /// face around it. Also returns the half-edge for each directed edge.
+    pub(crate) fn from_edges<K, L, D>(points: &[Point2<T>], edges: &[(usize, usize)], outer: F, mut label: L,
+                                      mut face_data: D) -> (DCEL<Point2<T>, F>, HalfEdgeMap)
+        where K: Ord + Copy, L: FnMut((usize, usize)) -> K, D: FnMut(K) -> F
+    {
+        // Create both halves of every edge on the outer face for now
+        let mut dcel = DCEL::with_outer_face(outer);
+        let outer_face = dcel.outer_face;
+        let verts: Vec<_> = points.iter().map(|p| dcel.add_vertex(*p)).collect();
+        let mut outgoing: Vec<Vec<(HalfEdgeId, usize)>> = vec![Vec::new(); points.len()];
+        let mut half_edges: HashMap<(usize, usize), HalfEdgeId> = HashMap::new();
+        for &(a, b) in edges {
+            let ab = dcel.add_half_edge(verts[a], outer_face);
+            let ba = dcel.add_half_edge(verts[b], outer_face);
+            dcel.make_twins(ab, ba);
+            outgoing[a].push((ab, b));
+            outgoing[b].push((ba, a));
+            half_edges.insert((a, b), ab);
+            half_edges.insert((b, a), ba);
+        }
+        dcel.faces[outer_face].set_incident_edge(None);
+
+        // Around each vertex, an incoming edge continues with the outgoing
+        // edge just clockwise of its twin, keeping faces on the left
+        for (v, out) in outgoing.iter_mut().enumerate() {
+            out.sort_by(|&(_, a), &(_, b)| angle_order(points[a] - points[v], points[b] - points[v]));
+            for i in 0..out.len() {
+                let incoming = half_edges[&(out[i].1, v)];
+                let next = out[(i + out.len() - 1) % out.len()].0;
+                dcel.make_next(incoming, next);
+            }
+        }
+
+        // Counter-clockwise cycles bound faces; the rest are holes
+        let mut seen = HashSet::new();
+        let mut bounded: Vec<(K, HalfEdgeId, T)> = Vec::new();
+        let mut holes: Vec<HalfEdgeId> = Vec::new();
+        for &(a, b) in edges {
+            for &key in &[(a, b), (b, a)] {
+                let start = half_edges[&key];
+                if seen.contains(&start) {
+                    continue;
+                }
+                seen.extend(dcel.get_cycle_from(start));
+                let area = dcel.cycle_area2(start);
+                if area > T::zero() {
+                    bounded.push((label(key), start, area));
+                } else {
+                    holes.push(start);
+                }
+            }
+        }
+        bounded.sort_by_key(|&(k, _, _)| k);
+
+        let mut faces = Vec::new();
+        for &(k, start, _) in &bounded {
+            let face = dcel.add_face_with(face_data(k));
+            dcel.faces[face].set_incident_edge(Some(start));
+            for e in dcel.get_cycle_from(start) {
+                dcel.edges[e].set_face(face);
+            }
+            faces.push(face);
+        }
+
+        // Each hole belongs to the smallest face whose boundary encloses it
+        for hole in holes {
+            let mut owner = outer_face;
+            let mut owner_area = None;
+            for (i, &(_, start, area)) in bounded.iter().enumerate() {
+                if owner_area.is_some_and(|best| area >= best) || !dcel.cycle_encloses(start, hole) {
+                    continue;
+                }
+                owner = faces[i];
+                owner_area = Some(area);
+            }
+            for e in dcel.get_cycle_from(hole) {
+                dcel.edges[e].set_face(owner);
+            }
+            dcel.add_inner_component(owner, hole);
+        }
+
+        return (dcel, half_edges);
+    }
+
+    /// Determines if the cycle through `inner` lies inside the cycle through
+    /// `outer`, judging by the first of its vertices not on `outer`.
+    pub(crate) fn cycle_encloses(&self, outer: HalfEdgeId, inner: HalfEdgeId) -> bool {
+        for e in self.cycle_edges(inner) {
+            let p = self.vertices[self.edges[e].origin()].coord();
+            let mut on_boundary = false;
+            let winding = self.winding_number(outer, &p, &mut on_boundary);
+            if !on_boundary {
+                return winding != 0;
+            }
+        }
+        return false;
+    }
+
+    /// Adds a simple polygon carrying `data` as a new face, given as a ring
+    /// of points in either orientation. Corners at the position of an
+    /// existing vertex reuse it, and sides along an existing edge of the
+    /// outer face take that edge over, but the polygon may not otherwise
+    /// touch or overlap the faces already there. On error the DCEL is left
+    /// untouched.
+    pub fn add_polygon(&mut self, points: &[Point2<T>], data: F) -> Result<FaceId, BuildError> {
+        let n = points.len();
+        let mut ring = points.to_vec();
+        let area = (0..n).fold(T::zero(), |area, i| {
+            let (a, b) = (ring[i], ring[(i + 1) % n]);
+            area + (a.x * b.y - b.x * a.y)
+        });
+        let repeated = (0..n).any(|i| ring[i + 1..].contains(&ring[i]));
+        if n < 3 || repeated || area == T::zero() {
+            return Err(BuildError::DegenerateRing);
+        } else if area < T::zero() {
+            ring.reverse();
+        }
+        let side = |i: usize| Segment2::new(ring[i], ring[(i + 1) % n]);
+        // Side `i` of a reversed ring is side `n - 2 - i` of the input
+        let input_side = |i: usize| if area < T::zero() { (2 * n - 2 - i) % n } else { i };
+        for i in 0..n {
+            for j in i + 1..n {
+                let adjacent = j == i + 1 || (i == 0 && j == n - 1);
+                match segment_intersection(&side(i), &side(j)) {
+                    Intersection::None => {}
+                    Intersection::Touching { .. } if adjacent => {}
+                    _ => {
+                        let (a, b) = (input_side(i), input_side(j));
+                        return Err(BuildError::SelfCrossing(a.min(b), a.max(b)));
+                    }
+                }
+            }
+        }
+
+        // Match corners to vertices and sides to edges
+        let outer_face = self.outer_face;
+        let corners: Vec<Option<VertexId>> = ring.iter()
+            .map(|p| self.vertices.iter().find(|&(_, v)| v.coord() == *p).map(|(v, _)| v))
+            .collect();
+        let existing: Vec<Option<HalfEdgeId>> = (0..n)
+            .map(|i| match (corners[i], corners[(i + 1) % n]) {
+                (Some(u), Some(v)) => self.vertex_edges(u).find(|&e| self.get_destination(e) == v),
+                _ => None,
+            })
+            .collect();
+
+        // Edges may only meet the new sides at shared corners, and nothing
+        // may lie inside the polygon
+        for (e, edge) in self.edges.iter() {
+            let twin = edge.twin().unwrap();
+            if e > twin || existing.contains(&Some(e)) || existing.contains(&Some(twin)) {
+                continue;
+            }
+            let (a, b) = (self.vertices[edge.origin()].coord(), self.vertices[self.edges[twin].origin()].coord());
+            for i in 0..n {
+                let s = side(i);
+                match segment_intersection(&s, &Segment2::new(a, b)) {
+                    Intersection::None => {}
+                    Intersection::Touching { point, .. }
+                        if (point == s.start || point == s.end) && (point == a || point == b) => {}
+                    _ => return Err(BuildError::Occupied),
+                }
+            }
+        }
+        for i in 0..n {
+            let face = match (existing[i], corners[i], corners[(i + 1) % n]) {
+                (Some(e), _, _) => self.edges[e].face(),
+                (None, Some(u), _) => self.face_towards(u, ring[(i + 1) % n] - ring[i]),
+                (None, None, _) => self.locate(&ring[i]),
+            };
+            if face != outer_face {
+                return Err(BuildError::Occupied);
+            }
+        }
+        for (v, vertex) in self.vertices.iter() {
+            if !corners.contains(&Some(v)) && ring_winding(&ring, &vertex.coord()) != 0 {
+                return Err(BuildError::Occupied);
+            }
+        }
+
+        // Note each corner's edges before relinking any of them
+        let mut outgoing: Vec<Vec<HalfEdgeId>> = corners.iter()
+            .map(|c| c.map_or(Vec::new(), |v| self.vertex_edges(v).collect()))
+            .collect();
+        let verts: Vec<VertexId> = corners.iter().zip(&ring)
+            .map(|(&c, &p)| c.unwrap_or_else(|| self.add_vertex(p)))
+            .collect();
+        let face = self.add_face_with(data);
+        let mut sides = Vec::with_capacity(n);
+        for i in 0..n {
+            let e = match existing[i] {
+                Some(e) => e,
+                None => {
+                    let e = self.add_half_edge(verts[i], face);
+                    let twin = self.add_half_edge(verts[(i + 1) % n], outer_face);
+                    self.make_twins(e, twin);
+                    outgoing[i].push(e);
+                    outgoing[(i + 1) % n].push(twin);
+                    e
+                }
+            };
+            self.edges[e].set_face(face);
+            sides.push(e);
+        }
+        self.faces[outer_face].set_incident_edge(None);
+        self.faces[face].set_incident_edge(Some(sides[0]));
+
+        // Around each corner, an incoming edge continues with the outgoing
+        // edge just clockwise of its twin, as in `from_edges`
+        for (i, out) in outgoing.iter_mut().enumerate() {
+            let p = ring[i];
+            let dest = |e: HalfEdgeId| self.vertices[self.get_destination(e)].coord() - p;
+            out.sort_by(|&a, &b| angle_order(dest(a), dest(b)));
+            for j in 0..out.len() {
+                let incoming = self.edges[out[j]].twin().unwrap();
+                self.make_next(incoming, out[(j + out.len() - 1) % out.len()]);
+            }
+        }
+
+        // The outer face's boundaries may have joined or split
+        self.find_outer_components();
+        return Ok(face);
+    }
+}
+
+impl<T: Scalar, F: Clone> DCEL<Point2<T>, F> {
+    /// Returns the face entered by leaving `v` in direction `d`, which
+    /// shouldn't run along any of its edges.
+    fn face_towards(&self, v: VertexId, d: Vector2<T>) -> FaceId {
+        let p = self.vertices[v].coord();
+        let mut out: Vec<HalfEdgeId> = self.vertex_edges(v).collect();
+        if out.is_empty() {
+            return self.locate(&p);
+        }
+        let dest = |e: HalfEdgeId| self.vertices[self.get_destination(e)].coord() - p;
+        out.sort_by(|&a, &b| angle_order(dest(a), dest(b)));
+        // The face to the left of the last edge clockwise of `d`
+        let before = out.iter().rev().find(|&&e| angle_order(dest(e), d) == Ordering::Less);
+        return self.edges[*before.unwrap_or(&out[out.len() - 1])].face();
+    }
+}
+
+/// Returns the winding number of a ring of points around `p`, which
+/// shouldn't lie on the ring.
+fn ring_winding<T: Scalar>(ring: &[Point2<T>], p: &Point2<T>) -> i32 {
+    let mut winding = 0;
+    for i in 0..ring.len() {
+        let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+        if a.y <= p.y {
+            if b.y > p.y && is_lht(&a, &b, p) {
+                winding += 1;
+            }
+        } else if b.y <= p.y && is_rht(&a, &b, p) {
+            winding -= 1;
+        }
+    }
+    return winding;
+}
+
+/// Returns twice the signed area of a ring of vertex indices.
+fn ring_area2<T: Scalar>(points: &[Point2<T>], ring: &[usize]) -> T {
+    let mut area = T::zero();
+    for i in 0..ring.len() {
+        let a = points[ring[i]];
+        let b = points[ring[(i + 1) % ring.len()]];
+        area = area + (a.x * b.y - b.x * a.y);
+    }
+    return area;
+}
+
+/// Orders directions counter-clockwise by angle, starting from the positive
+/// x axis, without computing any angles.
+pub(crate) fn angle_order<T: Scalar>(a: Vector2<T>, b: Vector2<T>) -> Ordering {
+    let lower = |d: &Vector2<T>| d.y < T::zero() || (d.y == T::zero() && d.x < T::zero());
+    match (lower(&a), lower(&b)) {
+        (false, true) => return Ordering::Less,
+        (true, false) => return Ordering::Greater,
+        _ => {}
+    }
+    let cross = a.cross(&b);
+    if cross > T::zero() {
+        return Ordering::Less;
+    } else if cross < T::zero() {
+        return Ordering::Greater;
+    }
+    return Ordering::Equal;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geometry::Point2;
+
+    fn square_points() -> Vec<Point2> {
+        return vec![
+            Point2::new(0.0, 0.0),
+            Point2::new(1.0, 0.0),
+            Point2::new(1.0, 1.0),
+            Point2::new(0.0, 1.0),
+        ];
+    }
+
+    #[test]
+    fn builds_shared_edges() {
+        let dcel = DCEL::from_polygons(&square_points(), &[[0, 1, 2], [0, 2, 3]]).unwrap();
+        assert_eq!(dcel.faces.len(), 3);
+        assert_eq!(dcel.edges.len(), 10);
+
+        let outer = dcel.face_cycles(dcel.outer_face);
+        assert_eq!(outer.len(), 1);
+        assert_eq!(dcel.get_cycle_from(outer[0]).len(), 4);
+        for (v, _) in dcel.vertices.iter() {
+            assert_ne!(dcel.edges[dcel.vertices[v].outgoing_edge().unwrap()].face(), dcel.outer_face);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_soup() {
+        let points = square_points();
+        assert_eq!(DCEL::from_polygons(&points, &[vec![0, 1, 2], vec![0, 1, 3]]).unwrap_err(),
+                   BuildError::NonManifoldEdge { from: 0, to: 1 });
+        assert_eq!(DCEL::from_polygons(&points, &[vec![0, 1]]).unwrap_err(),
+                   BuildError::DegeneratePolygon(0));
+        assert_eq!(DCEL::from_polygons(&points, &[vec![0, 1, 7]]).unwrap_err(),
+                   BuildError::IndexOutOfRange { polygon: 0, index: 7 });
+
+        // Two triangles touching at a single corner
+        let mut bowtie = points.clone();
+        bowtie.push(Point2::new(2.0, 0.0));
+        bowtie.push(Point2::new(2.0, 1.0));
+        assert_eq!(DCEL::from_polygons(&bowtie, &[vec![0, 1, 2], vec![2, 4, 5]]).unwrap_err(),
+                   BuildError::NonManifoldVertex(2));
+    }
+
+    #[test]
+    fn welds_soup_first() {
+        let mut points = square_points();
+        points.push(Point2::new(1.0 + 1e-9, 1.0));
+        points.push(Point2::new(1e-9, 0.0));
+        let dcel = DCEL::from_polygons_welded(&points, &[&[0, 1, 4][..], &[5, 2, 3], &[0, 5, 2, 4]], 1e-6).unwrap();
+        dcel.validate().unwrap();
+        assert_eq!((dcel.vertices.len(), dcel.faces.len(), dcel.edges.len()), (4, 3, 10));
+        assert_eq!(dcel.vertices[VertexId::from(2)].coord(), Point2::new(1.0, 1.0));
+        assert_eq!(DCEL::from_polygons_welded(&points, &[&[0, 5, 2][..], &[1, 4, 3, 1, 0]], 1e-6).unwrap_err(),
+                   BuildError::DegeneratePolygon(1));
+    }
+
+    #[test]
+    fn builds_hull_face() {
+        let mut points = square_points();
+        points.push(Point2::new(0.5, 0.5));
+        points.push(Point2::new(1.0, 0.5));
+        let dcel = DCEL::from_convex_hull(&points).unwrap();
+        dcel.validate().unwrap();
+        assert_eq!(dcel.vertices.len(), 4);
+        assert_eq!(dcel.faces.len(), 2);
+        let hull = FaceId::from(1);
+        assert_eq!(dcel.face_area(hull), 1.0);
+        assert_eq!(dcel.face_area(dcel.outer_face), -1.0);
+        for e in dcel.face_edges(hull) {
+            assert_eq!(dcel.edges[dcel.edges[e].twin().unwrap()].face(), dcel.outer_face);
+        }
+        assert_eq!(DCEL::from_convex_hull(&points[..2]).unwrap_err(), BuildError::DegeneratePolygon(0));
+    }
+
+    #[test]
+    fn builds_from_rings() {
+        let square = |x: f64, y: f64, size: f64| vec![
+            Point2::new(x, y), Point2::new(x + size, y), Point2::new(x + size, y + size), Point2::new(x, y + size),
+        ];
+        // A large square with two small ones along its top edge, which meets
+        // their corners in the middle, given clockwise
+        let mut top = square(0.0, 2.0, 2.0);
+        top.reverse();
+        let polygons = vec![vec![top], vec![square(0.0, 1.0, 1.0)], vec![square(1.0, 1.0, 1.0)]];
+        let dcel = DCEL::from_polygon_rings(&polygons, |p| p).unwrap();
+        assert_eq!(dcel.vertices.len(), 8);
+        assert_eq!(dcel.edges.len(), 20);
+        assert_eq!(dcel.faces.len(), 4);
+
+        let big = dcel.locate(&Point2::new(1.0, 3.0));
+        assert_eq!(dcel.face_data(big), &Some(0));
+        assert_eq!(dcel.face_edges(big).count(), 5);
+        assert!(dcel.cycle_area2(dcel.faces[big].incident_edge().unwrap()) > 0.0);
+
+        // A ring of four squares leaves a gap in the middle
+        let ring: Vec<_> = [(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0), (0.0, 2.0), (0.0, 1.0)]
+            .iter().map(|&(x, y)| vec![square(x, y, 1.0)]).collect();
+        let dcel = DCEL::from_polygon_rings(&ring, |p| p).unwrap();
+        let gap = dcel.locate(&Point2::new(1.5, 1.5));
+        assert_eq!(dcel.face_data(gap), &None);
+        assert_ne!(gap, dcel.outer_face);
+        assert_eq!(dcel.faces[dcel.outer_face].inner_components().len(), 1);
+
+        let crossing = vec![vec![square(0.0, 0.0, 2.0)], vec![square(1.0, 1.0, 2.0)]];
+        assert_eq!(DCEL::from_polygon_rings(&crossing, |_| ()).unwrap_err(), BuildError::CrossingRings(0, 1));
+    }
+
+    #[test]
+    fn adds_polygons_one_by_one() {
+        let ring = |corners: &[(i64, i64)]| -> Vec<Point2<i64>> { corners.iter().map(Point2::from).collect() };
+        let mut dcel: DCEL<Point2<i64>, usize> = DCEL::default();
+        // Squares around the middle of a 3x3 grid, leaving it open until last
+        let mut cells = Vec::new();
+        for &(x, y) in &[(0, 0), (1, 0), (2, 0), (2, 1), (2, 2), (1, 2), (0, 2), (0, 1), (1, 1)] {
+            cells.push(ring(&[(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)]));
+        }
+        cells[3].reverse();
+        for (i, cell) in cells.iter().enumerate() {
+            if i == 8 {
+                assert_eq!(dcel.face_cycles(dcel.outer_face).len(), 2);
+            }
+            let face = dcel.add_polygon(cell, i).unwrap();
+            dcel.validate().unwrap();
+            assert_eq!(*dcel.face_data(face), i);
+            assert_eq!(dcel.face_area(face), 1);
+        }
+        assert_eq!(dcel.vertices.len(), 16);
+        assert_eq!(dcel.edges.len(), 48);
+        assert_eq!(dcel.face_cycles(dcel.outer_face).len(), 1);
+        assert_eq!(dcel.face_area(dcel.outer_face), -9);
+
+        // Overlaps of every kind leave the DCEL alone
+        let before = dcel.to_text();
+        for bad in &[ring(&[(1, 1), (2, 1), (2, 2)]), ring(&[(-1, -1), (4, -1), (4, 4), (-1, 4)]),
+                     ring(&[(3, 0), (4, 0), (4, 2), (2, 2)]), ring(&[(3, 1), (4, 1), (4, 2), (3, 2)]).iter()
+                         .map(|p| Point2::new(p.x - 1, p.y)).collect()] {
+            assert_eq!(dcel.add_polygon(bad, 9), Err(BuildError::Occupied));
+        }
+        assert_eq!(dcel.add_polygon(&ring(&[(5, 0), (6, 0), (5, 0), (6, 1)]), 9), Err(BuildError::DegenerateRing));
+        assert_eq!(dcel.add_polygon(&ring(&[(5, 0), (7, 2), (7, 0), (5, 1)]), 9), Err(BuildError::SelfCrossing(0, 2)));
+        assert_eq!(dcel.add_polygon(&ring(&[(5, 1), (7, 0), (7, 2), (5, 0)]), 9), Err(BuildError::SelfCrossing(0, 2)));
+        assert_eq!(dcel.to_text(), before);
+
+        // A corner touching an existing vertex is fine
+        dcel.add_polygon(&ring(&[(3, 3), (4, 3), (4, 4)]), 9).unwrap();
+        dcel.validate().unwrap();
+        assert_eq!(dcel.face_cycles(dcel.outer_face).len(), 1);
+        assert_eq!(dcel.degree(dcel.vertices.iter().find(|&(_, v)| v.coord() == Point2::new(3, 3)).unwrap().0), 4);
+    }
+
+    #[test]
+    fn adds_triangles_sharing_sides() {
+        // The triangles of the `add_faces` tests sketched in `api.rs`
+        let ring = |corners: &[(i64, i64)]| -> Vec<Point2<i64>> { corners.iter().map(Point2::from).collect() };
+        let mut dcel: DCEL<Point2<i64>, usize> = DCEL::default();
+        dcel.add_polygon(&ring(&[(0, 0), (1, 1), (1, 0)]), 0).unwrap();
+        dcel.add_polygon(&ring(&[(0, 0), (1, 1), (0, 1)]), 1).unwrap();
+        assert_eq!((dcel.faces.len(), dcel.edges.len()), (3, 10));
+        assert_eq!(dcel.to_text(), include_str!("../fixtures/two_triangles.dcel"));
+
+        dcel.add_polygon(&ring(&[(0, 1), (0, 2), (1, 1)]), 2).unwrap();
+        assert_eq!((dcel.faces.len(), dcel.edges.len()), (4, 14));
+        assert_eq!(dcel.to_text(), include_str!("../fixtures/three_triangles.dcel"));
+    }
+}
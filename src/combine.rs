@@ -0,0 +1,354 @@
+//! Assembling DCELs from pieces built independently: moving one DCEL into
+//! another, and gluing pieces together along their shared boundaries.
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+use dcel::*;
+use geometry::Position;
+
+/// Maps the ids of one DCEL's elements to those of another's, as returned
+/// by operations that move elements between DCELs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IdMaps {
+    pub vertices: HashMap<VertexId, VertexId>,
+    pub edges: HashMap<HalfEdgeId, HalfEdgeId>,
+    pub faces: HashMap<FaceId, FaceId>,
+}
+
+/// Reasons two boundary chains can't be stitched together. The DCEL is
+/// left untouched when one of these is returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StitchError {
+    /// The chains are empty or differ in length.
+    LengthMismatch(usize, usize),
+    /// The half-edge doesn't exist or isn't on the boundary of the outer face.
+    NotOnBoundary(HalfEdgeId),
+    /// The half-edge doesn't follow on from the one before it in its chain.
+    NotAChain(HalfEdgeId),
+    /// The half-edge is in both chains, or twinned with its partner already.
+    Overlapping(HalfEdgeId),
+    /// The two half-edges to be glued don't have coincident endpoints.
+    NotCoincident(HalfEdgeId, HalfEdgeId),
+}
+
+impl fmt::Display for StitchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StitchError::LengthMismatch(a, b) => write!(f, "chains of {} and {} half-edges can't be stitched", a, b),
+            StitchError::NotOnBoundary(e) => write!(f, "half-edge {:?} isn't on the outer boundary", e),
+            StitchError::NotAChain(e) => write!(f, "half-edge {:?} doesn't continue its chain", e),
+            StitchError::Overlapping(e) => write!(f, "half-edge {:?} is on both sides of the seam", e),
+            StitchError::NotCoincident(a, b) => write!(f, "half-edges {:?} and {:?} don't coincide", a, b),
+        }
+    }
+}
+
+impl Error for StitchError {}
+
+impl<P: Position, F: Clone> DCEL<P, F> {
+    /// Moves every element of `other` into this DCEL, returning the new id
+    /// of each. The boundaries of `other`'s outer face become holes of this
+    /// one's and its payload is dropped; the two should not overlap.
+    pub fn append(&mut self, other: DCEL<P, F>) -> IdMaps {
+        let DCEL { vertices, edges, faces, outer_face: other_outer, .. } = other;
+        let mut maps = IdMaps::default();
+        maps.faces.insert(other_outer, self.outer_face);
+
+        let vertices = vertices.into_items();
+        for &(id, ref vertex) in &vertices {
+            let new = self.add_vertex(vertex.coord());
+            maps.vertices.insert(id, new);
+        }
+        let mut boundaries = Vec::new();
+        for (id, face) in faces.into_items() {
+            boundaries.push((id, face.incident_edge(), face.inner_components().to_vec()));
+            if id != other_outer {
+                let new = self.add_face_with(face.into_data());
+                maps.faces.insert(id, new);
+            }
+        }
+        let edges = edges.into_items();
+        let outer_edge = self.faces[self.outer_face].incident_edge();
+        for &(id, ref edge) in &edges {
+            let new = self.add_half_edge(maps.vertices[&edge.origin()], maps.faces[&edge.face()]);
+            maps.edges.insert(id, new);
+        }
+
+        // Relink everything under its new ids
+        for &(id, ref edge) in &edges {
+            let new = maps.edges[&id];
+            if let Some(twin) = edge.twin() {
+                self.make_twins(new, maps.edges[&twin]);
+            }
+            if let Some(next) = edge.next() {
+                self.make_next(new, maps.edges[&next]);
+            }
+        }
+        for (id, vertex) in vertices {
+            if let Some(edge) = vertex.outgoing_edge() {
+                self.vertices[maps.vertices[&id]].set_outgoing_edge(maps.edges[&edge]);
+            }
+        }
+        for (id, incident, holes) in boundaries {
+            let holes = holes.iter().map(|e| maps.edges[e]);
+            if id == other_outer {
+                for hole in holes {
+                    self.add_inner_component(self.outer_face, hole);
+                }
+                self.faces[self.outer_face].set_incident_edge(outer_edge);
+            } else {
+                let face = &mut self.faces[maps.faces[&id]];
+                face.set_incident_edge(incident.map(|e| maps.edges[&e]));
+                face.set_inner_components(holes.collect());
+            }
+        }
+        return maps;
+    }
+
+    /// Glues two stretches of the outer face's boundary into one seam. Each
+    /// chain lists consecutive half-edges along the boundary, and `b` runs
+    /// back along `a`: the first half-edge of `a` is glued to the last of
+    /// `b`, and so on, each pair having coincident endpoints. The half-edges
+    /// of the chains are removed, their twins become twins of each other,
+    /// and each vertex of `b` is merged into the coincident vertex of `a`.
+    ///
+    /// Gaps enclosed by the stitching stay part of the outer face.
+    pub fn stitch(&mut self, a: &[HalfEdgeId], b: &[HalfEdgeId]) -> Result<(), StitchError> {
+        if a.is_empty() || a.len() != b.len() {
+            return Err(StitchError::LengthMismatch(a.len(), b.len()));
+        }
+        for chain in &[a, b] {
+            for (i, &e) in chain.iter().enumerate() {
+                if !self.edges.contains(e) || self.edges[e].face() != self.outer_face {
+                    return Err(StitchError::NotOnBoundary(e));
+                }
+                if i > 0 && self.edges[chain[i - 1]].next() != Some(e) {
+                    return Err(StitchError::NotAChain(e));
+                }
+            }
+        }
+        let seam: HashSet<HalfEdgeId> = a.iter().chain(b).cloned().collect();
+        if seam.len() < a.len() * 2 {
+            return Err(StitchError::Overlapping(*a.iter().find(|e| b.contains(e)).unwrap()));
+        }
+
+        // Each vertex of `b` is merged into the one of `a` it coincides with
+        let pairs: Vec<(HalfEdgeId, HalfEdgeId)> = a.iter().cloned().zip(b.iter().rev().cloned()).collect();
+        let mut merged: HashMap<VertexId, VertexId> = HashMap::new();
+        for &(x, y) in &pairs {
+            if self.edges[x].twin() == Some(y) {
+                return Err(StitchError::Overlapping(x));
+            }
+            let ends = [(self.edges[x].origin(), self.get_destination(y)),
+                        (self.get_destination(x), self.edges[y].origin())];
+            for &(keep, merge) in &ends {
+                if self.vertices[keep].coord() != self.vertices[merge].coord() {
+                    return Err(StitchError::NotCoincident(x, y));
+                }
+                if keep != merge && *merged.entry(merge).or_insert(keep) != keep {
+                    return Err(StitchError::NotCoincident(x, y));
+                }
+            }
+        }
+        if merged.keys().any(|v| merged.values().any(|w| v == w)) {
+            return Err(StitchError::Overlapping(a[0]));
+        }
+
+        let ends = [(self.edges[a[0]].prev(), self.edges[b[b.len() - 1]].next()),
+                    (self.edges[b[0]].prev(), self.edges[a[a.len() - 1]].next())];
+        for e in self.edges.ids() {
+            if let Some(&keep) = merged.get(&self.edges[e].origin()) {
+                self.edges[e].set_origin(keep);
+            }
+        }
+        for &(x, y) in &pairs {
+            let (tx, ty) = (self.edges[x].twin().unwrap(), self.edges[y].twin().unwrap());
+            self.make_twins(tx, ty);
+            for &t in &[tx, ty] {
+                let origin = self.edges[t].origin();
+                self.vertices[origin].set_outgoing_edge(t);
+            }
+        }
+
+        // Where the seam starts and ends, the boundary runs on from one
+        // piece to the other, unless the seam closes it off there
+        for &(prev, next) in &ends {
+            if let (Some(prev), Some(next)) = (prev, next) {
+                if !seam.contains(&prev) && !seam.contains(&next) {
+                    self.make_next(prev, next);
+                }
+            }
+        }
+        for e in seam {
+            self.delete_edge(e);
+        }
+        for &v in merged.keys() {
+            self.delete_vertex(v);
+        }
+
+        // The outer face's boundaries may have joined, split or vanished
+        self.find_outer_components();
+        return Ok(());
+    }
+}
+
+impl<P: Position, F: Clone> DCEL<P, F> {
+    /// Copies the given faces, with their edges and vertices, into a DCEL of
+    /// their own. Whatever lies outside the selection, including any holes
+    /// in it, becomes the new outer face, which takes the payload of this
+    /// DCEL's. Returns the copy along with the source id of each of its
+    /// elements. The outer face can't be selected and is ignored.
+    pub fn extract(&self, faces: &[FaceId]) -> (DCEL<P, F>, IdMaps) {
+        let selected: HashSet<FaceId> = faces.iter().cloned().filter(|&f| f != self.outer_face).collect();
+        let mut piece = DCEL::with_outer_face(self.faces[self.outer_face].data().clone());
+        let mut maps = IdMaps::default();
+        maps.faces.insert(piece.outer_face, self.outer_face);
+
+        // Every edge of the selection is copied along with its twin
+        let mut copied: Vec<HalfEdgeId> = Vec::new();
+        for (e, edge) in self.edges.iter() {
+            let twin = edge.twin().unwrap();
+            if selected.contains(&edge.face()) || selected.contains(&self.edges[twin].face()) {
+                copied.push(e);
+            }
+        }
+        let mut new_vertices: HashMap<VertexId, VertexId> = HashMap::new();
+        let mut new_faces: HashMap<FaceId, FaceId> = HashMap::new();
+        let mut new_edges: HashMap<HalfEdgeId, HalfEdgeId> = HashMap::new();
+        for &e in &copied {
+            let origin = self.edges[e].origin();
+            new_vertices.entry(origin).or_insert_with(|| piece.add_vertex(self.vertices[origin].coord()));
+        }
+        let mut selected_ids: Vec<FaceId> = selected.iter().cloned().collect();
+        selected_ids.sort();
+        for &f in &selected_ids {
+            new_faces.insert(f, piece.add_face_with(self.faces[f].data().clone()));
+        }
+        for &e in &copied {
+            let face = *new_faces.get(&self.edges[e].face()).unwrap_or(&piece.outer_face);
+            new_edges.insert(e, piece.add_half_edge(new_vertices[&self.edges[e].origin()], face));
+        }
+
+        // Edges outside the selection continue with the next copied edge
+        // around their destination, skipping those that weren't copied
+        for &e in &copied {
+            let twin = self.edges[e].twin().unwrap();
+            piece.make_twins(new_edges[&e], new_edges[&twin]);
+            let mut next = self.edges[e].next().unwrap();
+            while !new_edges.contains_key(&next) {
+                next = self.edges[self.edges[next].twin().unwrap()].next().unwrap();
+            }
+            piece.make_next(new_edges[&e], new_edges[&next]);
+        }
+        for (&v, &new) in &new_vertices {
+            let outgoing = self.vertices[v].outgoing_edge().filter(|e| new_edges.contains_key(e))
+                .unwrap_or_else(|| self.vertex_edges(v).find(|e| new_edges.contains_key(e)).unwrap());
+            piece.vertices[new].set_outgoing_edge(new_edges[&outgoing]);
+        }
+        for (&f, &new) in &new_faces {
+            let face = &self.faces[f];
+            piece.faces[new].set_incident_edge(face.incident_edge().map(|e| new_edges[&e]));
+            piece.faces[new].set_inner_components(face.inner_components().iter().map(|e| new_edges[e]).collect());
+        }
+        piece.find_outer_components();
+
+        maps.vertices = new_vertices.into_iter().map(|(old, new)| (new, old)).collect();
+        maps.edges = new_edges.into_iter().map(|(old, new)| (new, old)).collect();
+        maps.faces.extend(new_faces.into_iter().map(|(old, new)| (new, old)));
+        return (piece, maps);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use compare::topologically_equal;
+    use geometry::Point2;
+
+    fn points(coords: &[(f64, f64)]) -> Vec<Point2> {
+        return coords.iter().map(|&p| Point2::from(p)).collect();
+    }
+
+    /// The chain of outer boundary half-edges on the line `x` bordering
+    /// `face`, in order.
+    fn seam(dcel: &DCEL<Point2, u32>, face: FaceId, x: f64) -> Vec<HalfEdgeId> {
+        let on_seam = |e: HalfEdgeId| dcel.edges[e].face() == dcel.outer_face
+            && dcel.edges[dcel.edges[e].twin().unwrap()].face() == face
+            && dcel.vertices[dcel.edges[e].origin()].coord().x == x
+            && dcel.vertices[dcel.get_destination(e)].coord().x == x;
+        let mut e = dcel.edges.ids().into_iter().find(|&e| on_seam(e) && !on_seam(dcel.edges[e].prev().unwrap())).unwrap();
+        let mut chain = vec![e];
+        while on_seam(dcel.edges[e].next().unwrap()) {
+            e = dcel.edges[e].next().unwrap();
+            chain.push(e);
+        }
+        return chain;
+    }
+
+    #[test]
+    fn appends_and_stitches_tiles() {
+        let left = points(&[(0.0, 0.0), (1.0, 0.0), (1.0, 0.5), (1.0, 1.0), (0.0, 1.0)]);
+        let right = points(&[(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 0.5)]);
+        let tile = |ring: &[Point2], data: u32| DCEL::from_polygon_rings(&[vec![ring.to_vec()]], |p| p.map_or(0, |_| data)).unwrap();
+
+        let mut dcel = tile(&left, 1);
+        let other = tile(&right, 2);
+        let (vertices, edges) = (other.vertices.len(), other.edges.len());
+        let maps = dcel.append(other);
+        dcel.validate().unwrap();
+        assert_eq!((dcel.vertices.len(), dcel.edges.len(), dcel.faces.len()), (10, 20, 3));
+        assert_eq!((maps.vertices.len(), maps.edges.len(), maps.faces.len()), (vertices, edges, 2));
+        assert_eq!(dcel.faces[dcel.outer_face].inner_components().len(), 2);
+        let (f1, f2) = (FaceId::from(1), maps.faces[&FaceId::from(1)]);
+        assert_eq!((*dcel.face_data(f1), *dcel.face_data(f2)), (1, 2));
+
+        let (a, b) = (seam(&dcel, f1, 1.0), seam(&dcel, f2, 1.0));
+        assert_eq!(dcel.stitch(&a[..1], &b), Err(StitchError::LengthMismatch(1, 2)));
+        assert_eq!(dcel.stitch(&a, &[b[1], b[0]]), Err(StitchError::NotAChain(b[0])));
+        assert_eq!(dcel.stitch(&a, &a), Err(StitchError::Overlapping(a[0])));
+        assert_eq!(dcel.stitch(&a, &[b[0]; 2]), Err(StitchError::NotAChain(b[0])));
+        dcel.stitch(&a, &b).unwrap();
+        dcel.validate().unwrap();
+
+        let expected = DCEL::from_polygon_rings(&[vec![left], vec![right]], |p| p.map_or(0, |i| i as u32 + 1)).unwrap();
+        assert!(topologically_equal(&dcel, &expected));
+        assert_eq!(dcel.faces[dcel.outer_face].inner_components().len(), 1);
+        assert_eq!((dcel.vertices.len(), dcel.edges.len()), (7, 16));
+    }
+
+    #[test]
+    fn extracts_face_selections() {
+        let grid: Vec<Point2> = (0..9).map(|i| Point2::new((i % 3) as f64, (i / 3) as f64)).collect();
+        let mut dcel: DCEL<Point2, u32> = DCEL::from_polygons(&grid, &[&[0, 1, 4, 3], &[1, 2, 5, 4], &[3, 4, 7, 6],
+                                                                       &[4, 5, 8, 7]]).unwrap()
+            .map_face_data(|_| 0);
+        for (i, f) in dcel.faces.ids().into_iter().enumerate() {
+            *dcel.face_data_mut(f) = i as u32;
+        }
+        let faces = dcel.faces.ids();
+
+        let (piece, maps) = dcel.extract(&[faces[1], faces[2], dcel.outer_face]);
+        piece.validate().unwrap();
+        assert_eq!((piece.vertices.len(), piece.edges.len(), piece.faces.len()), (6, 14, 3));
+        let expected = DCEL::from_polygons(&grid[..6], &[&[0, 1, 4, 3], &[1, 2, 5, 4]]).unwrap();
+        assert!(topologically_equal(&piece, &expected));
+        assert_eq!(piece.faces[piece.outer_face].inner_components().len(), 1);
+        for (f, face) in piece.faces.iter() {
+            assert_eq!(face.data(), dcel.face_data(maps.faces[&f]));
+        }
+        for (v, vertex) in piece.vertices.iter() {
+            assert_eq!(vertex.coord(), dcel.vertices[maps.vertices[&v]].coord());
+        }
+        for (e, edge) in piece.edges.iter() {
+            assert_eq!(maps.vertices[&edge.origin()], dcel.edges[maps.edges[&e]].origin());
+        }
+
+        // Faces meeting only at a corner share that vertex in the piece
+        let (piece, _) = dcel.extract(&[faces[1], faces[4]]);
+        piece.validate().unwrap();
+        assert_eq!((piece.vertices.len(), piece.faces.len()), (7, 3));
+        assert_eq!(piece.faces[piece.outer_face].inner_components().len(), 1);
+    }
+}
@@ -0,0 +1,474 @@
+//! Comparison of DCELs: the differences between two states of one DCEL, by
+//! id, and whether two DCELs have the same structure however their ids were
+//! assigned.
+
+use std::collections::{HashMap, HashSet};
+
+use dcel::*;
+use geometry::Position;
+
+/// The differences between two DCELs, element by element under the same
+/// ids, as found by `DCEL::diff`. Ids are listed in increasing order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DcelDiff<P> {
+    pub added_vertices: Vec<VertexId>,
+    pub removed_vertices: Vec<VertexId>,
+    /// Vertices in both whose position changed, with their old and new
+    /// positions.
+    pub moved_vertices: Vec<(VertexId, P, P)>,
+    /// Vertices in both whose outgoing edge changed.
+    pub relinked_vertices: Vec<VertexId>,
+    pub added_edges: Vec<HalfEdgeId>,
+    pub removed_edges: Vec<HalfEdgeId>,
+    /// Half-edges in both whose origin, twin, next, prev or face changed.
+    pub relinked_edges: Vec<HalfEdgeId>,
+    pub added_faces: Vec<FaceId>,
+    pub removed_faces: Vec<FaceId>,
+    /// Faces in both whose boundary, holes or payload changed.
+    pub changed_faces: Vec<FaceId>,
+}
+
+impl<P> DcelDiff<P> {
+    /// Determines if the two DCELs have the same live elements.
+    pub fn is_empty(&self) -> bool {
+        return self.added_vertices.is_empty() && self.removed_vertices.is_empty()
+            && self.moved_vertices.is_empty() && self.relinked_vertices.is_empty()
+            && self.added_edges.is_empty() && self.removed_edges.is_empty() && self.relinked_edges.is_empty()
+            && self.added_faces.is_empty() && self.removed_faces.is_empty() && self.changed_faces.is_empty();
+    }
+}
+
+/// Sorts the ids live in either of two arenas into those only in `old`,
+/// only in `new`, and those in both.
+fn split_ids<I: Copy + Ord + ::std::hash::Hash>(old: Vec<I>, new: Vec<I>) -> (Vec<I>, Vec<I>, Vec<I>) {
+    let old_set: HashSet<I> = old.iter().cloned().collect();
+    let new_set: HashSet<I> = new.iter().cloned().collect();
+    let removed = old.iter().cloned().filter(|i| !new_set.contains(i)).collect();
+    let added = new.iter().cloned().filter(|i| !old_set.contains(i)).collect();
+    let common = old.into_iter().filter(|i| new_set.contains(i)).collect();
+    return (added, removed, common);
+}
+
+impl<P: Position, F: PartialEq> DCEL<P, F> {
+    /// Lists what changed from this DCEL to `other`, comparing elements
+    /// with the same id. Deleted slots and reuse order are ignored.
+    pub fn diff(&self, other: &DCEL<P, F>) -> DcelDiff<P> {
+        let (added_vertices, removed_vertices, vertices) = split_ids(self.vertices.ids(), other.vertices.ids());
+        let (added_edges, removed_edges, edges) = split_ids(self.edges.ids(), other.edges.ids());
+        let (added_faces, removed_faces, faces) = split_ids(self.faces.ids(), other.faces.ids());
+        return DcelDiff {
+            added_vertices: added_vertices,
+            removed_vertices: removed_vertices,
+            moved_vertices: vertices.iter()
+                .filter(|&&v| self.vertices[v].coord() != other.vertices[v].coord())
+                .map(|&v| (v, self.vertices[v].coord(), other.vertices[v].coord()))
+                .collect(),
+            relinked_vertices: vertices.into_iter()
+                .filter(|&v| self.vertices[v].outgoing_edge() != other.vertices[v].outgoing_edge())
+                .collect(),
+            added_edges: added_edges,
+            removed_edges: removed_edges,
+            relinked_edges: edges.into_iter().filter(|&e| self.edges[e] != other.edges[e]).collect(),
+            added_faces: added_faces,
+            removed_faces: removed_faces,
+            changed_faces: faces.into_iter().filter(|&f| self.faces[f] != other.faces[f]).collect(),
+        };
+    }
+}
+
+/// The half-edges of a DCEL grouped into connected components, along with
+/// whether each lies on the outer boundary of its face.
+struct Darts<'a, P: 'a, F: 'a> {
+    dcel: &'a DCEL<P, F>,
+    components: Vec<Vec<HalfEdgeId>>,
+    on_outer_boundary: HashSet<HalfEdgeId>,
+}
+
+impl<'a, P: Position, F> Darts<'a, P, F> {
+    fn new(dcel: &'a DCEL<P, F>) -> Darts<'a, P, F> {
+        let mut on_outer_boundary = HashSet::new();
+        for (_, face) in dcel.faces.iter() {
+            if let Some(start) = face.incident_edge() {
+                on_outer_boundary.extend(dcel.cycle_edges(start));
+            }
+        }
+        let mut seen = HashSet::new();
+        let mut components = Vec::new();
+        for (start, _) in dcel.edges.iter() {
+            if !seen.insert(start) {
+                continue;
+            }
+            let mut component = vec![start];
+            let mut i = 0;
+            while i < component.len() {
+                let edge = &dcel.edges[component[i]];
+                for next in edge.next().into_iter().chain(edge.twin()) {
+                    if seen.insert(next) {
+                        component.push(next);
+                    }
+                }
+                i += 1;
+            }
+            components.push(component);
+        }
+        return Darts { dcel: dcel, components: components, on_outer_boundary: on_outer_boundary };
+    }
+
+    /// Returns a canonical code for the whole DCEL, equal for two DCELs
+    /// coded with the same `codes` exactly when they're topologically equal.
+    /// Each component is coded after the components in its faces, as with
+    /// rooted trees, so this needs components to nest as they do in a
+    /// planar subdivision: each with its holes in one face, owned by another
+    /// component or the outer face, and sharing no vertices. Returns `None`
+    /// if they don't.
+    fn code(&self, codes: &mut Codes) -> Option<usize> {
+        let dcel = self.dcel;
+        let n = self.components.len();
+        let mut component_of: HashMap<HalfEdgeId, usize> = HashMap::new();
+        for (c, component) in self.components.iter().enumerate() {
+            component_of.extend(component.iter().map(|&e| (e, c)));
+        }
+        let mut vertex_component: HashMap<VertexId, usize> = HashMap::new();
+        for (e, edge) in dcel.edges.iter() {
+            if *vertex_component.entry(edge.origin()).or_insert(component_of[&e]) != component_of[&e] {
+                return None;
+            }
+        }
+        let mut parent: Vec<Option<FaceId>> = vec![None; n];
+        let mut children: HashMap<FaceId, Vec<usize>> = HashMap::new();
+        for (f, face) in dcel.faces.iter() {
+            for &start in face.inner_components() {
+                let c = component_of[&start];
+                match parent[c] {
+                    Some(g) if g != f => return None,
+                    Some(_) => {}
+                    None => {
+                        parent[c] = Some(f);
+                        children.entry(f).or_default().push(c);
+                    }
+                }
+            }
+        }
+
+        // Order the components from the outer face inwards, through the
+        // faces each one owns
+        let owned = |c: usize| self.components[c].iter().cloned()
+            .filter(|&e| dcel.faces[dcel.edges[e].face()].incident_edge() == Some(e))
+            .map(|e| dcel.edges[e].face());
+        let mut order: Vec<usize> = (0..n).filter(|&c| [None, Some(dcel.outer_face)].contains(&parent[c])).collect();
+        let mut i = 0;
+        while i < order.len() {
+            for f in owned(order[i]) {
+                order.extend(children.get(&f).into_iter().flatten());
+            }
+            i += 1;
+        }
+        if order.len() != n {
+            return None;
+        }
+
+        // Then code them from the inside out
+        let mut component_code = vec![0; n];
+        let mut face_code: HashMap<FaceId, usize> = HashMap::new();
+        for &c in order.iter().rev() {
+            for f in owned(c) {
+                let mut inside: Vec<usize> = children.get(&f).into_iter().flatten().map(|&k| component_code[k]).collect();
+                inside.sort();
+                face_code.insert(f, codes.intern(inside));
+            }
+            component_code[c] = self.component_code(c, &face_code, codes)?;
+        }
+        let mut roots: Vec<usize> = (0..n).filter(|&c| [None, Some(dcel.outer_face)].contains(&parent[c]))
+            .map(|c| component_code[c])
+            .collect();
+        roots.sort();
+        return Some(codes.intern(roots));
+    }
+
+    /// Codes a component as the least of its encodings from each of its
+    /// half-edges, which takes time quadratic in its size. Half-edges on the
+    /// outer boundary of a face carry the code of what lies in the face.
+    fn component_code(&self, c: usize, face_code: &HashMap<FaceId, usize>, codes: &mut Codes) -> Option<usize> {
+        let dcel = self.dcel;
+        let darts = &self.components[c];
+        let m = darts.len();
+        let local: HashMap<HalfEdgeId, usize> = darts.iter().enumerate().map(|(i, &e)| (e, i)).collect();
+        let mut next = Vec::with_capacity(m);
+        let mut twin = Vec::with_capacity(m);
+        let mut vertex = Vec::with_capacity(m);
+        let mut face = Vec::with_capacity(m);
+        let mut kind = Vec::with_capacity(m);
+        let (mut vertices, mut faces) = (HashMap::new(), HashMap::new());
+        for (i, &e) in darts.iter().enumerate() {
+            let edge = &dcel.edges[e];
+            next.push(local[&edge.next()?]);
+            twin.push(local[&edge.twin()?]);
+            vertex.push(*vertices.entry(edge.origin()).or_insert(i));
+            face.push(*faces.entry(edge.face()).or_insert(i));
+            kind.push(if self.on_outer_boundary.contains(&e) { face_code[&edge.face()] + 1 } else { 0 });
+        }
+
+        let mut best: Option<Vec<usize>> = None;
+        for start in 0..m {
+            let mut index = vec![usize::MAX; m];
+            let mut visited = vec![start];
+            index[start] = 0;
+            let mut i = 0;
+            while i < visited.len() {
+                let x = visited[i];
+                for &y in &[next[x], twin[x]] {
+                    if index[y] == usize::MAX {
+                        index[y] = visited.len();
+                        visited.push(y);
+                    }
+                }
+                i += 1;
+            }
+            // Vertices and faces are named after the first half-edge visited
+            // in each
+            let (mut vertex_name, mut face_name) = (vec![usize::MAX; m], vec![usize::MAX; m]);
+            let mut code = Vec::with_capacity(5 * m);
+            for &x in &visited {
+                if vertex_name[vertex[x]] == usize::MAX {
+                    vertex_name[vertex[x]] = index[x];
+                }
+                if face_name[face[x]] == usize::MAX {
+                    face_name[face[x]] = index[x];
+                }
+                code.extend_from_slice(&[index[next[x]], index[twin[x]], vertex_name[vertex[x]], face_name[face[x]], kind[x]]);
+            }
+            match best {
+                Some(ref least) if *least <= code => {}
+                _ => best = Some(code),
+            }
+        }
+        return Some(codes.intern(best.unwrap_or_default()));
+    }
+}
+
+/// Canonical codes, each interned as a small number so that codes can be
+/// nested in one another. Equal numbers mean equal codes.
+#[derive(Default)]
+struct Codes {
+    ids: HashMap<Vec<usize>, usize>,
+}
+
+impl Codes {
+    fn intern(&mut self, code: Vec<usize>) -> usize {
+        let next = self.ids.len();
+        return *self.ids.entry(code).or_insert(next);
+    }
+}
+
+/// A partial isomorphism between two DCELs.
+#[derive(Clone, Default)]
+struct Matching {
+    edges: HashMap<HalfEdgeId, HalfEdgeId>,
+    vertices: HashMap<VertexId, VertexId>,
+    vertices_back: HashMap<VertexId, VertexId>,
+    faces: HashMap<FaceId, FaceId>,
+    faces_back: HashMap<FaceId, FaceId>,
+}
+
+impl Matching {
+    /// Pairs two elements, failing if either is already paired otherwise.
+    fn pair<I: Copy + Eq + ::std::hash::Hash>(there: &mut HashMap<I, I>, back: &mut HashMap<I, I>, a: I, b: I) -> bool {
+        match (there.get(&a), back.get(&b)) {
+            (None, None) => {
+                there.insert(a, b);
+                back.insert(b, a);
+                return true;
+            }
+            (Some(&mapped), Some(&from)) => return mapped == b && from == a,
+            _ => return false,
+        }
+    }
+
+    /// Extends the matching over the component of `a`, starting by pairing
+    /// `a` with `b`.
+    fn extend<P: Position, F, Q: Position, G>(&mut self, a: &Darts<P, F>, b: &Darts<Q, G>,
+                                              start_a: HalfEdgeId, start_b: HalfEdgeId) -> bool {
+        let mut stack = vec![(start_a, start_b)];
+        let mut mapped_b = HashSet::new();
+        while let Some((x, y)) = stack.pop() {
+            match self.edges.get(&x) {
+                Some(&mapped) if mapped == y => continue,
+                Some(_) => return false,
+                None => {}
+            }
+            if !mapped_b.insert(y) || a.on_outer_boundary.contains(&x) != b.on_outer_boundary.contains(&y) {
+                return false;
+            }
+            self.edges.insert(x, y);
+
+            let (ex, ey) = (&a.dcel.edges[x], &b.dcel.edges[y]);
+            if !Matching::pair(&mut self.vertices, &mut self.vertices_back, ex.origin(), ey.origin())
+                || !Matching::pair(&mut self.faces, &mut self.faces_back, ex.face(), ey.face()) {
+                return false;
+            }
+            for &(nx, ny) in &[(ex.next(), ey.next()), (ex.twin(), ey.twin()), (ex.prev(), ey.prev())] {
+                match (nx, ny) {
+                    (Some(nx), Some(ny)) => stack.push((nx, ny)),
+                    (None, None) => {}
+                    _ => return false,
+                }
+            }
+        }
+        return true;
+    }
+}
+
+/// Determines if two DCELs have the same combinatorial structure: if there
+/// is a one-to-one correspondence between their vertices, half-edges and
+/// faces preserving every link, taking outer face to outer face and outer
+/// boundaries to outer boundaries. Positions, payloads and ids are ignored.
+///
+/// Where the components nest as in a planar subdivision, each is given a
+/// canonical code that takes in what lies in its faces, and the codes are
+/// compared; this takes time quadratic in the number of half-edges.
+/// Otherwise, as for meshes whose faces share components oddly, the
+/// components are matched with each other by backtracking, which can take
+/// exponential time.
+pub fn topologically_equal<P: Position, F, Q: Position, G>(a: &DCEL<P, F>, b: &DCEL<Q, G>) -> bool {
+    if a.vertices.len() != b.vertices.len() || a.edges.len() != b.edges.len() || a.faces.len() != b.faces.len() {
+        return false;
+    }
+    let (darts_a, darts_b) = (Darts::new(a), Darts::new(b));
+    if darts_a.components.len() != darts_b.components.len() {
+        return false;
+    }
+    let mut codes = Codes::default();
+    match (darts_a.code(&mut codes), darts_b.code(&mut codes)) {
+        (Some(code_a), Some(code_b)) => return code_a == code_b,
+        (None, None) => {}
+        _ => return false,
+    }
+    let mut matching = Matching::default();
+    matching.faces.insert(a.outer_face, b.outer_face);
+    matching.faces_back.insert(b.outer_face, a.outer_face);
+    let mut used = vec![false; darts_b.components.len()];
+    return match_components(&darts_a, &darts_b, 0, &mut used, &matching);
+}
+
+/// Matches the components of `a` from the `i`th on with unused components
+/// of `b`, backtracking when a choice conflicts with a later one.
+fn match_components<P: Position, F, Q: Position, G>(a: &Darts<P, F>, b: &Darts<Q, G>, i: usize, used: &mut [bool],
+                                                    matching: &Matching) -> bool {
+    let component = match a.components.get(i) {
+        Some(component) => component,
+        None => return true,
+    };
+    for j in 0..b.components.len() {
+        if used[j] || b.components[j].len() != component.len() {
+            continue;
+        }
+        used[j] = true;
+        for &start in &b.components[j] {
+            let mut extended = matching.clone();
+            if extended.extend(a, b, component[0], start) && match_components(a, b, i + 1, used, &extended) {
+                return true;
+            }
+        }
+        used[j] = false;
+    }
+    return false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geometry::Point2;
+
+    fn unit_square() -> DCEL {
+        let points = [Point2::new(0.0, 0.0), Point2::new(1.0, 0.0), Point2::new(1.0, 1.0), Point2::new(0.0, 1.0)];
+        return DCEL::from_polygons(&points, &[&[0, 1, 2], &[0, 2, 3]]).unwrap();
+    }
+
+    fn diagonal(dcel: &DCEL) -> HalfEdgeId {
+        return dcel.vertex_edges(VertexId::from(0)).find(|&e| dcel.get_destination(e) == VertexId::from(2)).unwrap();
+    }
+
+    #[test]
+    fn diffs_by_id() {
+        let before = unit_square();
+        let mut after = before.snapshot();
+        assert!(before.diff(&after).is_empty());
+
+        let e = diagonal(&after);
+        let mid = after.split_edge_in_half(e);
+        let diff = before.diff(&after);
+        assert_eq!(diff.added_vertices, vec![mid]);
+        assert_eq!(diff.added_edges, vec![HalfEdgeId::from(10), HalfEdgeId::from(11)]);
+        let twin = before.edges[e].twin().unwrap();
+        let mut relinked = vec![e, twin, before.edges[e].next().unwrap(), before.edges[twin].next().unwrap()];
+        relinked.sort();
+        assert_eq!(diff.relinked_edges, relinked);
+        assert!(diff.removed_vertices.is_empty() && diff.moved_vertices.is_empty() && diff.changed_faces.is_empty());
+
+        let reverse = after.diff(&before);
+        assert_eq!(reverse.removed_vertices, vec![mid]);
+        assert_eq!(reverse.removed_edges, diff.added_edges);
+    }
+
+    #[test]
+    fn compares_structure_ignoring_ids() {
+        let mut split = unit_square();
+        split.split_edge_in_half(diagonal(&split));
+
+        // The same mesh built from scratch, with its elements in another order
+        let points = [Point2::new(0.5, 0.5), Point2::new(0.0, 1.0), Point2::new(0.0, 0.0),
+                      Point2::new(1.0, 1.0), Point2::new(1.0, 0.0)];
+        let expected = DCEL::from_polygons(&points, &[&[2, 0, 3, 1], &[3, 0, 2, 4]]).unwrap();
+        assert!(topologically_equal(&split, &expected));
+        assert!(topologically_equal(&expected, &split));
+        assert!(!topologically_equal(&unit_square(), &expected));
+
+        // A quad and a triangle fan around a vertex differ only in structure
+        let fan = DCEL::from_polygons(&points, &[&[2, 4, 0], &[4, 3, 0], &[3, 1, 0], &[1, 2, 0]]).unwrap();
+        let mut flipped = fan.clone();
+        let spoke = flipped.vertex_edges(VertexId::from(0)).next().unwrap();
+        assert!(topologically_equal(&fan, &flipped));
+        flipped.split_edge_in_half(spoke);
+        assert!(!topologically_equal(&fan, &flipped));
+
+        // Identical islands must be matched up with the right faces
+        let square = |x: f64| vec![Point2::new(x, 0.0), Point2::new(x + 1.0, 0.0), Point2::new(x + 1.0, 1.0),
+                                   Point2::new(x, 1.0)];
+        let big = |x: f64| vec![Point2::new(x, -2.0), Point2::new(x + 5.0, -2.0), Point2::new(x + 5.0, 3.0),
+                                Point2::new(x, 3.0)];
+        let holed = vec![big(0.0), square(2.0)];
+        let a: DCEL<Point2, Option<usize>> = DCEL::from_polygon_rings(
+            &[holed.clone(), vec![square(0.5)], vec![square(10.0)]], |p| p).unwrap();
+        let b: DCEL<Point2, Option<usize>> = DCEL::from_polygon_rings(
+            &[vec![square(-10.0)], holed.clone(), vec![square(3.5)]], |p| p).unwrap();
+        // The same islands, but neither inside the holed square
+        let c: DCEL<Point2, Option<usize>> = DCEL::from_polygon_rings(
+            &[holed.clone(), vec![square(-10.0)], vec![square(10.0)]], |p| p).unwrap();
+        assert!(topologically_equal(&a, &b));
+        assert!(!topologically_equal(&a, &c));
+    }
+
+    #[test]
+    fn compares_many_alike_components() {
+        let square = |x: f64, y: f64, size: f64| vec![Point2::new(x, y), Point2::new(x + size, y),
+                                                       Point2::new(x + size, y + size), Point2::new(x, y + size)];
+        // Holed squares in a row, with an island in every hole but one
+        let row = |empty: usize, reversed: bool| {
+            let mut rings = Vec::new();
+            for i in 0..20 {
+                let x = 10.0 * i as f64;
+                rings.push(vec![square(x, 0.0, 5.0), square(x + 1.0, 1.0, 3.0)]);
+                if i != empty {
+                    rings.push(vec![square(x + 2.0, 2.0, 1.0)]);
+                }
+            }
+            if reversed {
+                rings.reverse();
+            }
+            let dcel: DCEL<Point2, Option<usize>> = DCEL::from_polygon_rings(&rings, |p| p).unwrap();
+            return dcel;
+        };
+        assert!(topologically_equal(&row(3, false), &row(17, true)));
+        assert!(topologically_equal(&row(3, false), &row(3, true)));
+    }
+}
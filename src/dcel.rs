@@ -1,31 +1,71 @@
 use geometry::*;
 use arena::*;
+use observe::*;
+use scalar::Scalar;
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 // ArenaID types for a DCEL
 arena_id!(VertexId);
 arena_id!(HalfEdgeId);
 arena_id!(FaceId);
 
-#[derive(Debug, PartialEq)]
-pub struct Vertex {
-    coord: Point2,
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vertex<P = Point2> {
+    coord: P,
     outgoing_edge: Option<HalfEdgeId>,
 
     deleted: bool,
 }
 
-make_deleteable!(Vertex);
-impl Vertex {
-    pub fn new(coord: Point2) -> Vertex {
+impl<P> Deleteable for Vertex<P> {
+    fn is_deleted(&self) -> bool {
+        return self.deleted;
+    }
+
+    fn set_deleted(&mut self) {
+        self.deleted = true;
+    }
+}
+
+impl<P: Position> Vertex<P> {
+    pub fn new(coord: P) -> Vertex<P> {
         Vertex {
             coord: coord,
             outgoing_edge: None,
             deleted: false,
         }
     }
+
+    /// Rebuilds a vertex from its raw fields, as when deserializing.
+    pub(crate) fn from_raw(coord: P, outgoing_edge: Option<HalfEdgeId>, deleted: bool) -> Vertex<P> {
+        Vertex {
+            coord: coord,
+            outgoing_edge: outgoing_edge,
+            deleted: deleted,
+        }
+    }
+
+    pub fn coord(&self) -> P {
+        return self.coord;
+    }
+
+    pub fn outgoing_edge(&self) -> Option<HalfEdgeId> {
+        return self.outgoing_edge;
+    }
+
+    pub(crate) fn set_outgoing_edge(&mut self, edge: HalfEdgeId) {
+        self.outgoing_edge = Some(edge);
+    }
+
+    pub(crate) fn clear_outgoing_edge(&mut self) {
+        self.outgoing_edge = None;
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HalfEdge {
     origin: VertexId,
     twin: Option<HalfEdgeId>,
@@ -49,42 +89,301 @@ impl HalfEdge {
             deleted: false,
         }
     }
+
+    /// Rebuilds a half-edge from its raw fields, as when deserializing.
+    pub(crate) fn from_raw(origin: VertexId, twin: Option<HalfEdgeId>, next: Option<HalfEdgeId>,
+                           prev: Option<HalfEdgeId>, face: FaceId, deleted: bool) -> HalfEdge {
+        HalfEdge {
+            origin: origin,
+            twin: twin,
+            next: next,
+            prev: prev,
+            face: face,
+
+            deleted: deleted,
+        }
+    }
+
+    pub fn origin(&self) -> VertexId {
+        return self.origin;
+    }
+
+    pub fn twin(&self) -> Option<HalfEdgeId> {
+        return self.twin;
+    }
+
+    pub fn next(&self) -> Option<HalfEdgeId> {
+        return self.next;
+    }
+
+    pub fn prev(&self) -> Option<HalfEdgeId> {
+        return self.prev;
+    }
+
+    pub fn face(&self) -> FaceId {
+        return self.face;
+    }
+
+    pub(crate) fn set_face(&mut self, face: FaceId) {
+        self.face = face;
+    }
+
+    pub(crate) fn set_origin(&mut self, origin: VertexId) {
+        self.origin = origin;
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct Face {
+/// A face of the subdivision. `incident_edge` lies on its outer boundary and
+/// each of `inner_components` on the boundary of a hole. The outer face has
+/// no outer boundary, so every boundary cycle it has is an inner component.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Face<F = ()> {
     incident_edge: Option<HalfEdgeId>,
+    inner_components: Vec<HalfEdgeId>,
+    data: F,
 
     deleted: bool,
 }
 
-make_deleteable!(Face);
-impl Face {
-    pub fn new() -> Face {
+impl<F> Deleteable for Face<F> {
+    fn is_deleted(&self) -> bool {
+        return self.deleted;
+    }
+
+    fn set_deleted(&mut self) {
+        self.deleted = true;
+    }
+}
+
+impl<F: Default> Face<F> {
+    pub fn new() -> Face<F> {
+        return Face::with_data(F::default());
+    }
+}
+
+impl<F> Face<F> {
+    pub fn with_data(data: F) -> Face<F> {
         Face {
             incident_edge: None,
+            inner_components: Vec::new(),
+            data: data,
             deleted: false,
         }
     }
+
+    /// Rebuilds a face from its raw fields, as when deserializing.
+    pub(crate) fn from_raw(incident_edge: Option<HalfEdgeId>, inner_components: Vec<HalfEdgeId>,
+                           data: F, deleted: bool) -> Face<F> {
+        Face {
+            incident_edge: incident_edge,
+            inner_components: inner_components,
+            data: data,
+            deleted: deleted,
+        }
+    }
+
+    pub fn incident_edge(&self) -> Option<HalfEdgeId> {
+        return self.incident_edge;
+    }
+
+    pub(crate) fn set_incident_edge(&mut self, edge: Option<HalfEdgeId>) {
+        self.incident_edge = edge;
+    }
+
+    pub fn inner_components(&self) -> &[HalfEdgeId] {
+        return &self.inner_components;
+    }
+
+    pub(crate) fn set_inner_components(&mut self, holes: Vec<HalfEdgeId>) {
+        self.inner_components = holes;
+    }
+
+    /// The payload stored on this face.
+    pub fn data(&self) -> &F {
+        return &self.data;
+    }
+
+    pub fn data_mut(&mut self) -> &mut F {
+        return &mut self.data;
+    }
+
+    /// Consumes the face, returning its payload.
+    pub fn into_data(self) -> F {
+        return self.data;
+    }
+
+    /// Replaces any reference to the half-edge `old` with `new`, dropping
+    /// the reference entirely when `new` is `None`.
+    pub(crate) fn replace_edge(&mut self, old: HalfEdgeId, new: Option<HalfEdgeId>) {
+        if self.incident_edge == Some(old) {
+            self.incident_edge = new;
+        }
+        for i in (0..self.inner_components.len()).rev() {
+            if self.inner_components[i] == old {
+                match new {
+                    Some(e) => self.inner_components[i] = e,
+                    None => {
+                        self.inner_components.remove(i);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<F: Default> Default for Face<F> {
+    fn default() -> Face<F> {
+        return Face::new();
+    }
 }
 
-type VertexArena = Arena<Vertex, VertexId>;
+type VertexArena<P> = Arena<Vertex<P>, VertexId>;
 type HalfEdgeArena = Arena<HalfEdge, HalfEdgeId>;
-type FaceArena = Arena<Face, FaceId>;
+type FaceArena<F> = Arena<Face<F>, FaceId>;
+
+/// Errors raised by topological operations whose preconditions don't hold.
+/// Unless an operation says otherwise, the DCEL is left untouched when one
+/// of these is returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopologyError {
+    /// The operation needs an edge between two distinct, inner faces.
+    BoundaryEdge(HalfEdgeId),
+    /// The operation would create a face with fewer than three edges.
+    DegenerateFace(FaceId),
+    /// The operation would leave the surface non-manifold.
+    NonManifold(VertexId),
+}
+
+impl fmt::Display for TopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TopologyError::BoundaryEdge(e) => write!(f, "half-edge {:?} does not separate two inner faces", e),
+            TopologyError::DegenerateFace(face) => write!(f, "face {:?} would become degenerate", face),
+            TopologyError::NonManifold(v) => write!(f, "vertex {:?} would become non-manifold", v),
+        }
+    }
+}
+
+/// Inconsistencies found by `DCEL::validate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationError {
+    /// An element refers to a vertex, half-edge or face that doesn't exist
+    /// or has been deleted. Holds the kind and index of the missing element.
+    MissingElement(&'static str, usize),
+    /// The half-edge's twin is itself, missing, or doesn't point back.
+    TwinMismatch(HalfEdgeId),
+    /// The half-edge's `next` and `prev` pointers don't agree with their
+    /// neighbours, or `next` doesn't start where the half-edge ends.
+    LinkMismatch(HalfEdgeId),
+    /// The half-edge's face differs from that of the rest of its cycle, or
+    /// its face doesn't refer to its cycle exactly once.
+    FaceMismatch(HalfEdgeId),
+    /// The vertex's outgoing edge doesn't start at the vertex.
+    VertexMismatch(VertexId),
+    /// The arena's reuse stack doesn't list exactly its deleted slots.
+    ReuseStack(&'static str),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationError::MissingElement(kind, index) => write!(f, "reference to missing {} {}", kind, index),
+            ValidationError::TwinMismatch(e) => write!(f, "half-edge {:?} has an inconsistent twin", e),
+            ValidationError::LinkMismatch(e) => write!(f, "half-edge {:?} has inconsistent next/prev links", e),
+            ValidationError::FaceMismatch(e) => write!(f, "half-edge {:?} has an inconsistent face", e),
+            ValidationError::VertexMismatch(v) => write!(f, "vertex {:?} has an inconsistent outgoing edge", v),
+            ValidationError::ReuseStack(kind) => write!(f, "{} arena has an inconsistent reuse stack", kind),
+        }
+    }
+}
+
+/// Iterator over a cycle of half-edges, following `next` pointers.
+pub struct CycleEdges<'a, P: 'a, F: 'a> {
+    dcel: &'a DCEL<P, F>,
+    start: Option<HalfEdgeId>,
+    current: Option<HalfEdgeId>,
+}
+
+impl<'a, P, F> Iterator for CycleEdges<'a, P, F> {
+    type Item = HalfEdgeId;
+
+    fn next(&mut self) -> Option<HalfEdgeId> {
+        let current = self.current?;
+        let next = self.dcel.edges[current].next;
+        self.current = if next == self.start { None } else { next };
+        return Some(current);
+    }
+}
+
+/// Iterator over the half-edges leaving a vertex, rotating through `twin.next`.
+pub struct VertexEdges<'a, P: 'a, F: 'a> {
+    dcel: &'a DCEL<P, F>,
+    start: Option<HalfEdgeId>,
+    current: Option<HalfEdgeId>,
+}
+
+impl<'a, P, F> Iterator for VertexEdges<'a, P, F> {
+    type Item = HalfEdgeId;
+
+    fn next(&mut self) -> Option<HalfEdgeId> {
+        let current = self.current?;
+        let twin = self.dcel.edges[current].twin.unwrap();
+        let next = self.dcel.edges[twin].next;
+        self.current = if next == self.start { None } else { next };
+        return Some(current);
+    }
+}
 
-#[derive(Debug)]
-pub struct DCEL {
-    pub vertices: VertexArena,
+/// A DCEL whose vertices are positioned by `P` and whose faces carry a
+/// payload of type `F`.
+#[derive(Debug, Clone)]
+pub struct DCEL<P = Point2, F = ()> {
+    pub vertices: VertexArena<P>,
     pub edges: HalfEdgeArena,
-    pub faces: FaceArena,
+    pub faces: FaceArena<F>,
 
     pub outer_face: FaceId,
+
+    pub(crate) observers: Observers,
+}
+
+impl<P: Position, F: Default + Clone> Default for DCEL<P, F> {
+    fn default() -> DCEL<P, F> {
+        return DCEL::with_outer_face(F::default());
+    }
+}
+
+impl<P: Position> DCEL<P> {
+    pub fn new() -> DCEL<P> {
+        return DCEL::with_outer_face(());
+    }
 }
 
-impl DCEL {
-    pub fn new() -> DCEL {
+impl<P: Position, F: Default + Clone> DCEL<P, F> {
+    /// Adds a new face into this DCEL.
+    pub fn add_face(&mut self) -> FaceId {
+        // Create face, add it to arena, return id
+        let face = Face::new();
+        let id = self.faces.add(face);
+        self.notify(Event::FaceAdded(id));
+        return id;
+    }
+}
+
+impl<P: Position, F: Clone> DCEL<P, F> {
+    /// Returns a copy of this DCEL in O(1). The copy shares storage with
+    /// this DCEL until either is changed, and changes then copy only the
+    /// chunks of the arenas they touch. The copy starts with no observers.
+    pub fn snapshot(&self) -> DCEL<P, F> {
+        return self.clone();
+    }
+}
+
+impl<P: Position, F: Clone> DCEL<P, F> {
+    /// Returns an empty DCEL whose outer face carries `data`.
+    pub fn with_outer_face(data: F) -> DCEL<P, F> {
         let mut f_arena = FaceArena::new();
-        let outer_face = Face::new();
+        let outer_face = Face::with_data(data);
         let outer_face_id = f_arena.add(outer_face);
 
         DCEL {
@@ -93,17 +392,26 @@ impl DCEL {
             faces: f_arena,
 
             outer_face: outer_face_id,
+            observers: Observers::default(),
         }
     }
 
     /// Adds a new vertex to this DCEL.
-    pub fn add_vertex(&mut self, point: Point2) -> VertexId {
+    pub fn add_vertex(&mut self, point: P) -> VertexId {
         // Create vertex, add it to arena, return id
         let vert = Vertex::new(point);
         let id = self.vertices.add(vert);
+        self.notify(Event::VertexAdded(id));
         return id;
     }
 
+    /// Moves a vertex to `point`. Nothing else changes, so moving it across
+    /// an edge leaves the subdivision inconsistent with its coordinates.
+    pub fn set_vertex_coord(&mut self, vertex: VertexId, point: P) {
+        self.vertices[vertex].coord = point;
+        self.notify(Event::VertexMoved(vertex));
+    }
+
     /// Adds a new half-edge to this DCEL.
     pub fn add_half_edge(&mut self, origin_id: VertexId, face_id: FaceId) -> HalfEdgeId {
         // Create edge, add it to the arena
@@ -111,77 +419,115 @@ impl DCEL {
         let edge_id = self.edges.add(edge);
 
         // Update the origin of the edge
-        let mut origin = &mut self.vertices[origin_id];
+        let origin = &mut self.vertices[origin_id];
         origin.outgoing_edge = Some(edge_id);
 
-        // Update the face of the edge
-        let mut face = &mut self.faces[face_id];
-        face.incident_edge = Some(edge_id);
+        // Update the face of the edge, if it doesn't have any boundary yet
+        let face = &mut self.faces[face_id];
+        if face.incident_edge.is_none() && face.inner_components.is_empty() {
+            face.incident_edge = Some(edge_id);
+        }
 
-        // Return id
+        self.notify(Event::HalfEdgeAdded(edge_id));
         return edge_id;
     }
 
-    /// Adds a new face into this DCEL.
-    pub fn add_face(&mut self) -> FaceId {
-        // Create face, add it to arena, return id
-        let face = Face::new();
-        let id = self.faces.add(face);
+    /// Adds a new face carrying `data` into this DCEL.
+    pub fn add_face_with(&mut self, data: F) -> FaceId {
+        let id = self.faces.add(Face::with_data(data));
+        self.notify(Event::FaceAdded(id));
         return id;
     }
 
-    /// Given a half-edge, computes the cycle of edges it's a part of.
-    /// Panicks if a half-edge with no `next` field is encountered.
-    pub fn get_cycle_from(&self, start: HalfEdgeId) -> Vec<HalfEdgeId> {
-        let mut cycle = Vec::new();
-        let mut current = start;
+    /// Removes a vertex from its arena, telling observers.
+    pub(crate) fn delete_vertex(&mut self, vertex: VertexId) {
+        self.vertices.remove(vertex);
+        self.notify(Event::ElementRemoved(Element::Vertex(vertex)));
+    }
 
-        loop {
-            cycle.push(current);
-            current = self.edges[current].next.unwrap();
+    /// Removes a half-edge from its arena, telling observers.
+    pub(crate) fn delete_edge(&mut self, edge: HalfEdgeId) {
+        self.edges.remove(edge);
+        self.notify(Event::ElementRemoved(Element::HalfEdge(edge)));
+    }
 
-            if current == start {
-                break;
+    /// Removes a face from its arena, telling observers.
+    pub(crate) fn delete_face(&mut self, face: FaceId) {
+        self.faces.remove(face);
+        self.notify(Event::ElementRemoved(Element::Face(face)));
+    }
+
+    /// Records the cycle through `edge` as a hole in `face`.
+    pub fn add_inner_component(&mut self, face: FaceId, edge: HalfEdgeId) {
+        self.faces[face].inner_components.push(edge);
+    }
+
+    /// Makes each boundary cycle of the outer face one of its inner
+    /// components, after edits that may have joined or split them.
+    pub(crate) fn find_outer_components(&mut self) {
+        let mut seen = HashSet::new();
+        let mut holes = Vec::new();
+        for (e, edge) in self.edges.iter() {
+            if edge.face == self.outer_face && !seen.contains(&e) {
+                seen.extend(self.get_cycle_from(e));
+                holes.push(e);
             }
         }
+        let outer_face = self.outer_face;
+        self.faces[outer_face].incident_edge = None;
+        self.faces[outer_face].inner_components = holes;
+    }
 
-        return cycle;
+    pub fn face_data_mut(&mut self, face: FaceId) -> &mut F {
+        return &mut self.faces[face].data;
     }
 
-    /// Gets the destination vertex of a half-edge.
-    pub fn get_destination(&self, edge: HalfEdgeId) -> VertexId {
-        let twin_id = self.edges[edge].twin.unwrap();
-        let origin = self.edges[twin_id].origin;
-        return origin;
+    /// Converts the payload of every face, keeping all ids.
+    pub fn map_face_data<G, M: FnMut(F) -> G>(self, mut f: M) -> DCEL<P, G> {
+        let faces = self.faces.map(|face| Face {
+            incident_edge: face.incident_edge,
+            inner_components: face.inner_components,
+            data: f(face.data),
+            deleted: face.deleted,
+        });
+        return DCEL {
+            vertices: self.vertices,
+            edges: self.edges,
+            faces: faces,
+            outer_face: self.outer_face,
+            observers: self.observers,
+        };
     }
 
     /// Makes two half-edges twins.
     pub fn make_twins(&mut self, a: HalfEdgeId, b: HalfEdgeId) {
-        (&mut self.edges[a]).twin = Some(b);
-        (&mut self.edges[b]).twin = Some(a);
+        self.edges[a].twin = Some(b);
+        self.edges[b].twin = Some(a);
     }
 
     /// Links edges `a` and `b` sequentially.
     pub fn make_next(&mut self, a: HalfEdgeId, b: HalfEdgeId) {
-        (&mut self.edges[a]).next = Some(b);
-        (&mut self.edges[b]).prev = Some(a);
+        self.edges[a].next = Some(b);
+        self.edges[b].prev = Some(a);
     }
 
-    pub fn split_edge_in_half(&mut self, edge: HalfEdgeId) {
-        let face = (&self.edges[edge]).face;
-        let next = (&self.edges[edge]).next.unwrap();
+    /// Splits an edge at its midpoint, returning the new vertex. `edge` keeps
+    /// its origin and now ends at the new vertex.
+    pub fn split_edge_in_half(&mut self, edge: HalfEdgeId) -> VertexId {
+        let face = self.edges[edge].face;
+        let next = self.edges[edge].next.unwrap();
 
-        let twin = (&self.edges[edge]).twin.unwrap();
-        let twin_face = (&self.edges[twin]).face;
-        let twin_next = (&self.edges[twin]).next.unwrap();
+        let twin = self.edges[edge].twin.unwrap();
+        let twin_face = self.edges[twin].face;
+        let twin_next = self.edges[twin].next.unwrap();
 
         // Create vertex for the midpoint
         let mid = {
-            let origin = (&self.edges[edge]).origin;
-            let dest = (&self.edges[twin]).origin;
+            let origin = self.edges[edge].origin;
+            let dest = self.edges[twin].origin;
             let origin_coord = self.vertices[origin].coord;
             let dest_coord = self.vertices[dest].coord;
-            self.add_vertex(midpoint(origin_coord, dest_coord))
+            self.add_vertex(origin_coord.midpoint(&dest_coord))
         };
 
         // Create the two new half-edges
@@ -198,42 +544,929 @@ impl DCEL {
         // Set twin pointers
         self.make_twins(twin, n1);
         self.make_twins(edge, n2);
+
+        self.notify(Event::EdgeSplit { old: edge, new: n1 });
+        return mid;
     }
 
+    /// Removes an edge separating two distinct inner faces, merging the face
+    /// on the twin's side into the face on `edge`'s side. The holes of both
+    /// faces are kept, and removing an edge between a face and one of its
+    /// holes' neighbours turns the merged cycle into a hole.
     pub fn remove_inner_edge(&mut self, edge: HalfEdgeId) {
-        /* HAVEN'T TESTED THIS YET! */
-
         let outer_face = self.outer_face;
+        let face = self.edges[edge].face;
+        let twin_face = self.edges[self.edges[edge].twin.unwrap()].face;
+        if face == outer_face || twin_face == outer_face || face == twin_face {
+            return;
+        }
+        self.merge_across(edge);
+    }
 
-        let face = (&self.edges[edge]).face;
-        let next = (&self.edges[edge]).next.unwrap();
-        let prev = (&self.edges[edge]).prev.unwrap();
+    /// Removes an edge between two distinct faces, merging the face on the
+    /// twin's side into the face on `edge`'s side, holes and all.
+    fn merge_across(&mut self, edge: HalfEdgeId) {
+        let face = self.edges[edge].face;
+        let next = self.edges[edge].next.unwrap();
+        let prev = self.edges[edge].prev.unwrap();
 
-        let twin = (&self.edges[edge]).twin.unwrap();
-        let twin_face = (&self.edges[twin]).face;
-        let twin_next = (&self.edges[twin]).next.unwrap();
-        let twin_prev = (&self.edges[twin]).prev.unwrap();
+        let twin = self.edges[edge].twin.unwrap();
+        let twin_face = self.edges[twin].face;
+        let twin_next = self.edges[twin].next.unwrap();
+        let twin_prev = self.edges[twin].prev.unwrap();
 
-        let next_face = (&self.edges[next]).face;
-        let twin_next_face = (&self.edges[twin_next]).face;
+        // Work out which role each of the two cycles plays before relinking:
+        // the hole, if any, that each cycle is listed under
+        let hole_of = |f: FaceId, e: HalfEdgeId| {
+            return self.faces[f].inner_components.iter().position(|&h| self.cycle_edges(h).any(|c| c == e));
+        };
+        let edge_hole = hole_of(face, edge);
+        let twin_hole = hole_of(twin_face, twin);
+        let edge_in_hole = edge_hole.is_some();
+        let twin_in_hole = twin_hole.is_some();
+        let twin_cycles = self.face_cycles(twin_face);
 
-        if face == outer_face || twin_face == outer_face || face == twin_face {
-            return;
+        self.make_next(twin_prev, next);
+        self.make_next(prev, twin_next);
+
+        // Don't leave the endpoints pointing at the removed edges
+        let origin = self.edges[edge].origin;
+        let dest = self.edges[twin].origin;
+        if self.vertices[origin].outgoing_edge == Some(edge) {
+            self.vertices[origin].outgoing_edge = Some(twin_next);
+        }
+        if self.vertices[dest].outgoing_edge == Some(twin) {
+            self.vertices[dest].outgoing_edge = Some(next);
         }
 
-        self.make_next(twin_prev, next);
+        let mut holes: Vec<HalfEdgeId> = self.faces[face].inner_components.iter().cloned()
+            .enumerate().filter(|&(i, _)| Some(i) != edge_hole).map(|(_, e)| e).collect();
+        holes.extend(self.faces[twin_face].inner_components.iter().cloned()
+            .enumerate().filter(|&(i, _)| Some(i) != twin_hole).map(|(_, e)| e));
+        let outer = if edge_in_hole {
+            self.faces[face].incident_edge
+        } else if twin_in_hole {
+            self.faces[twin_face].incident_edge
+        } else {
+            Some(next)
+        };
+        if edge_in_hole || twin_in_hole {
+            holes.push(next);
+        }
+        self.faces[face].incident_edge = outer;
+        self.faces[face].inner_components = holes;
+
+        self.delete_edge(edge);
+        self.delete_edge(twin);
+        self.delete_face(twin_face);
+
+        for start in twin_cycles {
+            let start = if start == twin { next } else { start };
+            for e in self.get_cycle_from(start) {
+                self.edges[e].face = face;
+            }
+        }
+        self.notify(Event::FacesMerged { kept: face, removed: twin_face });
+    }
+
+    /// Rotates an edge one step forward within the two faces it separates.
+    /// Between two triangles this is the usual diagonal flip: the edge is
+    /// reconnected between the two vertices opposite to it.
+    pub fn flip_edge(&mut self, edge: HalfEdgeId) -> Result<(), TopologyError> {
+        let twin = self.edges[edge].twin.unwrap();
+        let face = self.edges[edge].face;
+        let twin_face = self.edges[twin].face;
+        if face == twin_face || face == self.outer_face || twin_face == self.outer_face {
+            return Err(TopologyError::BoundaryEdge(edge));
+        }
+
+        let next = self.edges[edge].next.unwrap();
+        let prev = self.edges[edge].prev.unwrap();
+        let twin_next = self.edges[twin].next.unwrap();
+        let twin_prev = self.edges[twin].prev.unwrap();
+        if next == prev {
+            return Err(TopologyError::DegenerateFace(face));
+        } else if twin_next == twin_prev {
+            return Err(TopologyError::DegenerateFace(twin_face));
+        }
+
+        let a = self.edges[edge].origin;
+        let b = self.edges[twin].origin;
+        let c = self.get_destination(next);
+        let d = self.get_destination(twin_next);
+        if c == d || self.vertex_neighbors(c).contains(&d) {
+            return Err(TopologyError::NonManifold(c));
+        }
+
+        // The endpoints each give up the flipped edge
+        if self.vertices[a].outgoing_edge == Some(edge) {
+            self.vertices[a].outgoing_edge = Some(twin_next);
+        }
+        if self.vertices[b].outgoing_edge == Some(twin) {
+            self.vertices[b].outgoing_edge = Some(next);
+        }
+        // `next` moves to the twin's face and `twin_next` to this one
+        self.faces[face].replace_edge(next, Some(edge));
+        self.faces[twin_face].replace_edge(twin_next, Some(twin));
+
+        // `edge` now runs d -> c and `twin` runs c -> d
+        let after_next = self.edges[next].next.unwrap();
+        let after_twin_next = self.edges[twin_next].next.unwrap();
+        self.edges[edge].origin = d;
+        self.edges[twin].origin = c;
+
         self.make_next(prev, twin_next);
+        self.make_next(twin_next, edge);
+        self.make_next(edge, after_next);
+        self.edges[twin_next].face = face;
 
-        self.edges.remove(edge);
-        self.edges.remove(twin);
-        self.faces.remove(twin_next_face);
+        self.make_next(twin_prev, next);
+        self.make_next(next, twin);
+        self.make_next(twin, after_twin_next);
+        self.edges[next].face = twin_face;
+
+        self.notify(Event::EdgeFlipped(edge));
+        return Ok(());
+    }
+
+    /// Collapses an edge by merging its origin into its destination, which
+    /// keeps its position. Faces reduced to two edges are removed and their
+    /// remaining edges glued together. Returns the surviving vertex.
+    pub fn collapse_edge(&mut self, edge: HalfEdgeId) -> Result<VertexId, TopologyError> {
+        let twin = self.edges[edge].twin.unwrap();
+        let v0 = self.edges[edge].origin;
+        let v1 = self.edges[twin].origin;
+        let face = self.edges[edge].face;
+        let twin_face = self.edges[twin].face;
+        if face == twin_face {
+            return Err(TopologyError::BoundaryEdge(edge));
+        }
+
+        let next = self.edges[edge].next.unwrap();
+        let prev = self.edges[edge].prev.unwrap();
+        let twin_next = self.edges[twin].next.unwrap();
+        let twin_prev = self.edges[twin].prev.unwrap();
+        if next == prev {
+            return Err(TopologyError::DegenerateFace(face));
+        } else if twin_next == twin_prev {
+            return Err(TopologyError::DegenerateFace(twin_face));
+        }
+
+        // Link condition: apart from the apexes of triangles on either side,
+        // the two endpoints may not share neighbours.
+        let mut allowed = HashSet::new();
+        if self.edges[next].next == Some(prev) {
+            allowed.insert(self.get_destination(next));
+        }
+        if self.edges[twin_next].next == Some(twin_prev) {
+            allowed.insert(self.get_destination(twin_next));
+        }
+        let n1: HashSet<_> = self.vertex_neighbors(v1).into_iter().collect();
+        for n in self.vertex_neighbors(v0) {
+            if n1.contains(&n) && !allowed.contains(&n) {
+                return Err(TopologyError::NonManifold(n));
+            }
+        }
 
-        for e in self.get_cycle_from(next) {
-            (&mut self.edges[e]).face = next_face;
+        for e in self.vertex_edges(v0).collect::<Vec<_>>() {
+            self.edges[e].origin = v1;
         }
+        self.make_next(prev, next);
+        self.make_next(twin_prev, twin_next);
+        self.faces[face].replace_edge(edge, Some(next));
+        self.faces[twin_face].replace_edge(twin, Some(twin_next));
+        self.vertices[v1].outgoing_edge = Some(next);
+
+        self.delete_edge(edge);
+        self.delete_edge(twin);
+        self.delete_vertex(v0);
+
+        self.remove_if_digon(next);
+        self.remove_if_digon(twin_next);
+        self.notify(Event::EdgeCollapsed { kept: v1, removed: v0 });
+        return Ok(v1);
+    }
+
+    /// If `edge` lies in a cycle of two half-edges, glues their twins together
+    /// and removes the cycle along with its face (unless it's the outer face).
+    fn remove_if_digon(&mut self, edge: HalfEdgeId) {
+        if !self.edges.contains(edge) {
+            return;
+        }
+        let other = self.edges[edge].next.unwrap();
+        if other == edge || self.edges[other].next != Some(edge) {
+            return;
+        }
+
+        let face = self.edges[edge].face;
+        let twin = self.edges[edge].twin.unwrap();
+        let other_twin = self.edges[other].twin.unwrap();
+        self.make_twins(twin, other_twin);
+
+        for &(e, replacement) in &[(edge, other_twin), (other, twin)] {
+            let origin = self.edges[e].origin;
+            if self.vertices[origin].outgoing_edge == Some(e) {
+                self.vertices[origin].outgoing_edge = Some(replacement);
+            }
+            self.delete_edge(e);
+        }
+
+        if face == self.outer_face {
+            self.faces[face].replace_edge(edge, None);
+            self.faces[face].replace_edge(other, None);
+        } else {
+            self.delete_face(face);
+        }
+    }
+
+}
+
+impl<P: Position, F> DCEL<P, F> {
+    /// Returns the payload of a face.
+    pub fn face_data(&self, face: FaceId) -> &F {
+        return &self.faces[face].data;
+    }
+
+    /// Given a half-edge, computes the cycle of edges it's a part of.
+    /// Panicks if a half-edge with no `next` field is encountered.
+    pub fn get_cycle_from(&self, start: HalfEdgeId) -> Vec<HalfEdgeId> {
+        let mut cycle = Vec::new();
+        let mut current = start;
+
+        loop {
+            cycle.push(current);
+            current = self.edges[current].next.unwrap();
+
+            if current == start {
+                break;
+            }
+        }
+
+        return cycle;
+    }
+
+    /// Gets the destination vertex of a half-edge.
+    pub fn get_destination(&self, edge: HalfEdgeId) -> VertexId {
+        let twin_id = self.edges[edge].twin.unwrap();
+        let origin = self.edges[twin_id].origin;
+        return origin;
+    }
+
+    /// Iterates over the cycle of half-edges starting at `start`.
+    pub fn cycle_edges(&self, start: HalfEdgeId) -> CycleEdges<'_, P, F> {
+        return CycleEdges {
+            dcel: self,
+            start: Some(start),
+            current: Some(start),
+        };
+    }
+
+    /// Iterates over the half-edges bounding a face, starting at its incident edge.
+    pub fn face_edges(&self, face: FaceId) -> CycleEdges<'_, P, F> {
+        let start = self.faces[face].incident_edge;
+        return CycleEdges {
+            dcel: self,
+            start: start,
+            current: start,
+        };
+    }
+
+    /// Returns one half-edge from each cycle bounding a face: its outer
+    /// boundary first, if it has one, then each of its holes.
+    pub fn face_cycles(&self, face: FaceId) -> Vec<HalfEdgeId> {
+        let face = &self.faces[face];
+        let mut cycles: Vec<HalfEdgeId> = face.incident_edge.into_iter().collect();
+        for &e in &face.inner_components {
+            if !cycles.contains(&e) {
+                cycles.push(e);
+            }
+        }
+        return cycles;
+    }
+
+    /// Iterates over the half-edges leaving a vertex.
+    pub fn vertex_edges(&self, vertex: VertexId) -> VertexEdges<'_, P, F> {
+        let start = self.vertices[vertex].outgoing_edge;
+        return VertexEdges {
+            dcel: self,
+            start: start,
+            current: start,
+        };
+    }
+
+    /// Returns the vertices joined to `vertex` by an edge.
+    pub fn vertex_neighbors(&self, vertex: VertexId) -> Vec<VertexId> {
+        return self.vertex_edges(vertex).map(|e| self.get_destination(e)).collect();
+    }
+
+    /// Returns the faces around `vertex`, including the outer face if it is
+    /// on the boundary.
+    pub fn vertex_faces(&self, vertex: VertexId) -> Vec<FaceId> {
+        return self.vertex_edges(vertex).map(|e| self.edges[e].face).collect();
+    }
+
+    /// Returns the number of edges leaving `vertex`.
+    pub fn degree(&self, vertex: VertexId) -> usize {
+        return self.vertex_edges(vertex).count();
+    }
+
+    /// Determines if the vertex lies on the boundary of the outer face.
+    pub fn is_boundary_vertex(&self, vertex: VertexId) -> bool {
+        return self.vertex_faces(vertex).contains(&self.outer_face);
+    }
+
+    /// Checks the structure of the DCEL: that every reference leads to a
+    /// live element, that twins, `next` and `prev` pointers agree, that each
+    /// cycle lies in one face which refers to it exactly once, and that the
+    /// arenas' reuse stacks list exactly their deleted slots.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        match self.validate_all().into_iter().next() {
+            Some(err) => return Err(err),
+            None => return Ok(()),
+        }
+    }
+
+    /// Runs the checks of `validate`, returning every violation found rather
+    /// than the first. Cycles are only followed once every half-edge's links
+    /// are consistent, so that they're sure to close; until then, violations
+    /// of the face references go unreported.
+    pub fn validate_all(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        errors.extend(check_reuse_stack(&self.vertices, "vertex").err());
+        errors.extend(check_reuse_stack(&self.edges, "half-edge").err());
+        errors.extend(check_reuse_stack(&self.faces, "face").err());
+
+        errors.extend(self.live_face(self.outer_face).err());
+        for (id, v) in self.vertices.iter() {
+            if let Some(e) = v.outgoing_edge {
+                match self.live_edge(Some(e)) {
+                    Ok(e) if self.edges[e].origin != id => errors.push(ValidationError::VertexMismatch(id)),
+                    Ok(_) => {}
+                    Err(err) => errors.push(err),
+                }
+            }
+        }
+        let mut linked = true;
+        for (id, _) in self.edges.iter() {
+            if let Err(err) = self.check_links(id) {
+                linked = false;
+                errors.push(err);
+            }
+        }
+        if !linked {
+            return errors;
+        }
+
+        // With the links consistent, every cycle closes; each must be
+        // referenced once by its face
+        let mut referenced = HashSet::new();
+        for (id, f) in self.faces.iter() {
+            for &start in f.incident_edge.iter().chain(f.inner_components.iter()) {
+                let start = match self.live_edge(Some(start)) {
+                    Ok(start) => start,
+                    Err(err) => {
+                        errors.push(err);
+                        continue;
+                    }
+                };
+                if self.edges[start].face != id {
+                    errors.push(ValidationError::FaceMismatch(start));
+                }
+                for e in self.cycle_edges(start) {
+                    if !referenced.insert(e) {
+                        errors.push(ValidationError::FaceMismatch(e));
+                    }
+                }
+            }
+        }
+        errors.extend(self.edges.iter().filter(|&(id, _)| !referenced.contains(&id)).map(|(id, _)| ValidationError::FaceMismatch(id)));
+        return errors;
+    }
+
+    fn live_edge(&self, e: Option<HalfEdgeId>) -> Result<HalfEdgeId, ValidationError> {
+        match e {
+            Some(e) if self.edges.contains(e) => return Ok(e),
+            Some(e) => return Err(ValidationError::MissingElement("half-edge", e.into())),
+            None => return Err(ValidationError::MissingElement("half-edge", usize::MAX)),
+        }
+    }
+
+    fn live_face(&self, f: FaceId) -> Result<FaceId, ValidationError> {
+        if !self.faces.contains(f) {
+            return Err(ValidationError::MissingElement("face", f.into()));
+        }
+        return Ok(f);
+    }
+
+    /// Checks a half-edge's references and that its links agree with its
+    /// neighbours'.
+    fn check_links(&self, id: HalfEdgeId) -> Result<(), ValidationError> {
+        let e = &self.edges[id];
+        if !self.vertices.contains(e.origin) {
+            return Err(ValidationError::MissingElement("vertex", e.origin.into()));
+        }
+        self.live_face(e.face)?;
+        let twin = self.live_edge(e.twin)?;
+        let next = self.live_edge(e.next)?;
+        let prev = self.live_edge(e.prev)?;
+        if twin == id || self.edges[twin].twin != Some(id) {
+            return Err(ValidationError::TwinMismatch(id));
+        }
+        if self.edges[next].prev != Some(id) || self.edges[prev].next != Some(id)
+            || self.edges[next].origin != self.edges[twin].origin {
+            return Err(ValidationError::LinkMismatch(id));
+        }
+        if self.edges[next].face != e.face {
+            return Err(ValidationError::FaceMismatch(id));
+        }
+        return Ok(());
+    }
+}
+
+/// Checks that an arena's reuse stack holds each of its deleted slots once.
+fn check_reuse_stack<T: Deleteable, I: ArenaId>(arena: &Arena<T, I>, kind: &'static str) -> Result<(), ValidationError> {
+    let mut seen = HashSet::new();
+    for &id in arena.reuse_stack() {
+        let index: usize = id.into();
+        if index >= arena.slot_count() || !arena.slot(index).is_deleted() || !seen.insert(index) {
+            return Err(ValidationError::ReuseStack(kind));
+        }
+    }
+    if arena.slots().filter(|&(_, s)| s.is_deleted()).count() != seen.len() {
+        return Err(ValidationError::ReuseStack(kind));
+    }
+    return Ok(());
+}
+
+impl<T: Scalar, F> DCEL<Point2<T>, F> {
+    /// Returns twice the signed area enclosed by the cycle through `start`,
+    /// positive when the cycle runs counter-clockwise.
+    pub fn cycle_area2(&self, start: HalfEdgeId) -> T {
+        let mut area = T::zero();
+        for e in self.cycle_edges(start) {
+            let a = self.vertices[self.edges[e].origin].coord;
+            let b = self.vertices[self.get_destination(e)].coord;
+            area = area + (a.x * b.y - b.x * a.y);
+        }
+        return area;
+    }
+
+    /// Returns the area of a face, less that of its holes. The outer face,
+    /// having no outer boundary, has negative area.
+    pub fn face_area(&self, face: FaceId) -> T {
+        let mut area2 = T::zero();
+        for start in self.face_cycles(face) {
+            area2 = area2 + self.cycle_area2(start);
+        }
+        return area2.half();
+    }
+
+    /// Returns the winding number of the cycle through `start` around `p`.
+    /// Points on the cycle itself are reported through `on_boundary`.
+    pub(crate) fn winding_number(&self, start: HalfEdgeId, p: &Point2<T>, on_boundary: &mut bool) -> i32 {
+        let mut winding = 0;
+        for e in self.cycle_edges(start) {
+            let a = self.vertices[self.edges[e].origin].coord;
+            let b = self.vertices[self.get_destination(e)].coord;
+            if Segment2::new(a, b).contains(p) {
+                *on_boundary = true;
+            }
+            if a.y <= p.y {
+                if b.y > p.y && is_lht(&a, &b, p) {
+                    winding += 1;
+                }
+            } else if b.y <= p.y && is_rht(&a, &b, p) {
+                winding -= 1;
+            }
+        }
+        return winding;
+    }
+
+    /// Determines if `p` lies inside or on the boundary of an inner face.
+    /// Points inside one of the face's holes are not contained.
+    pub fn face_contains(&self, face: FaceId, p: &Point2<T>) -> bool {
+        if self.faces[face].incident_edge.is_none() {
+            return false;
+        }
+        let mut on_boundary = false;
+        let mut winding = 0;
+        for start in self.face_cycles(face) {
+            winding += self.winding_number(start, p, &mut on_boundary);
+        }
+        return on_boundary || winding != 0;
+    }
+
+    /// Finds the inner face containing `p`, or the outer face if there is
+    /// none. Points on an edge are reported in either of its faces.
+    pub fn locate(&self, p: &Point2<T>) -> FaceId {
+        for (id, _) in self.faces.iter() {
+            if id != self.outer_face && self.face_contains(id, p) {
+                return id;
+            }
+        }
+        return self.outer_face;
+    }
+}
+
+impl<T: Scalar, F: Clone> DCEL<Point2<T>, F> {
+    /// Removes an edge along with its twin. An edge between two faces merges
+    /// them, keeping the outer face if it is one of them and otherwise the
+    /// face on `edge`'s side. An edge with the same face on both sides splits
+    /// that face's boundary cycle in two, the counter-clockwise part staying
+    /// its outer boundary and the rest becoming holes. Endpoints left without
+    /// any edges are removed.
+    pub fn remove_edge(&mut self, edge: HalfEdgeId) {
+        let twin = self.edges[edge].twin.unwrap();
+        let face = self.edges[edge].face;
+        let twin_face = self.edges[twin].face;
+        if face != twin_face {
+            self.merge_across(if twin_face == self.outer_face { twin } else { edge });
+            return;
+        }
+
+        let next = self.edges[edge].next.unwrap();
+        let prev = self.edges[edge].prev.unwrap();
+        let twin_next = self.edges[twin].next.unwrap();
+        let twin_prev = self.edges[twin].prev.unwrap();
+        let was_outer = match self.faces[face].incident_edge {
+            Some(start) => self.cycle_edges(start).any(|e| e == edge),
+            None => false,
+        };
+        let old: Vec<HalfEdgeId> = self.face_cycles(face).into_iter()
+            .filter(|&start| self.cycle_edges(start).any(|e| e == edge))
+            .collect();
+
+        // Short-circuit the edge from both sides; a side with nothing left is
+        // a vertex with no other edges
+        let mut cycles = Vec::new();
+        let origin = self.edges[edge].origin;
+        let dest = self.edges[twin].origin;
+        if twin_next != edge {
+            self.make_next(prev, twin_next);
+            cycles.push(twin_next);
+            if self.vertices[origin].outgoing_edge == Some(edge) {
+                self.vertices[origin].outgoing_edge = Some(twin_next);
+            }
+        } else {
+            self.delete_vertex(origin);
+        }
+        if next != twin {
+            self.make_next(twin_prev, next);
+            cycles.push(next);
+            if self.vertices[dest].outgoing_edge == Some(twin) {
+                self.vertices[dest].outgoing_edge = Some(next);
+            }
+        } else {
+            self.delete_vertex(dest);
+        }
+        self.delete_edge(edge);
+        self.delete_edge(twin);
+
+        for start in old {
+            self.faces[face].replace_edge(start, None);
+        }
+        if was_outer && face != self.outer_face {
+            let outer = cycles.iter().cloned().max_by(|&a, &b| {
+                self.cycle_area2(a).partial_cmp(&self.cycle_area2(b)).unwrap_or(Ordering::Equal)
+            });
+            cycles.retain(|&c| Some(c) != outer);
+            self.faces[face].incident_edge = outer;
+        }
+        self.faces[face].inner_components.extend(cycles);
+    }
+
+    /// Merges adjacent inner faces whose payloads map to the same key, by
+    /// removing every edge between two such faces, including edges with the
+    /// same inner face on both sides. Each merged face keeps the payload of
+    /// one of its parts, and holes are kept or closed as the edges around
+    /// them go. Endpoints of removed edges left in the middle of a straight
+    /// run of two edges are removed too. The outer face is never merged.
+    pub fn dissolve_by<K: PartialEq, KF: FnMut(&F) -> K>(&mut self, mut key_fn: KF) {
+        let outer_face = self.outer_face;
+        let keys: HashMap<FaceId, K> = self.faces.iter()
+            .filter(|&(f, _)| f != outer_face)
+            .map(|(f, face)| (f, key_fn(&face.data)))
+            .collect();
+        let removable: Vec<HalfEdgeId> = self.edges.iter()
+            .filter(|&(e, edge)| {
+                let twin = edge.twin.unwrap();
+                let (face, twin_face) = (edge.face, self.edges[twin].face);
+                e < twin && face != outer_face && twin_face != outer_face && keys[&face] == keys[&twin_face]
+            })
+            .map(|(e, _)| e)
+            .collect();
+
+        let mut ends = Vec::new();
+        for e in removable {
+            if self.edges.contains(e) {
+                ends.push(self.edges[e].origin);
+                ends.push(self.get_destination(e));
+                self.remove_edge(e);
+            }
+        }
+
+        for v in ends {
+            if !self.vertices.contains(v) || self.degree(v) != 2 {
+                continue;
+            }
+            let out = self.vertices[v].outgoing_edge.unwrap();
+            let back = self.edges[self.edges[out].twin.unwrap()].next.unwrap();
+            let p = self.vertices[v].coord;
+            let a = self.vertices[self.get_destination(out)].coord;
+            let b = self.vertices[self.get_destination(back)].coord;
+            if area_of_parallelogram(&a, &p, &b) == T::zero() && (a - p).dot(&(b - p)) < T::zero() {
+                let _ = self.collapse_edge(out);
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scalar::Rational;
+
+    fn mesh<P: Position>(points: &[P], polygons: &[&[usize]]) -> DCEL<P> {
+        return DCEL::from_polygons(points, polygons).unwrap();
+    }
+
+    fn edge_between<P: Position>(dcel: &DCEL<P>, a: usize, b: usize) -> HalfEdgeId {
+        let (a, b) = (VertexId::from(a), VertexId::from(b));
+        return dcel.vertex_edges(a).find(|&e| dcel.get_destination(e) == b).unwrap();
+    }
+
+    fn unit_square() -> DCEL {
+        let points = [Point2::new(0.0, 0.0), Point2::new(1.0, 0.0), Point2::new(1.0, 1.0), Point2::new(0.0, 1.0)];
+        return mesh(&points, &[&[0, 1, 2], &[0, 2, 3]]);
     }
 
-    pub fn flip_edge(&mut self, edge: HalfEdgeId) {
-        unimplemented!()
+    /// Builds a single triangle by hand, returning the DCEL and its inner edges.
+    fn triangle<T: Scalar>(points: [Point2<T>; 3]) -> (DCEL<Point2<T>>, Vec<HalfEdgeId>) {
+        let mut dcel = DCEL::new();
+        let verts: Vec<_> = points.iter().map(|p| dcel.add_vertex(*p)).collect();
+        let inner_face = dcel.add_face();
+        let outer_face = dcel.outer_face;
+
+        let inner: Vec<_> = verts.iter().map(|&v| dcel.add_half_edge(v, inner_face)).collect();
+        let outer: Vec<_> = (0..3).map(|i| dcel.add_half_edge(verts[(i + 1) % 3], outer_face)).collect();
+        for i in 0..3 {
+            dcel.make_twins(inner[i], outer[i]);
+            dcel.make_next(inner[i], inner[(i + 1) % 3]);
+            dcel.make_next(outer[(i + 1) % 3], outer[i]);
+        }
+        return (dcel, inner);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn split_with_exact_coordinates() {
+        let (mut dcel, inner) = triangle([
+            Point2::<Rational>::from((0, 0)),
+            Point2::from((1, 0)),
+            Point2::from((0, 1)),
+        ]);
+        dcel.split_edge_in_half(inner[0]);
+
+        let mid = dcel.get_destination(inner[0]);
+        assert_eq!(dcel.vertices[mid].coord, Point2::new(Rational::new(1, 2), Rational::from_integer(0)));
+        assert_eq!(dcel.get_cycle_from(inner[0]).len(), 4);
+    }
+
+    #[test]
+    fn split_on_integer_grid() {
+        let (mut dcel, inner) = triangle([Point2::<i64>::new(0, 0), Point2::new(4, 0), Point2::new(0, 4)]);
+        dcel.split_edge_in_half(inner[1]);
+
+        let mid = dcel.get_destination(inner[1]);
+        assert_eq!(dcel.vertices[mid].coord, Point2::new(2, 2));
+    }
+
+    #[test]
+    fn flip_square_diagonal() {
+        let mut dcel = unit_square();
+        let diagonal = edge_between(&dcel, 0, 2);
+        dcel.flip_edge(diagonal).unwrap();
+
+        let ends = (dcel.edges[diagonal].origin, dcel.get_destination(diagonal));
+        assert!(ends == (VertexId::from(1), VertexId::from(3)) || ends == (VertexId::from(3), VertexId::from(1)));
+        for (face, _) in dcel.faces.iter() {
+            if face != dcel.outer_face {
+                assert_eq!(dcel.face_edges(face).count(), 3);
+                assert!(dcel.face_edges(face).all(|e| dcel.edges[e].face == face));
+            }
+        }
+        dcel.validate().unwrap();
+
+        let boundary = edge_between(&dcel, 0, 1);
+        assert_eq!(dcel.flip_edge(dcel.edges[boundary].twin.unwrap()),
+                   Err(TopologyError::BoundaryEdge(dcel.edges[boundary].twin.unwrap())));
+    }
+
+    #[test]
+    fn collapse_undoes_split() {
+        let mut dcel = unit_square();
+        let side = edge_between(&dcel, 0, 1);
+        let mid = dcel.split_edge_in_half(side);
+        assert_eq!(dcel.degree(mid), 2);
+
+        let to_dest = dcel.edges[side].next.unwrap();
+        assert_eq!(dcel.collapse_edge(to_dest), Ok(VertexId::from(1)));
+        assert_eq!(dcel.vertices.len(), 4);
+        assert_eq!(dcel.get_destination(side), VertexId::from(1));
+        assert_eq!(dcel.face_edges(dcel.edges[side].face).count(), 3);
+        dcel.validate().unwrap();
+    }
+
+    #[test]
+    fn collapse_removes_triangles() {
+        let mut dcel = unit_square();
+        let diagonal = edge_between(&dcel, 0, 2);
+        dcel.collapse_edge(diagonal).unwrap();
+
+        // Both triangles degenerate, leaving a path 1 - 2 - 3 on the outer face
+        assert_eq!(dcel.faces.len(), 1);
+        assert_eq!(dcel.edges.len(), 4);
+        assert_eq!(dcel.degree(VertexId::from(2)), 2);
+        dcel.validate().unwrap();
+    }
+
+    #[test]
+    fn bordered_surface_circulators() {
+        // An open square pyramid: four triangles around an apex
+        let points = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(0.5, 0.5, 1.0),
+        ];
+        let mut dcel = mesh(&points, &[&[0, 1, 4], &[1, 2, 4], &[2, 3, 4], &[3, 0, 4]]);
+        let apex = VertexId::from(4);
+        assert_eq!(dcel.degree(apex), 4);
+        assert!(!dcel.is_boundary_vertex(apex));
+        assert!(dcel.is_boundary_vertex(VertexId::from(0)));
+
+        let spoke = edge_between(&dcel, 0, 4);
+        assert_eq!(dcel.flip_edge(spoke), Ok(()));
+        assert_eq!(dcel.degree(apex), 3);
+
+        let mid = dcel.split_edge_in_half(edge_between(&dcel, 2, 4));
+        assert_eq!(dcel.vertices[mid].coord, Point3::new(0.75, 0.75, 0.5));
+    }
+
+    #[test]
+    fn locate_points() {
+        let dcel = unit_square();
+        let lower = dcel.edges[edge_between(&dcel, 0, 1)].face;
+        let upper = dcel.edges[edge_between(&dcel, 2, 3)].face;
+        assert_eq!(dcel.locate(&Point2::new(0.75, 0.25)), lower);
+        assert_eq!(dcel.locate(&Point2::new(0.25, 0.75)), upper);
+        assert_eq!(dcel.locate(&Point2::new(2.0, 0.5)), dcel.outer_face);
+        assert_eq!(dcel.cycle_area2(edge_between(&dcel, 0, 1)), 1.0);
+    }
+
+    #[test]
+    fn remove_edge_keeps_holes() {
+        let square = |x: f64, y: f64, w: f64, h: f64| vec![
+            Point2::new(x, y), Point2::new(x + w, y), Point2::new(x + w, y + h), Point2::new(x, y + h),
+        ];
+        // Two halves of a rectangle, the left one with a hole
+        let polygons = vec![vec![square(0.0, 0.0, 4.0, 4.0), square(1.0, 1.0, 2.0, 2.0)], vec![square(4.0, 0.0, 4.0, 4.0)]];
+        let mut dcel = DCEL::from_polygon_rings(&polygons, |p| p).unwrap();
+        let left = dcel.locate(&Point2::new(0.5, 0.5));
+        let right = dcel.locate(&Point2::new(6.0, 2.0));
+        let shared = dcel.face_edges(right).find(|&e| dcel.edges[dcel.edges[e].twin().unwrap()].face() == left).unwrap();
+
+        // The inside of the hole is a face of its own, without a polygon
+        dcel.remove_inner_edge(shared);
+        assert_eq!(dcel.faces.len(), 3);
+        assert_eq!(dcel.face_cycles(right).len(), 2);
+        assert_eq!(dcel.face_edges(right).count(), 6);
+        assert_eq!(dcel.locate(&Point2::new(0.5, 0.5)), right);
+        let inside = dcel.locate(&Point2::new(2.0, 2.0));
+        assert_eq!(dcel.face_data(inside), &None);
+        assert_ne!(inside, dcel.outer_face);
+        for start in dcel.face_cycles(right) {
+            assert!(dcel.cycle_edges(start).all(|e| dcel.edges[e].face() == right));
+        }
+        dcel.validate().unwrap();
+
+        // Detaching a half-edge from its cycle is caught
+        let e = dcel.face_edges(right).next().unwrap();
+        let twin = dcel.edges[e].twin().unwrap();
+        dcel.make_next(e, twin);
+        assert!(dcel.validate().is_err());
+    }
+
+    #[test]
+    fn validate_all_reports_every_violation() {
+        let points = [Point2::new(0.0, 0.0), Point2::new(1.0, 0.0), Point2::new(0.0, 1.0)];
+        let mut dcel: DCEL = DCEL::from_polygons(&points, &[vec![0, 1, 2]]).unwrap();
+        assert!(dcel.validate_all().is_empty());
+        let (a, b) = (VertexId::from(0), VertexId::from(1));
+        let (from_a, from_b) = (dcel.vertices[a].outgoing_edge, dcel.vertices[b].outgoing_edge);
+        dcel.vertices[a].outgoing_edge = from_b;
+        dcel.vertices[b].outgoing_edge = from_a;
+        let errors = dcel.validate_all();
+        assert_eq!(errors, vec![ValidationError::VertexMismatch(a), ValidationError::VertexMismatch(b)]);
+        assert_eq!(dcel.validate(), Err(errors[0]));
+    }
+
+    #[test]
+    fn remove_edge_around_filled_hole() {
+        let square = |x: f64, y: f64, w: f64| vec![
+            Point2::new(x, y), Point2::new(x + w, y), Point2::new(x + w, y + w), Point2::new(x, y + w),
+        ];
+        // Whichever edge of the hole's cycle the square lists, removing any
+        // shared edge leaves the merged cycle listed once
+        for side in 0..4 {
+            let polygons = vec![vec![square(0.0, 0.0, 4.0), square(1.0, 1.0, 2.0)], vec![square(1.0, 1.0, 2.0)]];
+            let mut dcel = DCEL::from_polygon_rings(&polygons, |p| p).unwrap();
+            let outer = dcel.locate(&Point2::new(0.5, 0.5));
+            let filling = dcel.locate(&Point2::new(2.0, 2.0));
+            let shared: Vec<HalfEdgeId> = dcel.face_edges(filling).collect();
+            dcel.remove_inner_edge(shared[side]);
+            dcel.validate().unwrap();
+            assert_eq!(dcel.faces.len(), 2);
+            let kept = dcel.locate(&Point2::new(2.0, 2.0));
+            assert_eq!(dcel.locate(&Point2::new(0.5, 0.5)), kept);
+            assert!(kept == outer || kept == filling);
+            assert_eq!(dcel.face_cycles(kept).len(), 2);
+        }
+    }
+
+    #[test]
+    fn remove_edge_into_outer_face() {
+        let mut dcel = unit_square();
+        let (lower, upper) = (FaceId::from(1), FaceId::from(2));
+
+        // The lower triangle joins the outer face, leaving a dangling edge
+        dcel.remove_edge(edge_between(&dcel, 0, 1));
+        dcel.validate().unwrap();
+        assert_eq!(dcel.faces.len(), 2);
+        let dangling = edge_between(&dcel, 1, 2);
+        assert_eq!(dcel.edges[dangling].face(), dcel.outer_face);
+        assert_eq!(dcel.edges[dcel.edges[dangling].twin().unwrap()].face(), dcel.outer_face);
+
+        // Removing it takes its loose end with it
+        dcel.remove_edge(dangling);
+        dcel.validate().unwrap();
+        assert_eq!(dcel.vertices.len(), 3);
+        assert_eq!(dcel.face_area(dcel.outer_face), -0.5);
+        assert_eq!(dcel.face_area(upper), 0.5);
+        assert!(!dcel.faces.contains(lower));
+    }
+
+    #[test]
+    fn dissolve_by_key() {
+        let square = |x: f64, y: f64, w: f64| vec![
+            Point2::new(x, y), Point2::new(x + w, y), Point2::new(x + w, y + w), Point2::new(x, y + w),
+        ];
+        // Parcels 0 and 1 form a district, with parcel 2 in a hole of parcel 0
+        let polygons = vec![vec![square(0.0, 0.0, 4.0), square(1.0, 1.0, 2.0)], vec![square(4.0, 0.0, 4.0)],
+                            vec![square(1.0, 1.0, 2.0)], vec![square(8.0, 0.0, 4.0)]];
+        let district = |p: &Option<usize>| p.map(|p| if p < 3 { 0 } else { 1 });
+        let mut dcel = DCEL::from_polygon_rings(&polygons, |p| p).unwrap();
+        assert_eq!(dcel.faces.len(), 5);
+
+        dcel.dissolve_by(district);
+        dcel.validate().unwrap();
+        assert_eq!(dcel.faces.len(), 3);
+        let merged = dcel.locate(&Point2::new(2.0, 2.0));
+        assert_eq!(merged, dcel.locate(&Point2::new(6.0, 2.0)));
+        assert!(dcel.faces[merged].inner_components().is_empty());
+        assert_eq!(dcel.face_area(merged), 32.0);
+        assert_eq!(dcel.face_edges(merged).count(), 4);
+        // The corners of the filled hole went with it, as did the straight
+        // joints of the dissolved edge, while the far junctions stay
+        assert_eq!(dcel.vertices.len(), 6);
+        assert_eq!(dcel.vertices.iter().filter(|&(v, _)| dcel.degree(v) == 3).count(), 2);
+
+        // Keeping the hole's contents apart keeps the hole
+        let mut dcel = DCEL::from_polygon_rings(&polygons, |p| p).unwrap();
+        dcel.dissolve_by(|p| p.map(|p| p < 2));
+        dcel.validate().unwrap();
+        let merged = dcel.locate(&Point2::new(6.0, 2.0));
+        assert_eq!(dcel.faces[merged].inner_components().len(), 1);
+        assert_eq!(dcel.face_area(merged), 28.0);
+        assert_eq!(dcel.face_edges(merged).count(), 4);
+    }
+
+    #[test]
+    fn snapshots_fork_cheaply() {
+        let original = unit_square();
+        let before = original.to_text();
+        let mut forks: Vec<DCEL> = (0..100).map(|_| original.snapshot()).collect();
+        for (i, fork) in forks.iter_mut().enumerate() {
+            let diagonal = edge_between(fork, 0, 2);
+            if i % 2 == 0 {
+                fork.flip_edge(diagonal).unwrap();
+            } else {
+                fork.split_edge_in_half(diagonal);
+            }
+            fork.validate().unwrap();
+        }
+        assert_eq!(original.to_text(), before);
+        assert_eq!(forks[0].vertices.len(), 4);
+        assert_eq!(forks[1].vertices.len(), 5);
+        assert_ne!(edge_between(&forks[0], 1, 3), edge_between(&original, 0, 1));
+    }
+}
@@ -0,0 +1,652 @@
+//! The native binary format: a versioned, little-endian image of the three
+//! arenas, including deleted slots and reuse stacks so that ids survive a
+//! round trip.
+//!
+//! The layout is a header (magic, version, and the type tags of the
+//! positions and face payloads), the outer face id, then one section per
+//! arena: the slot count, the reuse stack, and one record per slot. A CRC-32
+//! of everything before it closes the file. Records are written and read
+//! in order through a small buffer, with the checksum kept as they go, so
+//! neither side holds the whole file in memory.
+
+use std::io::{self, Read, Write};
+
+use arena::{Arena, Deleteable};
+use dcel::*;
+use formats::json::Value;
+use formats::FormatError;
+use geometry::{Point2, Point3, Position};
+use observe::Observers;
+use scalar::{Rational, Scalar};
+
+/// The first eight bytes of every file.
+pub const MAGIC: &[u8; 8] = b"DCELBIN\0";
+
+/// The version written by `DCEL::write_to`, and the only one it reads.
+pub const VERSION: u32 = 1;
+
+/// Stands in for `None` wherever an id is optional.
+const NO_ID: u64 = u64::MAX;
+
+/// How many bytes are buffered between the DCEL and the stream.
+const CHUNK: usize = 1 << 16;
+
+/// Types that can be stored in the binary format.
+pub trait BinaryCodec: Sized {
+    /// Names the type in the file header, so that a file isn't read back
+    /// as a different type.
+    fn tag() -> String;
+
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Decodes a value, or returns `None` if the input is malformed.
+    fn decode(input: &mut Decoder) -> Option<Self>;
+}
+
+/// A running CRC-32 (IEEE 802.3).
+struct Crc32 {
+    table: [u32; 256],
+    crc: u32,
+}
+
+impl Crc32 {
+    fn new() -> Crc32 {
+        let mut table = [0u32; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            let mut c = i as u32;
+            for _ in 0..8 {
+                c = if c & 1 == 1 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 };
+            }
+            *entry = c;
+        }
+        return Crc32 { table: table, crc: !0 };
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.crc = self.table[((self.crc ^ b as u32) & 0xff) as usize] ^ (self.crc >> 8);
+        }
+    }
+
+    fn value(&self) -> u32 {
+        return !self.crc;
+    }
+}
+
+/// Buffers encoded records on their way to the stream, keeping the
+/// checksum of everything written.
+struct Encoder<'a, W: Write + 'a> {
+    writer: &'a mut W,
+    out: Vec<u8>,
+    crc: Crc32,
+}
+
+impl<'a, W: Write> Encoder<'a, W> {
+    fn new(writer: &'a mut W) -> Encoder<'a, W> {
+        return Encoder { writer: writer, out: Vec::new(), crc: Crc32::new() };
+    }
+
+    /// Writes out the buffer once it holds a chunk.
+    fn flush_full(&mut self) -> io::Result<()> {
+        if self.out.len() < CHUNK {
+            return Ok(());
+        }
+        return self.flush();
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.crc.update(&self.out);
+        self.writer.write_all(&self.out)?;
+        self.out.clear();
+        return Ok(());
+    }
+}
+
+/// Reads the bytes being decoded from a stream, keeping the checksum of
+/// those taken. The last four bytes of the stream are the checksum itself,
+/// so they're always held back.
+pub struct Decoder<'a> {
+    reader: &'a mut dyn Read,
+    buffer: Vec<u8>,
+    pos: usize,
+    crc: Crc32,
+    error: Option<io::Error>,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(reader: &'a mut dyn Read) -> Decoder<'a> {
+        return Decoder { reader: reader, buffer: Vec::new(), pos: 0, crc: Crc32::new(), error: None };
+    }
+
+    /// Reads ahead until `n` bytes past the cursor are buffered, and returns
+    /// whether the stream held that many. A read error is kept for `finish`.
+    fn fill(&mut self, n: usize) -> bool {
+        if self.buffer.len() - self.pos >= n {
+            return true;
+        }
+        self.buffer.drain(..self.pos);
+        self.pos = 0;
+        while self.buffer.len() < n && self.error.is_none() {
+            // Only read what the stream has, whatever length it claims
+            let want = n.saturating_sub(self.buffer.len()).max(CHUNK);
+            match Read::take(&mut *self.reader, want as u64).read_to_end(&mut self.buffer) {
+                Ok(0) => return false,
+                Ok(_) => {}
+                Err(e) => self.error = Some(e),
+            }
+        }
+        return self.buffer.len() >= n;
+    }
+
+    /// Takes the next `n` bytes.
+    pub fn take(&mut self, n: usize) -> Option<&[u8]> {
+        if !self.fill(n.checked_add(4)?) {
+            return None;
+        }
+        self.pos += n;
+        self.crc.update(&self.buffer[self.pos - n..self.pos]);
+        return Some(&self.buffer[self.pos - n..self.pos]);
+    }
+
+    /// Reads the rest of the stream. Returns whether it ends in a checksum
+    /// of everything before it, and whether nothing else was left untaken.
+    fn finish(&mut self) -> io::Result<(bool, bool)> {
+        let mut untaken = 0;
+        loop {
+            let more = self.fill(CHUNK + 4);
+            if let Some(e) = self.error.take() {
+                return Err(e);
+            }
+            let available = self.buffer.len() - self.pos;
+            if available < 4 {
+                return Ok((false, false));
+            }
+            let skipped = available - 4;
+            self.crc.update(&self.buffer[self.pos..self.pos + skipped]);
+            self.pos += skipped;
+            untaken += skipped;
+            if !more {
+                break;
+            }
+        }
+        let mut checksum = [0; 4];
+        checksum.copy_from_slice(&self.buffer[self.pos..]);
+        return Ok((u32::from_le_bytes(checksum) == self.crc.value(), untaken == 0));
+    }
+}
+
+macro_rules! int_codec {
+    ($T:ident) => {
+        impl BinaryCodec for $T {
+            fn tag() -> String {
+                return stringify!($T).to_string();
+            }
+
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn decode(input: &mut Decoder) -> Option<$T> {
+                let mut bytes = [0; ::std::mem::size_of::<$T>()];
+                let len = bytes.len();
+                bytes.copy_from_slice(input.take(len)?);
+                return Some($T::from_le_bytes(bytes));
+            }
+        }
+    }
+}
+
+int_codec!(u8);
+int_codec!(u32);
+int_codec!(u64);
+int_codec!(i32);
+int_codec!(i64);
+int_codec!(i128);
+int_codec!(f32);
+int_codec!(f64);
+
+impl BinaryCodec for usize {
+    fn tag() -> String {
+        return "usize".to_string();
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode(out);
+    }
+
+    fn decode(input: &mut Decoder) -> Option<usize> {
+        let value = u64::decode(input)?;
+        if value > usize::MAX as u64 {
+            return None;
+        }
+        return Some(value as usize);
+    }
+}
+
+impl BinaryCodec for bool {
+    fn tag() -> String {
+        return "bool".to_string();
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    fn decode(input: &mut Decoder) -> Option<bool> {
+        match u8::decode(input)? {
+            0 => return Some(false),
+            1 => return Some(true),
+            _ => return None,
+        }
+    }
+}
+
+impl BinaryCodec for () {
+    fn tag() -> String {
+        return "()".to_string();
+    }
+
+    fn encode(&self, _out: &mut Vec<u8>) {}
+
+    fn decode(_input: &mut Decoder) -> Option<()> {
+        return Some(());
+    }
+}
+
+impl BinaryCodec for Rational {
+    fn tag() -> String {
+        return "Rational".to_string();
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.numerator().encode(out);
+        self.denominator().encode(out);
+    }
+
+    fn decode(input: &mut Decoder) -> Option<Rational> {
+        let num = i128::decode(input)?;
+        let den = i128::decode(input)?;
+        if den <= 0 {
+            return None;
+        }
+        return Some(Rational::new(num, den));
+    }
+}
+
+impl BinaryCodec for String {
+    fn tag() -> String {
+        return "String".to_string();
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u64).encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(input: &mut Decoder) -> Option<String> {
+        let len = usize::decode(input)?;
+        return String::from_utf8(input.take(len)?.to_vec()).ok();
+    }
+}
+
+/// JSON values, such as GeoJSON properties, are stored as their text.
+impl BinaryCodec for Value {
+    fn tag() -> String {
+        return "json".to_string();
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.to_string().encode(out);
+    }
+
+    fn decode(input: &mut Decoder) -> Option<Value> {
+        return Value::parse(&String::decode(input)?).ok();
+    }
+}
+
+impl<T: BinaryCodec> BinaryCodec for Option<T> {
+    fn tag() -> String {
+        return format!("Option<{}>", T::tag());
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        match *self {
+            Some(ref value) => {
+                out.push(1);
+                value.encode(out);
+            }
+            None => out.push(0),
+        }
+    }
+
+    fn decode(input: &mut Decoder) -> Option<Option<T>> {
+        match u8::decode(input)? {
+            0 => return Some(None),
+            1 => return Some(Some(T::decode(input)?)),
+            _ => return None,
+        }
+    }
+}
+
+impl<T: Scalar + BinaryCodec> BinaryCodec for Point2<T> {
+    fn tag() -> String {
+        return format!("Point2<{}>", T::tag());
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.x.encode(out);
+        self.y.encode(out);
+    }
+
+    fn decode(input: &mut Decoder) -> Option<Point2<T>> {
+        return Some(Point2::new(T::decode(input)?, T::decode(input)?));
+    }
+}
+
+impl<T: Scalar + BinaryCodec> BinaryCodec for Point3<T> {
+    fn tag() -> String {
+        return format!("Point3<{}>", T::tag());
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.x.encode(out);
+        self.y.encode(out);
+        self.z.encode(out);
+    }
+
+    fn decode(input: &mut Decoder) -> Option<Point3<T>> {
+        return Some(Point3::new(T::decode(input)?, T::decode(input)?, T::decode(input)?));
+    }
+}
+
+fn encode_id<I: Into<usize>>(id: Option<I>, out: &mut Vec<u8>) {
+    match id {
+        Some(id) => (id.into() as u64).encode(out),
+        None => NO_ID.encode(out),
+    }
+}
+
+fn decode_id<I: From<usize>>(input: &mut Decoder) -> Option<Option<I>> {
+    let raw = u64::decode(input)?;
+    if raw == NO_ID {
+        return Some(None);
+    } else if raw > usize::MAX as u64 {
+        return None;
+    }
+    return Some(Some(I::from(raw as usize)));
+}
+
+fn decode_required<I: From<usize>>(input: &mut Decoder) -> Option<I> {
+    return decode_id(input)?;
+}
+
+/// Writes an arena's slot count and reuse stack, then every slot.
+fn encode_arena<T, I, W, E>(arena: &Arena<T, I>, out: &mut Encoder<W>, mut encode_slot: E) -> io::Result<()>
+    where T: Deleteable, I: Copy + From<usize> + Into<usize>, W: Write, E: FnMut(&T, &mut Vec<u8>)
+{
+    arena.slot_count().encode(&mut out.out);
+    arena.reuse_stack().len().encode(&mut out.out);
+    for &id in arena.reuse_stack() {
+        encode_id(Some(id), &mut out.out);
+        out.flush_full()?;
+    }
+    for (_, slot) in arena.slots() {
+        out.out.push(slot.is_deleted() as u8);
+        encode_slot(slot, &mut out.out);
+        out.flush_full()?;
+    }
+    return Ok(());
+}
+
+fn decode_arena<T, I, D>(input: &mut Decoder, mut decode_slot: D) -> Option<Arena<T, I>>
+    where T: Deleteable + Clone, I: Copy + From<usize> + Into<usize>, D: FnMut(&mut Decoder, bool) -> Option<T>
+{
+    // The counts aren't trusted for preallocation; a bogus one runs into the
+    // end of the stream instead
+    let slots = usize::decode(input)?;
+    let reused = usize::decode(input)?;
+    let mut reuse_stack = Vec::new();
+    for _ in 0..reused {
+        reuse_stack.push(decode_required(input)?);
+    }
+    let mut data = Vec::new();
+    for _ in 0..slots {
+        let deleted = bool::decode(input)?;
+        data.push(decode_slot(input, deleted)?);
+    }
+    return Some(Arena::from_parts(data, reuse_stack));
+}
+
+impl<P: Position + BinaryCodec, F: BinaryCodec + Clone> DCEL<P, F> {
+    /// Writes this DCEL, deleted slots and all, in the native binary format.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), FormatError> {
+        let mut out = Encoder::new(writer);
+        out.out.extend_from_slice(MAGIC);
+        VERSION.encode(&mut out.out);
+        P::tag().encode(&mut out.out);
+        F::tag().encode(&mut out.out);
+        encode_id(Some(self.outer_face), &mut out.out);
+
+        encode_arena(&self.vertices, &mut out, |v, out| {
+            encode_id(v.outgoing_edge(), out);
+            v.coord().encode(out);
+        })?;
+        encode_arena(&self.edges, &mut out, |e, out| {
+            encode_id(Some(e.origin()), out);
+            encode_id(e.twin(), out);
+            encode_id(e.next(), out);
+            encode_id(e.prev(), out);
+            encode_id(Some(e.face()), out);
+        })?;
+        encode_arena(&self.faces, &mut out, |f, out| {
+            encode_id(f.incident_edge(), out);
+            f.inner_components().len().encode(out);
+            for &e in f.inner_components() {
+                encode_id(Some(e), out);
+            }
+            f.data().encode(out);
+        })?;
+
+        out.flush()?;
+        let checksum = out.crc.value();
+        out.writer.write_all(&checksum.to_le_bytes())?;
+        return Ok(());
+    }
+
+    /// Reads a DCEL written by `write_to`, checking the header, the checksum
+    /// and, with `validate`, the structure of the result.
+    pub fn read_from<R: Read>(mut reader: R) -> Result<DCEL<P, F>, FormatError> {
+        let mut input = Decoder::new(&mut reader);
+        if input.take(MAGIC.len()) != Some(&MAGIC[..]) {
+            return Err(FormatError::Corrupt("not a DCEL binary file".to_string()));
+        }
+        let decoded = decode_file(&mut input);
+
+        // Damage anywhere is reported as such, whatever decoding made of it
+        let (intact, at_end) = input.finish()?;
+        if !intact {
+            return Err(FormatError::Corrupt("checksum mismatch".to_string()));
+        }
+        let dcel = decoded?;
+        if !at_end {
+            return Err(truncated());
+        }
+        dcel.validate()?;
+        return Ok(dcel);
+    }
+}
+
+fn truncated() -> FormatError {
+    return FormatError::Corrupt("truncated or malformed data".to_string());
+}
+
+/// Decodes everything between the magic number and the checksum.
+fn decode_file<P: Position + BinaryCodec, F: BinaryCodec + Clone>(input: &mut Decoder) -> Result<DCEL<P, F>, FormatError> {
+    let version = u32::decode(input).ok_or_else(truncated)?;
+    if version != VERSION {
+        return Err(FormatError::Unsupported(format!("unsupported format version {}", version)));
+    }
+    let position_tag = String::decode(input).ok_or_else(truncated)?;
+    let payload_tag = String::decode(input).ok_or_else(truncated)?;
+    if position_tag != P::tag() || payload_tag != F::tag() {
+        return Err(FormatError::Unsupported(format!(
+            "file holds DCEL<{}, {}>, expected DCEL<{}, {}>", position_tag, payload_tag, P::tag(), F::tag())));
+    }
+    return decode_dcel(input).ok_or_else(truncated);
+}
+
+fn decode_dcel<P: Position + BinaryCodec, F: BinaryCodec + Clone>(input: &mut Decoder) -> Option<DCEL<P, F>> {
+    let outer_face = decode_required(input)?;
+    let vertices = decode_arena(input, |input, deleted| {
+        let outgoing = decode_id(input)?;
+        return Some(Vertex::from_raw(P::decode(input)?, outgoing, deleted));
+    })?;
+    let edges = decode_arena(input, |input, deleted| {
+        let origin = decode_required(input)?;
+        let twin = decode_id(input)?;
+        let next = decode_id(input)?;
+        let prev = decode_id(input)?;
+        return Some(HalfEdge::from_raw(origin, twin, next, prev, decode_required(input)?, deleted));
+    })?;
+    let faces = decode_arena(input, |input, deleted| {
+        let incident = decode_id(input)?;
+        let count = usize::decode(input)?;
+        let mut inner = Vec::new();
+        for _ in 0..count {
+            inner.push(decode_required(input)?);
+        }
+        return Some(Face::from_raw(incident, inner, F::decode(input)?, deleted));
+    })?;
+
+    return Some(DCEL {
+        vertices: vertices,
+        edges: edges,
+        faces: faces,
+        outer_face: outer_face,
+        observers: Observers::default(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use formats::geojson;
+
+    fn fan() -> DCEL {
+        let points = [Point2::new(0.0, 0.0), Point2::new(2.0, 0.0), Point2::new(2.0, 2.0),
+                      Point2::new(0.0, 2.0), Point2::new(1.0, 3.0)];
+        return DCEL::from_polygons(&points, &[vec![0, 1, 2], vec![0, 2, 3], vec![3, 2, 4]]).unwrap();
+    }
+
+    #[test]
+    fn round_trip_keeps_ids() {
+        let mut dcel = fan();
+        let diagonal = dcel.face_edges(FaceId::from(1)).nth(2).unwrap();
+        dcel.remove_inner_edge(diagonal);
+
+        let mut bytes = Vec::new();
+        dcel.write_to(&mut bytes).unwrap();
+        let mut again: DCEL = DCEL::read_from(&bytes[..]).unwrap();
+        assert_eq!(again.vertices.ids(), dcel.vertices.ids());
+        assert_eq!(again.edges.ids(), dcel.edges.ids());
+        assert_eq!(again.faces.ids(), dcel.faces.ids());
+        for (id, e) in dcel.edges.iter() {
+            assert_eq!(&again.edges[id], e);
+        }
+
+        // The next additions reuse the same slots in both copies
+        assert_eq!(again.add_face(), dcel.add_face());
+        assert_eq!(again.add_vertex(Point2::new(5.0, 5.0)), dcel.add_vertex(Point2::new(5.0, 5.0)));
+    }
+
+    #[test]
+    fn round_trip_payloads() {
+        let text = r#"{"type": "Feature", "properties": {"zone": 7}, "geometry": {"type": "Polygon",
+                      "coordinates": [[[0, 0], [1, 0], [0, 1], [0, 0]]]}}"#;
+        let dcel: DCEL<Point2<Rational>, _> = geojson::read(text.as_bytes()).unwrap();
+        let mut bytes = Vec::new();
+        dcel.write_to(&mut bytes).unwrap();
+        let again: DCEL<Point2<Rational>, Option<Value>> = DCEL::read_from(&bytes[..]).unwrap();
+        assert_eq!(again.face_data(FaceId::from(1)), dcel.face_data(FaceId::from(1)));
+        assert_eq!(again.vertices[VertexId::from(2)].coord(), dcel.vertices[VertexId::from(2)].coord());
+    }
+
+    /// Hands out a few bytes per read, as a pipe might.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(7);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            return Ok(n);
+        }
+    }
+
+    #[test]
+    fn streams_across_chunks() {
+        // A grid big enough to span several chunks either way
+        let n = 40;
+        let points: Vec<Point2> = (0..(n + 1) * (n + 1))
+            .map(|i| Point2::new((i % (n + 1)) as f64, (i / (n + 1)) as f64))
+            .collect();
+        let squares: Vec<Vec<usize>> = (0..n * n)
+            .map(|i| {
+                let corner = i / n * (n + 1) + i % n;
+                return vec![corner, corner + 1, corner + n + 2, corner + n + 1];
+            })
+            .collect();
+        let dcel = DCEL::from_polygons(&points, &squares).unwrap();
+        let mut bytes = Vec::new();
+        dcel.write_to(&mut bytes).unwrap();
+        assert!(bytes.len() > 2 * CHUNK);
+
+        let again: DCEL = DCEL::read_from(Trickle(&bytes)).unwrap();
+        assert_eq!(again.to_text(), dcel.to_text());
+
+        // Trailing bytes are caught even after a valid checksum
+        let mut longer = bytes.clone();
+        longer.extend_from_slice(&[0; 4]);
+        let checksum = {
+            let mut crc = Crc32::new();
+            crc.update(&longer[..longer.len() - 4]);
+            crc.value()
+        };
+        let len = longer.len();
+        longer[len - 4..].copy_from_slice(&checksum.to_le_bytes());
+        match DCEL::<Point2>::read_from(Trickle(&longer)) {
+            Err(FormatError::Corrupt(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let mut bytes = Vec::new();
+        fan().write_to(&mut bytes).unwrap();
+
+        let mut flipped = bytes.clone();
+        flipped[30] ^= 1;
+        match DCEL::<Point2>::read_from(&flipped[..]) {
+            Err(FormatError::Corrupt(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match DCEL::<Point3>::read_from(&bytes[..]) {
+            Err(FormatError::Unsupported(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(DCEL::<Point2>::read_from(&bytes[..20]).is_err());
+
+        // A well-formed file describing a broken DCEL fails validation
+        let mut broken = fan();
+        let e = broken.edges.ids()[0];
+        let twin = broken.edges[e].twin().unwrap();
+        broken.make_next(e, twin);
+        let mut bytes = Vec::new();
+        broken.write_to(&mut bytes).unwrap();
+        match DCEL::<Point2>::read_from(&bytes[..]) {
+            Err(FormatError::Invalid(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
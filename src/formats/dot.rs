@@ -0,0 +1,160 @@
+//! Graphviz DOT export of the pointer structure of a DCEL, for debugging
+//! topology rather than geometry.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use dcel::{DCEL, FaceId, HalfEdgeId};
+use geometry::Position;
+
+/// Controls what `DCEL::to_dot_with` draws.
+pub struct DotOptions {
+    /// Group the half-edges of each face into a cluster.
+    pub face_clusters: bool,
+    /// Draw `twin` pointers, as dotted lines between twins.
+    pub twins: bool,
+    /// Draw `next` pointers, as dashed blue arcs.
+    pub next: bool,
+    /// Draw `prev` pointers, as dashed grey arcs.
+    pub prev: bool,
+}
+
+impl Default for DotOptions {
+    fn default() -> DotOptions {
+        return DotOptions {
+            face_clusters: true,
+            twins: true,
+            next: true,
+            prev: false,
+        };
+    }
+}
+
+impl<P: Position, F> DCEL<P, F> {
+    /// Renders the half-edge graph as a DCEL digraph, with the default options.
+    pub fn to_dot(&self) -> String {
+        return self.to_dot_with(&DotOptions::default());
+    }
+
+    /// Renders the half-edge graph in DOT. Vertices are round nodes. Each
+    /// half-edge is a small node labelled with its id, sitting on a solid
+    /// arc from its origin to its destination, so that its `twin`, `next`
+    /// and `prev` pointers can be drawn as arcs between half-edges. Pointers
+    /// to missing or deleted elements lead to red nodes.
+    pub fn to_dot_with(&self, options: &DotOptions) -> String {
+        let mut dot = String::new();
+        let mut missing = HashSet::new();
+        let mut edge_ref = |e: HalfEdgeId, dcel: &DCEL<P, F>| -> String {
+            if dcel.edges.contains(e) {
+                return format!("e{}", usize::from(e));
+            }
+            missing.insert(format!("e{}", usize::from(e)));
+            return format!("missing_e{}", usize::from(e));
+        };
+
+        writeln!(dot, "digraph dcel {{").unwrap();
+        writeln!(dot, "  node [fontname=\"Helvetica\", fontsize=10];").unwrap();
+        for (v, _) in self.vertices.iter() {
+            writeln!(dot, "  v{0} [label=\"v{0}\", shape=circle];", usize::from(v)).unwrap();
+        }
+
+        // Half-edge nodes, grouped by face if asked
+        let half_edge_node = |e: HalfEdgeId| {
+            format!("e{0} [label=\"e{0}\", shape=box, style=rounded, height=0.2];", usize::from(e))
+        };
+        if options.face_clusters {
+            for (face, _) in self.faces.iter() {
+                writeln!(dot, "  subgraph cluster_f{} {{", usize::from(face)).unwrap();
+                writeln!(dot, "    label=\"{}\"; style=dashed; color=grey;", face_label(self, face)).unwrap();
+                for (e, edge) in self.edges.iter() {
+                    if edge.face() == face {
+                        writeln!(dot, "    {}", half_edge_node(e)).unwrap();
+                    }
+                }
+                writeln!(dot, "  }}").unwrap();
+            }
+        }
+        for (e, edge) in self.edges.iter() {
+            if !options.face_clusters || !self.faces.contains(edge.face()) {
+                writeln!(dot, "  {}", half_edge_node(e)).unwrap();
+            }
+        }
+
+        for (e, edge) in self.edges.iter() {
+            writeln!(dot, "  v{} -> e{} [arrowhead=none];", usize::from(edge.origin()), usize::from(e)).unwrap();
+            if let Some(twin) = edge.twin().filter(|&t| self.edges.contains(t)) {
+                writeln!(dot, "  e{} -> v{};", usize::from(e), usize::from(self.edges[twin].origin())).unwrap();
+            }
+        }
+        for (e, edge) in self.edges.iter() {
+            if options.twins {
+                if let Some(twin) = edge.twin() {
+                    // Each pair once, unless the pointers disagree
+                    let paired = self.edges.contains(twin) && self.edges[twin].twin() == Some(e);
+                    if !paired || e < twin {
+                        writeln!(dot, "  e{} -> {} [style=dotted, dir={}, constraint=false];", usize::from(e),
+                                 edge_ref(twin, self), if paired { "both" } else { "forward" }).unwrap();
+                    }
+                }
+            }
+            if options.next {
+                if let Some(next) = edge.next() {
+                    writeln!(dot, "  e{} -> {} [style=dashed, color=blue, constraint=false];",
+                             usize::from(e), edge_ref(next, self)).unwrap();
+                }
+            }
+            if options.prev {
+                if let Some(prev) = edge.prev() {
+                    writeln!(dot, "  e{} -> {} [style=dashed, color=grey, constraint=false];",
+                             usize::from(e), edge_ref(prev, self)).unwrap();
+                }
+            }
+        }
+
+        let mut missing: Vec<String> = missing.into_iter().collect();
+        missing.sort();
+        for name in missing {
+            writeln!(dot, "  missing_{0} [label=\"{0}?\", color=red, fontcolor=red];", name).unwrap();
+        }
+        writeln!(dot, "}}").unwrap();
+        return dot;
+    }
+}
+
+fn face_label<P, F>(dcel: &DCEL<P, F>, face: FaceId) -> String {
+    if face == dcel.outer_face {
+        return format!("f{} (outer)", usize::from(face));
+    }
+    return format!("f{}", usize::from(face));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geometry::Point2;
+
+    #[test]
+    fn draws_pointer_structure() {
+        let points = [Point2::new(0.0, 0.0), Point2::new(1.0, 0.0), Point2::new(0.0, 1.0)];
+        let mut dcel: DCEL = DCEL::from_polygons(&points, &[[0, 1, 2]]).unwrap();
+        let dot = dcel.to_dot();
+        assert!(dot.starts_with("digraph dcel {"));
+        assert_eq!(dot.matches("shape=circle").count(), 3);
+        assert_eq!(dot.matches("shape=box").count(), 6);
+        assert_eq!(dot.matches("subgraph cluster_f").count(), 2);
+        assert!(dot.contains("label=\"f0 (outer)\""));
+        assert_eq!(dot.matches("style=dotted, dir=both").count(), 3);
+        assert_eq!(dot.matches("color=blue").count(), 6);
+        assert!(dot.contains("v0 -> e0 [arrowhead=none];\n  e0 -> v1;"));
+
+        // A pointer to a deleted half-edge shows up in red
+        let e = dcel.face_edges(FaceId::from(1)).next().unwrap();
+        let next = dcel.edges[e].next().unwrap();
+        dcel.edges.remove(next);
+        let options = DotOptions { face_clusters: false, ..DotOptions::default() };
+        let dot = dcel.to_dot_with(&options);
+        assert!(!dot.contains("subgraph"));
+        assert!(dot.contains(&format!("e{} -> missing_e{}", usize::from(e), usize::from(next))));
+        assert!(dot.contains("color=red"));
+    }
+}
@@ -0,0 +1,208 @@
+//! GeoJSON `Polygon` and `MultiPolygon` geometries, alone, as features or
+//! in a feature collection.
+
+use std::io::{Read, Write};
+
+use dcel::{DCEL, FaceId, HalfEdgeId};
+use formats::json::Value;
+use formats::FormatError;
+use geometry::Point2;
+use scalar::Scalar;
+
+/// Reads GeoJSON polygons into a planar subdivision. Boundaries shared by
+/// neighbouring polygons become twin edges and holes become inner
+/// components. Each face carries the `properties` of the feature it came
+/// from (`Null` for bare geometries), while the outer face and any gaps
+/// between polygons carry `None`.
+pub fn read<T: Scalar, R: Read>(mut reader: R) -> Result<DCEL<Point2<T>, Option<Value>>, FormatError> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let root = Value::parse(&text)?;
+
+    let mut polygons: Vec<Vec<Vec<Point2<T>>>> = Vec::new();
+    let mut properties: Vec<Value> = Vec::new();
+    collect(&root, &Value::Null, &mut polygons, &mut properties)?;
+
+    let dcel = DCEL::from_polygon_rings(&polygons, |p| p.map(|i| properties[i].clone()))?;
+    return Ok(dcel);
+}
+
+/// Gathers the polygons of a GeoJSON object, tagging each with `properties`.
+fn collect<T: Scalar>(object: &Value, properties: &Value, polygons: &mut Vec<Vec<Vec<Point2<T>>>>,
+                      tags: &mut Vec<Value>) -> Result<(), FormatError> {
+    let kind = object.get("type").and_then(Value::as_str).ok_or_else(|| unsupported("object without a `type`"))?;
+    match kind {
+        "FeatureCollection" => {
+            let features = object.get("features").and_then(Value::as_array)
+                .ok_or_else(|| unsupported("feature collection without `features`"))?;
+            for feature in features {
+                collect(feature, properties, polygons, tags)?;
+            }
+        }
+        "Feature" => {
+            let own = object.get("properties").unwrap_or(&Value::Null);
+            match object.get("geometry") {
+                Some(&Value::Null) | None => {}
+                Some(geometry) => collect(geometry, own, polygons, tags)?,
+            }
+        }
+        "Polygon" => {
+            polygons.push(polygon(coordinates(object)?)?);
+            tags.push(properties.clone());
+        }
+        "MultiPolygon" => {
+            for part in coordinates(object)? {
+                let part = part.as_array().ok_or_else(|| unsupported("malformed MultiPolygon"))?;
+                polygons.push(polygon(part)?);
+                tags.push(properties.clone());
+            }
+        }
+        other => return Err(unsupported(&format!("unsupported geometry type `{}`", other))),
+    }
+    return Ok(());
+}
+
+fn unsupported(message: &str) -> FormatError {
+    return FormatError::Unsupported(message.to_string());
+}
+
+fn coordinates(object: &Value) -> Result<&[Value], FormatError> {
+    return object.get("coordinates").and_then(Value::as_array).ok_or_else(|| unsupported("geometry without `coordinates`"));
+}
+
+/// Converts the rings of one polygon, exterior first.
+fn polygon<T: Scalar>(rings: &[Value]) -> Result<Vec<Vec<Point2<T>>>, FormatError> {
+    let mut result = Vec::new();
+    for ring in rings {
+        let ring = ring.as_array().ok_or_else(|| unsupported("malformed polygon ring"))?;
+        let mut points = Vec::new();
+        for position in ring {
+            let position = position.as_array().unwrap_or(&[]);
+            let x = position.first().and_then(Value::as_number::<T>);
+            let y = position.get(1).and_then(Value::as_number::<T>);
+            match (x, y) {
+                (Some(x), Some(y)) => points.push(Point2::new(x, y)),
+                _ => return Err(unsupported("invalid position")),
+            }
+        }
+        result.push(points);
+    }
+    return Ok(result);
+}
+
+/// Writes inner faces as `Polygon` features of a feature collection, one
+/// feature per line. Outer boundaries run counter-clockwise and holes
+/// clockwise, as RFC 7946 asks. `properties` picks the faces to write and
+/// their properties from each face's payload: `None` leaves the face out,
+/// such as a gap inside a hole, and `Some(None)` writes `null`. For a DCEL
+/// read by `read`, `|data| data.clone().map(Some)` writes back just the
+/// `feature_faces`.
+pub fn write<T, F, W, G>(dcel: &DCEL<Point2<T>, F>, properties: G, mut writer: W) -> Result<(), FormatError>
+    where T: Scalar, W: Write, G: Fn(&F) -> Option<Option<Value>>
+{
+    write!(writer, "{{\"type\":\"FeatureCollection\",\"features\":[")?;
+    let mut first = true;
+    for (face, _) in dcel.faces.iter() {
+        if face == dcel.outer_face || dcel.faces[face].incident_edge().is_none() {
+            continue;
+        }
+        let properties = match properties(dcel.face_data(face)) {
+            Some(properties) => properties.unwrap_or(Value::Null),
+            None => continue,
+        };
+        let rings: Vec<Value> = dcel.face_cycles(face).into_iter().map(|start| ring(dcel, start)).collect();
+        let feature = Value::Object(vec![
+            ("type".to_string(), Value::String("Feature".to_string())),
+            ("properties".to_string(), properties),
+            ("geometry".to_string(), Value::Object(vec![
+                ("type".to_string(), Value::String("Polygon".to_string())),
+                ("coordinates".to_string(), Value::Array(rings)),
+            ])),
+        ]);
+        write!(writer, "{}\n{}", if first { "" } else { "," }, feature)?;
+        first = false;
+    }
+    writeln!(writer, "\n]}}")?;
+    return Ok(());
+}
+
+/// The positions around a cycle, closed by repeating the first.
+fn ring<T: Scalar, F>(dcel: &DCEL<Point2<T>, F>, start: HalfEdgeId) -> Value {
+    let mut positions: Vec<Value> = dcel.cycle_edges(start).map(|e| position(dcel, e)).collect();
+    positions.push(position(dcel, start));
+    return Value::Array(positions);
+}
+
+fn position<T: Scalar, F>(dcel: &DCEL<Point2<T>, F>, edge: HalfEdgeId) -> Value {
+    let p = dcel.vertices[dcel.edges[edge].origin()].coord();
+    return Value::Array(vec![Value::from_scalar(p.x), Value::from_scalar(p.y)]);
+}
+
+/// Returns the faces of a DCEL read from GeoJSON that came from features,
+/// leaving out the outer face and any gaps.
+pub fn feature_faces<T: Scalar>(dcel: &DCEL<Point2<T>, Option<Value>>) -> Vec<FaceId> {
+    return dcel.faces.iter().filter(|f| f.1.data().is_some()).map(|f| f.0).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scalar::Rational;
+
+    // Two squares sharing an edge, the left one with a hole, plus a
+    // triangle given as a one-part MultiPolygon sitting in that hole.
+    const PARCELS: &str = r#"{"type": "FeatureCollection", "features": [
+        {"type": "Feature", "properties": {"name": "left"}, "geometry": {"type": "Polygon", "coordinates": [
+            [[0, 0], [4, 0], [4, 4], [0, 4], [0, 0]],
+            [[1, 1], [1, 3], [3, 3], [3, 1], [1, 1]]]}},
+        {"type": "Feature", "properties": {"name": "right"}, "geometry": {"type": "Polygon", "coordinates": [
+            [[4, 0], [8, 0], [8, 4], [4, 4], [4, 0]]]}},
+        {"type": "Feature", "properties": null, "geometry": {"type": "MultiPolygon", "coordinates": [
+            [[[1, 1], [3, 1], [2, 2.5], [1, 1]]]]}}
+    ]}"#;
+
+    #[test]
+    fn reads_shared_edges_and_holes() {
+        let dcel: DCEL<Point2<Rational>, _> = read(PARCELS.as_bytes()).unwrap();
+        assert_eq!(dcel.vertices.len(), 11);
+        assert_eq!(feature_faces(&dcel).len(), 3);
+
+        // The squares share one edge, and the gap left in the hole beside the
+        // triangle becomes a face of its own
+        let left = dcel.locate(&Point2::new(Rational::new(1, 2), Rational::new(1, 2)));
+        let right = dcel.locate(&Point2::new(Rational::from(6), Rational::from(2)));
+        let gap = dcel.locate(&Point2::new(Rational::from(2), Rational::new(11, 4)));
+        assert_eq!(dcel.face_data(left).as_ref().unwrap().get("name").unwrap().as_str(), Some("left"));
+        assert_eq!(dcel.face_data(right).as_ref().unwrap().get("name").unwrap().as_str(), Some("right"));
+        assert_eq!(dcel.face_data(gap), &None);
+        assert_eq!(dcel.faces[left].inner_components().len(), 1);
+        assert_eq!(dcel.faces.len(), 5);
+
+        let shared = dcel.face_edges(left).filter(|&e| dcel.edges[dcel.edges[e].twin().unwrap()].face() == right).count();
+        assert_eq!(shared, 1);
+    }
+
+    #[test]
+    fn round_trip() {
+        let dcel: DCEL<Point2, _> = read(PARCELS.as_bytes()).unwrap();
+        let mut out = Vec::new();
+        write(&dcel, |data| data.clone().map(Some), &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.lines().count(), 5);
+        assert!(text.contains(r#""properties":{"name":"left"},"geometry":{"type":"Polygon","coordinates":[[["#));
+        assert_eq!(text.matches(r#""properties":null"#).count(), 1);
+
+        // The gap in the hole isn't a feature, so it isn't written as one
+        let again: DCEL<Point2, _> = read(text.as_bytes()).unwrap();
+        assert_eq!(feature_faces(&again).len(), feature_faces(&dcel).len());
+        assert_eq!(again.vertices.len(), dcel.vertices.len());
+        assert_eq!(again.edges.len(), dcel.edges.len());
+        assert_eq!(again.faces.len(), dcel.faces.len());
+    }
+
+    #[test]
+    fn rejects_other_geometries() {
+        let point = r#"{"type": "Point", "coordinates": [0, 0]}"#;
+        assert!(read::<f64, _>(point.as_bytes()).is_err());
+    }
+}
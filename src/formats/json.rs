@@ -0,0 +1,344 @@
+//! A small JSON value type with a parser and a compact writer, enough for
+//! GeoJSON. Numbers keep their source text so coordinates can be read into
+//! any `Scalar` without passing through `f64`.
+
+use std::fmt;
+use std::fmt::Write;
+
+use formats::{parse_error, FormatError};
+use scalar::Scalar;
+
+/// A JSON value. Object members keep their order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    /// A number, as written in the source.
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Parses a complete JSON document.
+    pub fn parse(text: &str) -> Result<Value, FormatError> {
+        let mut parser = Parser { text: text.as_bytes(), pos: 0, line: 1 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos < parser.text.len() {
+            return Err(parser.error("trailing characters after JSON value"));
+        }
+        return Ok(value);
+    }
+
+    /// Returns a number holding `value`. Values whose `Display` isn't valid
+    /// JSON (such as the fractions of `Rational`) are written through `f64`.
+    pub fn from_scalar<T: Scalar>(value: T) -> Value {
+        let text = value.to_string();
+        if is_number(&text) {
+            return Value::Number(text);
+        }
+        return Value::Number(value.to_f64().to_string());
+    }
+
+    /// Looks up a member of an object.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match *self {
+            Value::Object(ref members) => return members.iter().find(|m| m.0 == key).map(|m| &m.1),
+            _ => return None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref s) => return Some(s),
+            _ => return None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match *self {
+            Value::Array(ref items) => return Some(items),
+            _ => return None,
+        }
+    }
+
+    /// Parses a number into any type with a `FromStr`, such as a `Scalar`.
+    pub fn as_number<T: ::std::str::FromStr>(&self) -> Option<T> {
+        match *self {
+            Value::Number(ref text) => return text.parse().ok(),
+            _ => return None,
+        }
+    }
+}
+
+/// Writes the value compactly, on a single line.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Null => return f.write_str("null"),
+            Value::Bool(b) => return write!(f, "{}", b),
+            Value::Number(ref text) => return f.write_str(text),
+            Value::String(ref s) => return write_string(f, s),
+            Value::Array(ref items) => {
+                f.write_char('[')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                return f.write_char(']');
+            }
+            Value::Object(ref members) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                return f.write_char('}');
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    return f.write_char('"');
+}
+
+/// Determines if `text` follows the JSON number grammar.
+fn is_number(text: &str) -> bool {
+    let mut parser = Parser { text: text.as_bytes(), pos: 0, line: 1 };
+    return parser.number().is_ok() && parser.pos == text.len();
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> FormatError {
+        return parse_error(self.line, message);
+    }
+
+    fn peek(&self) -> Option<u8> {
+        return self.text.get(self.pos).cloned();
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            match c {
+                b'\n' => self.line += 1,
+                b' ' | b'\t' | b'\r' => {}
+                _ => return,
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), FormatError> {
+        self.skip_whitespace();
+        if self.peek() != Some(c) {
+            return Err(self.error(&format!("expected `{}`", c as char)));
+        }
+        self.pos += 1;
+        return Ok(());
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value, FormatError> {
+        if !self.text[self.pos..].starts_with(word.as_bytes()) {
+            return Err(self.error("invalid literal"));
+        }
+        self.pos += word.len();
+        return Ok(value);
+    }
+
+    fn value(&mut self) -> Result<Value, FormatError> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => return Err(self.error("unexpected end of input")),
+            Some(b'n') => return self.literal("null", Value::Null),
+            Some(b't') => return self.literal("true", Value::Bool(true)),
+            Some(b'f') => return self.literal("false", Value::Bool(false)),
+            Some(b'"') => return Ok(Value::String(self.string()?)),
+            Some(b'[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(b']') => {
+                            self.pos += 1;
+                            return Ok(Value::Array(items));
+                        }
+                        _ => return Err(self.error("expected `,` or `]`")),
+                    }
+                }
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                let mut members = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(b'}') {
+                    self.pos += 1;
+                    return Ok(Value::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() != Some(b'"') {
+                        return Err(self.error("expected a member name"));
+                    }
+                    let key = self.string()?;
+                    self.expect(b':')?;
+                    members.push((key, self.value()?));
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(b'}') => {
+                            self.pos += 1;
+                            return Ok(Value::Object(members));
+                        }
+                        _ => return Err(self.error("expected `,` or `}`")),
+                    }
+                }
+            }
+            Some(_) => {
+                let start = self.pos;
+                self.number()?;
+                let text = String::from_utf8(self.text[start..self.pos].to_vec()).unwrap();
+                return Ok(Value::Number(text));
+            }
+        }
+    }
+
+    fn digits(&mut self) -> Result<(), FormatError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(self.error("invalid number"));
+        }
+        return Ok(());
+    }
+
+    fn number(&mut self) -> Result<(), FormatError> {
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        if self.peek() == Some(b'0') {
+            self.pos += 1;
+        } else {
+            self.digits()?;
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            self.digits()?;
+        }
+        if let Some(b'e') | Some(b'E') = self.peek() {
+            self.pos += 1;
+            if let Some(b'+') | Some(b'-') = self.peek() {
+                self.pos += 1;
+            }
+            self.digits()?;
+        }
+        return Ok(());
+    }
+
+    fn string(&mut self) -> Result<String, FormatError> {
+        self.pos += 1;
+        let mut bytes = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some(b'"') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let escaped = match self.peek() {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            let code = self.text.get(self.pos + 1..self.pos + 5)
+                                .and_then(|hex| ::std::str::from_utf8(hex).ok())
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .ok_or_else(|| self.error("invalid unicode escape"))?;
+                            self.pos += 4;
+                            ::std::char::from_u32(code).unwrap_or('\u{fffd}')
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    let mut buffer = [0; 4];
+                    bytes.extend_from_slice(escaped.encode_utf8(&mut buffer).as_bytes());
+                    self.pos += 1;
+                }
+                Some(c) => {
+                    if c == b'\n' {
+                        self.line += 1;
+                    }
+                    bytes.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        return String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8 in string"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_write() {
+        let text = r#"{"a": [1, -2.5e3, true, null], "b": {"c": "x\"yA"}}"#;
+        let value = Value::parse(text).unwrap();
+        assert_eq!(value.get("a").unwrap().as_array().unwrap()[1], Value::Number("-2.5e3".to_string()));
+        assert_eq!(value.get("b").unwrap().get("c").unwrap().as_str(), Some("x\"yA"));
+        assert_eq!(value.to_string(), r#"{"a":[1,-2.5e3,true,null],"b":{"c":"x\"yA"}}"#);
+        assert_eq!(Value::parse(&value.to_string()).unwrap(), value);
+    }
+
+    #[test]
+    fn reports_errors() {
+        match Value::parse("{\n\"a\": [1,,2]}") {
+            Err(FormatError::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(Value::parse("[1] 2").is_err());
+        assert!(Value::parse("01").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! Readers and writers for the file formats a DCEL can be exchanged in.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use build::BuildError;
+use dcel::{DCEL, ValidationError, VertexId};
+use geometry::Position;
+
+pub mod binary;
+pub mod dot;
+pub mod geojson;
+pub mod json;
+pub mod obj;
+pub mod off;
+pub mod svg;
+pub mod text;
+pub mod wkt;
+
+/// Errors raised while reading or writing a DCEL file.
+#[derive(Debug)]
+pub enum FormatError {
+    Io(io::Error),
+    /// The input is malformed at the given (1-based) line.
+    Parse { line: usize, message: String },
+    /// The input parsed, but doesn't describe a valid DCEL.
+    Build(BuildError),
+    /// The input is well-formed but holds something this reader can't use.
+    Unsupported(String),
+    /// Binary input is truncated or fails its checksum.
+    Corrupt(String),
+    /// The input decoded, but the DCEL it describes is inconsistent.
+    Invalid(ValidationError),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FormatError::Io(ref err) => write!(f, "{}", err),
+            FormatError::Parse { line, ref message } => write!(f, "line {}: {}", line, message),
+            FormatError::Build(ref err) => write!(f, "{}", err),
+            FormatError::Unsupported(ref message) => write!(f, "{}", message),
+            FormatError::Corrupt(ref message) => write!(f, "corrupt input: {}", message),
+            FormatError::Invalid(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for FormatError {}
+
+impl From<io::Error> for FormatError {
+    fn from(err: io::Error) -> FormatError {
+        return FormatError::Io(err);
+    }
+}
+
+impl From<ValidationError> for FormatError {
+    fn from(err: ValidationError) -> FormatError {
+        return FormatError::Invalid(err);
+    }
+}
+
+impl From<BuildError> for FormatError {
+    fn from(err: BuildError) -> FormatError {
+        return FormatError::Build(err);
+    }
+}
+
+/// Shorthand for a parse error at `line`.
+pub(crate) fn parse_error<S: Into<String>>(line: usize, message: S) -> FormatError {
+    return FormatError::Parse { line: line, message: message.into() };
+}
+
+/// Parses a single token, reporting failures against `line`.
+pub(crate) fn parse_token<T: FromStr>(token: &str, line: usize) -> Result<T, FormatError> {
+    return token.parse::<T>().map_err(|_| parse_error(line, format!("invalid number `{}`", token)));
+}
+
+/// Flattens a DCEL into polygon soup: the positions of its live vertices,
+/// renumbered consecutively, and one index polygon per inner face. Polygon
+/// soup has no holes, so faces with any are refused.
+pub(crate) fn to_polygons<P: Position, F>(dcel: &DCEL<P, F>) -> Result<(Vec<P>, Vec<Vec<usize>>), FormatError> {
+    let mut index: HashMap<VertexId, usize> = HashMap::new();
+    let mut points = Vec::new();
+    for (id, vertex) in dcel.vertices.iter() {
+        index.insert(id, points.len());
+        points.push(vertex.coord());
+    }
+
+    let mut polygons = Vec::new();
+    for (id, _) in dcel.faces.iter() {
+        if id == dcel.outer_face {
+            continue;
+        } else if !dcel.faces[id].inner_components().is_empty() {
+            return Err(FormatError::Unsupported(format!("face {} has holes", usize::from(id))));
+        }
+        let polygon = dcel.face_edges(id).map(|e| index[&dcel.edges[e].origin()]).collect();
+        polygons.push(polygon);
+    }
+    return Ok((points, polygons));
+}
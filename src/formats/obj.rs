@@ -0,0 +1,152 @@
+//! Wavefront OBJ, restricted to `v` and `f` lines. Other statements
+//! (normals, texture coordinates, groups, materials) are skipped on read.
+
+use std::io::{BufRead, Write};
+
+use dcel::DCEL;
+use formats::{parse_error, parse_token, to_polygons, FormatError};
+use geometry::Position;
+
+/// Reads an OBJ file into a DCEL. Two dimensional positions drop the `z`
+/// coordinate, and faces must be consistently oriented.
+pub fn read<P: Position, R: BufRead>(reader: R) -> Result<DCEL<P>, FormatError> {
+    let mut points: Vec<P> = Vec::new();
+    let mut polygons: Vec<Vec<usize>> = Vec::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_no = i + 1;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let mut components = Vec::new();
+                for token in tokens.take(3) {
+                    components.push(parse_token(token, line_no)?);
+                }
+                if components.len() < 2 {
+                    return Err(parse_error(line_no, "vertex needs at least two coordinates"));
+                }
+                points.push(P::from_components(&components));
+            }
+            Some("f") => {
+                let mut polygon = Vec::new();
+                for token in tokens {
+                    // Only the position index of `v/vt/vn` matters here
+                    let index_str = token.split('/').next().unwrap();
+                    let index: i64 = parse_token(index_str, line_no)?;
+                    let resolved = if index > 0 {
+                        index - 1
+                    } else {
+                        points.len() as i64 + index
+                    };
+                    if index == 0 || resolved < 0 || resolved >= points.len() as i64 {
+                        return Err(parse_error(line_no, format!("vertex index {} out of range", index)));
+                    }
+                    polygon.push(resolved as usize);
+                }
+                polygons.push(polygon);
+            }
+            _ => {}
+        }
+    }
+
+    return Ok(DCEL::from_polygons(&points, &polygons)?);
+}
+
+/// Writes every live vertex and every inner face of a DCEL as OBJ. OBJ faces
+/// can't have holes, so a DCEL with any is refused.
+pub fn write<P: Position, F, W: Write>(dcel: &DCEL<P, F>, mut writer: W) -> Result<(), FormatError> {
+    let (points, polygons) = to_polygons(dcel)?;
+    for p in &points {
+        write!(writer, "v")?;
+        for i in 0..3 {
+            if i < P::DIM {
+                write!(writer, " {}", p.component(i))?;
+            } else {
+                write!(writer, " 0")?;
+            }
+        }
+        writeln!(writer)?;
+    }
+    for polygon in &polygons {
+        write!(writer, "f")?;
+        for index in polygon {
+            write!(writer, " {}", index + 1)?;
+        }
+        writeln!(writer)?;
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geometry::{Point2, Point3};
+
+    const PYRAMID: &str = "# open square pyramid
+o pyramid
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 0.5 0.5 1
+vn 0 0 1
+f 1//1 2//1 5//1
+f 2 3 5
+f 3 4 5
+f -2 -5 -1
+";
+
+    #[test]
+    fn round_trip() {
+        let dcel: DCEL<Point3> = read(PYRAMID.as_bytes()).unwrap();
+        assert_eq!(dcel.vertices.len(), 5);
+        assert_eq!(dcel.faces.len(), 5);
+
+        let mut out = Vec::new();
+        write(&dcel, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("v 0 0 0\nv 1 0 0\n"));
+        assert!(text.contains("v 0.5 0.5 1\n"));
+        assert!(text.ends_with("f 4 1 5\n"));
+
+        let again: DCEL<Point3> = read(text.as_bytes()).unwrap();
+        assert_eq!(again.edges.len(), dcel.edges.len());
+    }
+
+    #[test]
+    fn planar_import() {
+        let dcel: DCEL<Point2> = read("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n".as_bytes()).unwrap();
+        assert_eq!(dcel.vertices[1.into()].coord(), Point2::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn reports_errors() {
+        match read::<Point3, _>("v 0 0 0\nv 1 x 0\n".as_bytes()) {
+            Err(FormatError::Parse { line: 2, .. }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match read::<Point3, _>("v 0 0 0\nf 1 2 3\n".as_bytes()) {
+            Err(FormatError::Parse { line: 2, .. }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        let twice = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nf 1 2 3\nf 1 2 4\n";
+        match read::<Point3, _>(twice.as_bytes()) {
+            Err(FormatError::Build(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn refuses_holes() {
+        // The hole would otherwise come out as a face overlapping the square
+        let square = vec![Point2::new(0.0, 0.0), Point2::new(4.0, 0.0), Point2::new(4.0, 4.0), Point2::new(0.0, 4.0)];
+        let hole = vec![Point2::new(1.0, 1.0), Point2::new(1.0, 3.0), Point2::new(3.0, 3.0), Point2::new(3.0, 1.0)];
+        let dcel: DCEL<Point2, _> = DCEL::from_polygon_rings(&[vec![square, hole]], |i| i).unwrap();
+        match write(&dcel, &mut Vec::new()) {
+            Err(FormatError::Unsupported(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(::formats::off::write(&dcel, &mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! Object File Format (OFF): a header, vertex and face counts, then one
+//! line per vertex and one `n i0 i1 ...` line per face.
+
+use std::cmp;
+use std::io::{BufRead, Write};
+
+use dcel::DCEL;
+use formats::{parse_error, parse_token, to_polygons, FormatError};
+use geometry::Position;
+
+/// Reads an OFF file into a DCEL. Two dimensional positions drop the `z`
+/// coordinate, and faces must be consistently oriented. Trailing face colours
+/// are ignored.
+pub fn read<P: Position, R: BufRead>(reader: R) -> Result<DCEL<P>, FormatError> {
+    // Tokenise ahead of time, skipping blank lines and comments
+    let mut lines = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let content = line.split('#').next().unwrap().trim().to_string();
+        if !content.is_empty() {
+            lines.push((i + 1, content));
+        }
+    }
+    let mut lines = lines.into_iter();
+
+    let (header_no, header) = lines.next().ok_or_else(|| parse_error(1, "missing OFF header"))?;
+    let counts_text = if let Some(rest) = header.strip_prefix("OFF") {
+        let rest = rest.trim().to_string();
+        if rest.is_empty() {
+            lines.next().ok_or_else(|| parse_error(header_no, "missing element counts"))?
+        } else {
+            (header_no, rest)
+        }
+    } else {
+        return Err(parse_error(header_no, "expected `OFF` header"));
+    };
+
+    let counts: Vec<usize> = counts_text.1
+        .split_whitespace()
+        .map(|t| parse_token(t, counts_text.0))
+        .collect::<Result<_, _>>()?;
+    if counts.len() < 2 {
+        return Err(parse_error(counts_text.0, "expected vertex and face counts"));
+    }
+
+    // Counts come from the file, so don't reserve more than there are lines
+    let mut points: Vec<P> = Vec::with_capacity(cmp::min(counts[0], lines.len()));
+    for _ in 0..counts[0] {
+        let (line_no, line) = lines.next().ok_or_else(|| parse_error(counts_text.0, "missing vertices"))?;
+        let mut components = Vec::new();
+        for token in line.split_whitespace().take(3) {
+            components.push(parse_token(token, line_no)?);
+        }
+        if components.len() < 2 {
+            return Err(parse_error(line_no, "vertex needs at least two coordinates"));
+        }
+        points.push(P::from_components(&components));
+    }
+
+    let mut polygons: Vec<Vec<usize>> = Vec::with_capacity(cmp::min(counts[1], lines.len()));
+    for _ in 0..counts[1] {
+        let (line_no, line) = lines.next().ok_or_else(|| parse_error(counts_text.0, "missing faces"))?;
+        let mut tokens = line.split_whitespace();
+        let n: usize = parse_token(tokens.next().unwrap(), line_no)?;
+        let mut polygon = Vec::new();
+        for _ in 0..n {
+            let token = tokens.next().ok_or_else(|| parse_error(line_no, "too few vertex indices"))?;
+            let index: usize = parse_token(token, line_no)?;
+            if index >= points.len() {
+                return Err(parse_error(line_no, format!("vertex index {} out of range", index)));
+            }
+            polygon.push(index);
+        }
+        polygons.push(polygon);
+    }
+
+    return Ok(DCEL::from_polygons(&points, &polygons)?);
+}
+
+/// Writes every live vertex and every inner face of a DCEL as OFF. OFF faces
+/// can't have holes, so a DCEL with any is refused.
+pub fn write<P: Position, F, W: Write>(dcel: &DCEL<P, F>, mut writer: W) -> Result<(), FormatError> {
+    let (points, polygons) = to_polygons(dcel)?;
+    let edge_count = dcel.edges.len() / 2;
+    writeln!(writer, "OFF")?;
+    writeln!(writer, "{} {} {}", points.len(), polygons.len(), edge_count)?;
+    for p in &points {
+        let components: Vec<String> = (0..3)
+            .map(|i| if i < P::DIM { p.component(i).to_string() } else { "0".to_string() })
+            .collect();
+        writeln!(writer, "{}", components.join(" "))?;
+    }
+    for polygon in &polygons {
+        let indices: Vec<String> = polygon.iter().map(|i| i.to_string()).collect();
+        writeln!(writer, "{} {}", polygon.len(), indices.join(" "))?;
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geometry::Point3;
+
+    const TETRAHEDRON: &str = "OFF
+# a closed surface
+4 4 6
+0 0 0
+1 0 0
+0 1 0
+0 0 1
+3 0 2 1
+3 0 1 3
+3 1 2 3
+3 0 3 2 255 0 0
+";
+
+    #[test]
+    fn round_trip() {
+        let dcel: DCEL<Point3> = read(TETRAHEDRON.as_bytes()).unwrap();
+        assert_eq!(dcel.edges.len(), 12);
+        assert!(dcel.faces[dcel.outer_face].incident_edge().is_none());
+
+        let mut out = Vec::new();
+        write(&dcel, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("OFF\n4 4 6\n0 0 0\n"));
+        assert!(text.ends_with("3 0 3 2\n"));
+
+        let again: DCEL<Point3> = read(text.as_bytes()).unwrap();
+        assert_eq!(again.faces.len(), dcel.faces.len());
+    }
+
+    #[test]
+    fn header_with_counts() {
+        let dcel: DCEL<Point3> = read("OFF 3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n".as_bytes()).unwrap();
+        assert_eq!(dcel.faces.len(), 2);
+        assert!(read::<Point3, _>("3 1 0\n".as_bytes()).is_err());
+
+        // Huge counts are caught as missing lines rather than allocated
+        assert!(read::<Point3, _>("OFF\n99999999999999999 1 0\n".as_bytes()).is_err());
+        assert!(read::<Point3, _>("OFF 1 1 0\n0 0 0\n99999999999999999 0\n".as_bytes()).is_err());
+    }
+}
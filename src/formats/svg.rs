@@ -0,0 +1,199 @@
+//! SVG rendering of planar DCELs, for debugging and reports.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use dcel::{DCEL, FaceId, HalfEdgeId};
+use geometry::{Bbox2, Point2, Vector2};
+use scalar::Scalar;
+
+/// Controls what `DCEL::to_svg` draws and how.
+pub struct SvgOptions {
+    /// Width of the image in pixels; the height follows the aspect ratio.
+    pub width: f64,
+    /// Empty space around the drawing, in pixels.
+    pub margin: f64,
+    /// Fill colour of each inner face, as any SVG colour string.
+    pub face_fill: Box<dyn Fn(FaceId) -> String>,
+    pub edge_color: String,
+    pub vertex_radius: f64,
+    /// Label vertices, faces and (when drawn) half-edges with their ids.
+    pub label_ids: bool,
+    /// Draw each half-edge offset to the side of its face, with an arrowhead
+    /// showing the direction of `next`.
+    pub half_edges: bool,
+    /// Highlight the cycle of half-edges through this edge.
+    pub highlight_cycle: Option<HalfEdgeId>,
+}
+
+impl Default for SvgOptions {
+    fn default() -> SvgOptions {
+        return SvgOptions {
+            width: 800.0,
+            margin: 20.0,
+            face_fill: Box::new(|_| "#e0e8f0".to_string()),
+            edge_color: "#333333".to_string(),
+            vertex_radius: 3.0,
+            label_ids: false,
+            half_edges: false,
+            highlight_cycle: None,
+        };
+    }
+}
+
+/// Maps DCEL coordinates onto the SVG canvas, flipping the y axis.
+struct Viewport {
+    bbox: Bbox2,
+    scale: f64,
+    margin: f64,
+    height: f64,
+}
+
+impl Viewport {
+    fn map(&self, p: &Point2) -> Point2 {
+        return Point2::new(
+            self.margin + (p.x - self.bbox.min.x) * self.scale,
+            self.height - self.margin - (p.y - self.bbox.min.y) * self.scale,
+        );
+    }
+}
+
+impl<T: Scalar, F> DCEL<Point2<T>, F> {
+    /// Renders this DCEL as a standalone SVG document.
+    pub fn to_svg(&self, options: &SvgOptions) -> String {
+        let points: Vec<Point2> = self.vertices.iter().map(|(_, v)| v.coord().cast()).collect();
+        let bbox = Bbox2::from_points(&points).unwrap_or_else(|| Bbox2::new(Point2::new(0.0, 0.0), Point2::new(1.0, 1.0)));
+        let extent = bbox.width().max(bbox.height());
+        let scale = if extent > 0.0 { (options.width - 2.0 * options.margin) / extent } else { 1.0 };
+        let height = bbox.height() * scale + 2.0 * options.margin;
+        let view = Viewport { bbox: bbox, scale: scale, margin: options.margin, height: height };
+        let pos = |v| view.map(&self.vertices[v].coord().cast());
+
+        let mut svg = String::new();
+        writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"0 0 {:.2} {:.2}\">",
+                 options.width, height, options.width, height).unwrap();
+        writeln!(svg, "<defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"6\" markerHeight=\"6\" orient=\"auto\">\
+                       <path d=\"M 0 0 L 10 5 L 0 10 z\" fill=\"{}\"/></marker></defs>", options.edge_color).unwrap();
+
+        // Faces, as closed paths so that any holes are cut out
+        for (face, _) in self.faces.iter() {
+            if face == self.outer_face {
+                continue;
+            }
+            let mut d = String::new();
+            for start in self.face_cycles(face) {
+                for (i, e) in self.cycle_edges(start).enumerate() {
+                    let p = pos(self.edges[e].origin());
+                    write!(d, "{}{:.2} {:.2} ", if i == 0 { "M " } else { "L " }, p.x, p.y).unwrap();
+                }
+                d.push_str("Z ");
+            }
+            writeln!(svg, "<path class=\"face\" d=\"{}\" fill=\"{}\" fill-rule=\"evenodd\" stroke=\"none\"/>",
+                     d.trim_end(), (options.face_fill)(face)).unwrap();
+        }
+
+        // Each edge once, as a line between its endpoints
+        let mut drawn = HashSet::new();
+        for (e, edge) in self.edges.iter() {
+            let twin = edge.twin().unwrap();
+            if !drawn.insert(twin) {
+                continue;
+            }
+            drawn.insert(e);
+            let a = pos(edge.origin());
+            let b = pos(self.get_destination(e));
+            writeln!(svg, "<line class=\"edge\" x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\" stroke=\"{}\" stroke-width=\"1.5\"/>",
+                     a.x, a.y, b.x, b.y, options.edge_color).unwrap();
+        }
+
+        if options.half_edges {
+            for (e, edge) in self.edges.iter() {
+                let (a, b) = half_edge_offset(pos(edge.origin()), pos(self.get_destination(e)));
+                writeln!(svg, "<line class=\"half-edge\" x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\" stroke=\"{}\" stroke-width=\"0.75\" marker-end=\"url(#arrow)\"/>",
+                         a.x, a.y, b.x, b.y, options.edge_color).unwrap();
+                if options.label_ids {
+                    let m = Point2::new(0.5 * (a.x + b.x), 0.5 * (a.y + b.y));
+                    writeln!(svg, "<text class=\"half-edge-label\" x=\"{:.2}\" y=\"{:.2}\" font-size=\"9\" text-anchor=\"middle\">e{}</text>",
+                             m.x, m.y, usize::from(e)).unwrap();
+                }
+            }
+        }
+
+        if let Some(start) = options.highlight_cycle {
+            for e in self.cycle_edges(start) {
+                let (a, b) = half_edge_offset(pos(self.edges[e].origin()), pos(self.get_destination(e)));
+                writeln!(svg, "<line class=\"highlight\" x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\" stroke=\"#d62728\" stroke-width=\"2.5\" marker-end=\"url(#arrow)\"/>",
+                         a.x, a.y, b.x, b.y).unwrap();
+            }
+        }
+
+        for (v, _) in self.vertices.iter() {
+            let p = pos(v);
+            writeln!(svg, "<circle class=\"vertex\" cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"{}\"/>",
+                     p.x, p.y, options.vertex_radius, options.edge_color).unwrap();
+            if options.label_ids {
+                writeln!(svg, "<text class=\"vertex-label\" x=\"{:.2}\" y=\"{:.2}\" font-size=\"11\">v{}</text>",
+                         p.x + options.vertex_radius + 2.0, p.y - options.vertex_radius - 2.0, usize::from(v)).unwrap();
+            }
+        }
+
+        if options.label_ids {
+            for (face, _) in self.faces.iter() {
+                if face == self.outer_face || self.faces[face].incident_edge().is_none() {
+                    continue;
+                }
+                let corners: Vec<Point2> = self.face_edges(face).map(|e| pos(self.edges[e].origin())).collect();
+                let n = corners.len() as f64;
+                let cx = corners.iter().map(|p| p.x).sum::<f64>() / n;
+                let cy = corners.iter().map(|p| p.y).sum::<f64>() / n;
+                writeln!(svg, "<text class=\"face-label\" x=\"{:.2}\" y=\"{:.2}\" font-size=\"13\" text-anchor=\"middle\">f{}</text>",
+                         cx, cy, usize::from(face)).unwrap();
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        return svg;
+    }
+}
+
+/// Shrinks a canvas-space edge and shifts it sideways, towards the inside of
+/// a counter-clockwise face once the y axis has been flipped.
+fn half_edge_offset(a: Point2, b: Point2) -> (Point2, Point2) {
+    let d = b - a;
+    let len = d.length();
+    if len == 0.0 {
+        return (a, b);
+    }
+    let offset = (len * 0.06).min(6.0);
+    let normal = Vector2::new(d.y, -d.x) * (offset / len);
+    let shrink = d * (0.12_f64.min(10.0 / len));
+    return (a + shrink + normal, b - shrink + normal);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_every_element() {
+        let points = [Point2::new(0.0, 0.0), Point2::new(1.0, 0.0), Point2::new(1.0, 1.0), Point2::new(0.0, 1.0)];
+        let dcel = DCEL::from_polygons(&points, &[[0, 1, 2], [0, 2, 3]]).unwrap();
+        let options = SvgOptions {
+            face_fill: Box::new(|f| if usize::from(f) == 1 { "red".to_string() } else { "blue".to_string() }),
+            label_ids: true,
+            half_edges: true,
+            highlight_cycle: dcel.faces[FaceId::from(1)].incident_edge(),
+            ..SvgOptions::default()
+        };
+        let svg = dcel.to_svg(&options);
+
+        assert!(svg.starts_with("<svg "));
+        assert_eq!(svg.matches("class=\"face\"").count(), 2);
+        assert!(svg.contains("fill=\"red\"") && svg.contains("fill=\"blue\""));
+        assert_eq!(svg.matches("class=\"edge\"").count(), 5);
+        assert_eq!(svg.matches("class=\"half-edge\"").count(), 10);
+        assert_eq!(svg.matches("class=\"highlight\"").count(), 3);
+        assert_eq!(svg.matches("class=\"vertex\"").count(), 4);
+        assert!(svg.contains(">v3</text>") && svg.contains(">f2</text>") && svg.contains(">e9</text>"));
+    }
+}
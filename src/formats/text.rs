@@ -0,0 +1,282 @@
+//! A line-based text dump of a DCEL's arenas, for test fixtures and
+//! debugging. It lists every live element under its id, so it diffs cleanly
+//! and reads back to an identical DCEL:
+//!
+//! ```text
+//! dcel 1
+//! outer f0
+//! v 0 e0 0 0                # id, outgoing edge, coordinates
+//! e 0 v0 e1 e2 e4 f1        # id, origin, twin, next, prev, face
+//! f 1 e0                    # id, outer boundary, holes...
+//! free e 7 3                # reuse stack, bottom first
+//! ```
+//!
+//! Missing references are written `-`, and `#` starts a comment. Face
+//! payloads aren't stored; they are read back as their default.
+
+use std::fmt::Write as FmtWrite;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+use arena::Arena;
+use dcel::*;
+use formats::{parse_error, parse_token, FormatError};
+use geometry::Position;
+use observe::Observers;
+
+/// The header line, naming the format version.
+const HEADER: &str = "dcel 1";
+
+impl<P: Position, F> DCEL<P, F> {
+    /// Returns the text dump of this DCEL.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        writeln!(text, "{}", HEADER).unwrap();
+        writeln!(text, "outer f{}", usize::from(self.outer_face)).unwrap();
+        for (id, v) in self.vertices.iter() {
+            let coords: Vec<String> = (0..P::DIM).map(|i| v.coord().component(i).to_string()).collect();
+            writeln!(text, "v {} {} {}", usize::from(id), reference("e", v.outgoing_edge()), coords.join(" ")).unwrap();
+        }
+        for (id, e) in self.edges.iter() {
+            writeln!(text, "e {} v{} {} {} {} f{}", usize::from(id), usize::from(e.origin()), reference("e", e.twin()),
+                     reference("e", e.next()), reference("e", e.prev()), usize::from(e.face())).unwrap();
+        }
+        for (id, f) in self.faces.iter() {
+            write!(text, "f {} {}", usize::from(id), reference("e", f.incident_edge())).unwrap();
+            for &hole in f.inner_components() {
+                write!(text, " e{}", usize::from(hole)).unwrap();
+            }
+            text.push('\n');
+        }
+        for &(kind, ref stack) in &[("v", ids(self.vertices.reuse_stack())), ("e", ids(self.edges.reuse_stack())),
+                                    ("f", ids(self.faces.reuse_stack()))] {
+            if !stack.is_empty() {
+                let stack: Vec<String> = stack.iter().map(|i| i.to_string()).collect();
+                writeln!(text, "free {} {}", kind, stack.join(" ")).unwrap();
+            }
+        }
+        return text;
+    }
+}
+
+impl<P: Position, F: Default + Clone> DCEL<P, F> {
+    /// Parses a text dump, as written by `to_text`.
+    pub fn from_text(text: &str) -> Result<DCEL<P, F>, FormatError> {
+        return read(text.as_bytes());
+    }
+}
+
+fn reference<I: Into<usize>>(prefix: &str, id: Option<I>) -> String {
+    match id {
+        Some(id) => return format!("{}{}", prefix, id.into()),
+        None => return "-".to_string(),
+    }
+}
+
+fn ids<I: Copy + Into<usize>>(stack: &[I]) -> Vec<usize> {
+    return stack.iter().map(|&i| i.into()).collect();
+}
+
+/// Writes the text dump of a DCEL.
+pub fn write<P: Position, F, W: Write>(dcel: &DCEL<P, F>, mut writer: W) -> Result<(), FormatError> {
+    writer.write_all(dcel.to_text().as_bytes())?;
+    return Ok(());
+}
+
+/// Reads a text dump, checking that it describes a valid DCEL.
+pub fn read<P: Position, F: Default + Clone, R: BufRead>(reader: R) -> Result<DCEL<P, F>, FormatError> {
+    let mut outer_face = None;
+    let mut vertices: Vec<(usize, Vertex<P>)> = Vec::new();
+    let mut edges: Vec<(usize, HalfEdge)> = Vec::new();
+    let mut faces: Vec<(usize, Face<F>)> = Vec::new();
+    let mut free: [Vec<usize>; 3] = [Vec::new(), Vec::new(), Vec::new()];
+    let mut seen_header = false;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_no = i + 1;
+        let tokens: Vec<&str> = line.split('#').next().unwrap().split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        } else if !seen_header {
+            if tokens.join(" ") != HEADER {
+                return Err(parse_error(line_no, format!("expected `{}` header", HEADER)));
+            }
+            seen_header = true;
+            continue;
+        }
+
+        let arity = |n: usize| if tokens.len() == n {
+            Ok(())
+        } else {
+            Err(parse_error(line_no, format!("expected {} fields, found {}", n, tokens.len())))
+        };
+        match tokens[0] {
+            "outer" => {
+                arity(2)?;
+                outer_face = Some(parse_ref(tokens[1], "f", line_no)?);
+            }
+            "v" => {
+                arity(3 + P::DIM)?;
+                let mut coords = Vec::new();
+                for token in &tokens[3..] {
+                    coords.push(parse_token(token, line_no)?);
+                }
+                let vertex = Vertex::from_raw(P::from_components(&coords), parse_opt(tokens[2], "e", line_no)?, false);
+                vertices.push((parse_token(tokens[1], line_no)?, vertex));
+            }
+            "e" => {
+                arity(7)?;
+                let edge = HalfEdge::from_raw(parse_ref(tokens[2], "v", line_no)?, parse_opt(tokens[3], "e", line_no)?,
+                                              parse_opt(tokens[4], "e", line_no)?, parse_opt(tokens[5], "e", line_no)?,
+                                              parse_ref(tokens[6], "f", line_no)?, false);
+                edges.push((parse_token(tokens[1], line_no)?, edge));
+            }
+            "f" => {
+                if tokens.len() < 3 {
+                    return Err(parse_error(line_no, "face needs an id and an outer boundary"));
+                }
+                let mut holes = Vec::new();
+                for token in &tokens[3..] {
+                    holes.push(parse_ref(token, "e", line_no)?);
+                }
+                let face = Face::from_raw(parse_opt(tokens[2], "e", line_no)?, holes, F::default(), false);
+                faces.push((parse_token(tokens[1], line_no)?, face));
+            }
+            "free" => {
+                let kind = match tokens.get(1) {
+                    Some(&"v") => 0,
+                    Some(&"e") => 1,
+                    Some(&"f") => 2,
+                    _ => return Err(parse_error(line_no, "expected `free v`, `free e` or `free f`")),
+                };
+                for token in &tokens[2..] {
+                    free[kind].push(parse_token(token, line_no)?);
+                }
+            }
+            other => return Err(parse_error(line_no, format!("unknown record `{}`", other))),
+        }
+    }
+
+    let outer_face = outer_face.ok_or_else(|| parse_error(1, "missing `outer` record"))?;
+    let dcel = DCEL {
+        vertices: fill(vertices, &free[0], || Vertex::from_raw(P::from_components(&[]), None, true))?,
+        edges: fill(edges, &free[1], || HalfEdge::from_raw(VertexId::from(0), None, None, None, FaceId::from(0), true))?,
+        faces: fill(faces, &free[2], || Face::from_raw(None, Vec::new(), F::default(), true))?,
+        outer_face: outer_face,
+        observers: Observers::default(),
+    };
+    dcel.validate()?;
+    return Ok(dcel);
+}
+
+/// Places live items at their ids, filling every other slot with a deleted
+/// placeholder. Each slot must be filled exactly once.
+fn fill<T, I, D>(items: Vec<(usize, T)>, free: &[usize], deleted: D) -> Result<Arena<T, I>, FormatError>
+    where T: ::arena::Deleteable + Clone, I: Copy + From<usize> + Into<usize>, D: Fn() -> T
+{
+    // Every slot is listed once, so no id can reach the number listed
+    let len = items.len() + free.len();
+    if let Some(id) = items.iter().map(|i| i.0).chain(free.iter().cloned()).find(|&id| id >= len) {
+        return Err(FormatError::Unsupported(format!("id {} is out of range for {} slots", id, len)));
+    }
+    let mut slots: Vec<Option<T>> = (0..len).map(|_| None).collect();
+    for (id, item) in items.into_iter() {
+        if slots[id].is_some() {
+            return Err(FormatError::Unsupported(format!("id {} is listed twice", id)));
+        }
+        slots[id] = Some(item);
+    }
+    for &id in free {
+        if slots[id].is_some() {
+            return Err(FormatError::Unsupported(format!("id {} is both live and free", id)));
+        }
+        slots[id] = Some(deleted());
+    }
+    let mut data = Vec::with_capacity(len);
+    for (id, slot) in slots.into_iter().enumerate() {
+        data.push(slot.ok_or_else(|| FormatError::Unsupported(format!("id {} is neither live nor free", id)))?);
+    }
+    return Ok(Arena::from_parts(data, free.iter().map(|&i| I::from(i)).collect()));
+}
+
+/// Parses a reference such as `e12`.
+pub(crate) fn parse_ref<I: From<usize>>(token: &str, prefix: &str, line: usize) -> Result<I, FormatError> {
+    match token.strip_prefix(prefix).and_then(|t| usize::from_str(t).ok()) {
+        Some(index) => return Ok(I::from(index)),
+        None => return Err(parse_error(line, format!("expected a `{}` reference, found `{}`", prefix, token))),
+    }
+}
+
+/// Parses a reference that may be `-`.
+fn parse_opt<I: From<usize>>(token: &str, prefix: &str, line: usize) -> Result<Option<I>, FormatError> {
+    if token == "-" {
+        return Ok(None);
+    }
+    return parse_ref(token, prefix, line).map(Some);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geometry::{Point2, Point3};
+
+    fn split_square() -> DCEL {
+        let points = [Point2::new(0.0, 0.0), Point2::new(1.0, 0.0), Point2::new(1.0, 1.0), Point2::new(0.0, 1.0)];
+        let mut dcel = DCEL::from_polygons(&points, &[[0, 1, 2], [0, 2, 3]]).unwrap();
+        let e = dcel.face_edges(FaceId::from(2)).next().unwrap();
+        dcel.split_edge_in_half(e);
+        return dcel;
+    }
+
+    #[test]
+    fn matches_fixture() {
+        let dcel = split_square();
+        assert_eq!(dcel.to_text(), include_str!("../../fixtures/split_square.dcel"));
+    }
+
+    #[test]
+    fn round_trips_exactly() {
+        let mut dcel = split_square();
+        let diagonal = dcel.face_edges(FaceId::from(1)).nth(2).unwrap();
+        dcel.remove_inner_edge(diagonal);
+        let text = dcel.to_text();
+        assert!(text.contains("\nfree e"));
+        assert!(text.contains("\nfree f 2\n"));
+
+        let mut again: DCEL = DCEL::from_text(&text).unwrap();
+        assert_eq!(again.to_text(), text);
+        assert_eq!(again.add_face(), dcel.add_face());
+
+        let tetra: DCEL<Point3> = DCEL::from_polygons(
+            &[Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0), Point3::new(0.0, 0.0, 1.0)],
+            &[[0, 2, 1], [0, 1, 3], [1, 2, 3], [0, 3, 2]]).unwrap();
+        assert_eq!(DCEL::<Point3>::from_text(&tetra.to_text()).unwrap().to_text(), tetra.to_text());
+    }
+
+    #[test]
+    fn reports_errors() {
+        let text = "dcel 1\nouter f0\nv 0 - 0 0\nv 1 - 1\n";
+        match DCEL::<Point2>::from_text(text) {
+            Err(FormatError::Parse { line, .. }) => assert_eq!(line, 4),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(DCEL::<Point2>::from_text("dcel 2\n").is_err());
+
+        // Edge 1 is the twin of edge 0, but not the other way round
+        let text = "dcel 1\nouter f0\nv 0 e0 0 0\nv 1 e1 1 0\ne 0 v0 e1 e1 e1 f0\ne 1 v1 - e0 e0 f0\nf 0 e0\n";
+        match DCEL::<Point2>::from_text(text) {
+            Err(FormatError::Invalid(ValidationError::TwinMismatch(_))) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // Ids beyond the records listed are refused before allocating
+        for text in &["dcel 1\nouter f0\nv 4000000000 e0 0 0\nf 0 - e0\n",
+                      "dcel 1\nouter f0\nv 18446744073709551615 e0 0 0\nf 0 - e0\n"] {
+            match DCEL::<Point2>::from_text(text) {
+                Err(FormatError::Unsupported(_)) => {}
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! Well-known text (WKT) `POLYGON` and `MULTIPOLYGON` geometries.
+
+use std::io::{Read, Write};
+
+use dcel::{DCEL, HalfEdgeId};
+use formats::{parse_error, parse_token, FormatError};
+use geometry::Point2;
+use scalar::Scalar;
+
+/// Reads any number of WKT polygons and multipolygons, separated by
+/// whitespace, into a planar subdivision. Boundaries shared by neighbouring
+/// polygons become twin edges and holes become inner components. Each face
+/// carries the index of the geometry it came from; the outer face and any
+/// gaps between polygons carry `None`.
+pub fn read<T: Scalar, R: Read>(mut reader: R) -> Result<DCEL<Point2<T>, Option<usize>>, FormatError> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let mut tokens = Tokens::new(&text);
+
+    let mut polygons: Vec<Vec<Vec<Point2<T>>>> = Vec::new();
+    let mut sources: Vec<usize> = Vec::new();
+    let mut geometry = 0;
+    while let Some((line, word)) = tokens.next() {
+        match word.to_ascii_uppercase().as_str() {
+            "POLYGON" => {
+                if let Some(rings) = polygon_text(&mut tokens)? {
+                    polygons.push(rings);
+                    sources.push(geometry);
+                }
+            }
+            "MULTIPOLYGON" => {
+                if !tokens.empty()? {
+                    tokens.expect("(")?;
+                    loop {
+                        if let Some(rings) = polygon_text(&mut tokens)? {
+                            polygons.push(rings);
+                            sources.push(geometry);
+                        }
+                        if tokens.list_end()? {
+                            break;
+                        }
+                    }
+                }
+            }
+            _ => return Err(parse_error(line, format!("expected POLYGON or MULTIPOLYGON, found `{}`", word))),
+        }
+        geometry += 1;
+    }
+
+    let dcel = DCEL::from_polygon_rings(&polygons, |p| p.map(|i| sources[i]))?;
+    return Ok(dcel);
+}
+
+/// Parses `(ring, ring, ...)` or `EMPTY`.
+fn polygon_text<T: Scalar>(tokens: &mut Tokens) -> Result<Option<Vec<Vec<Point2<T>>>>, FormatError> {
+    if tokens.empty()? {
+        return Ok(None);
+    }
+    tokens.expect("(")?;
+    let mut rings = Vec::new();
+    loop {
+        tokens.expect("(")?;
+        let mut ring = Vec::new();
+        loop {
+            let (line, x) = tokens.require()?;
+            let (_, y) = tokens.require()?;
+            ring.push(Point2::new(parse_token(x, line)?, parse_token(y, line)?));
+            if tokens.list_end()? {
+                break;
+            }
+        }
+        rings.push(ring);
+        if tokens.list_end()? {
+            break;
+        }
+    }
+    return Ok(Some(rings));
+}
+
+/// Splits WKT into words and punctuation, remembering their lines.
+struct Tokens<'a> {
+    tokens: Vec<(usize, &'a str)>,
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(text: &'a str) -> Tokens<'a> {
+        let mut tokens = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let mut start = None;
+            for (j, c) in line.char_indices() {
+                let separator = c.is_whitespace() || c == '(' || c == ')' || c == ',';
+                if separator {
+                    if let Some(s) = start.take() {
+                        tokens.push((i + 1, &line[s..j]));
+                    }
+                    if !c.is_whitespace() {
+                        tokens.push((i + 1, &line[j..j + 1]));
+                    }
+                } else if start.is_none() {
+                    start = Some(j);
+                }
+            }
+            if let Some(s) = start {
+                tokens.push((i + 1, &line[s..]));
+            }
+        }
+        return Tokens { tokens: tokens, pos: 0 };
+    }
+
+    fn next(&mut self) -> Option<(usize, &'a str)> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        return token;
+    }
+
+    fn last_line(&self) -> usize {
+        return self.tokens.last().map_or(1, |t| t.0);
+    }
+
+    fn require(&mut self) -> Result<(usize, &'a str), FormatError> {
+        let line = self.last_line();
+        return self.next().ok_or_else(|| parse_error(line, "unexpected end of input"));
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), FormatError> {
+        let (line, found) = self.require()?;
+        if found != token {
+            return Err(parse_error(line, format!("expected `{}`, found `{}`", token, found)));
+        }
+        return Ok(());
+    }
+
+    /// Consumes `EMPTY` if it comes next.
+    fn empty(&mut self) -> Result<bool, FormatError> {
+        match self.tokens.get(self.pos) {
+            Some(&(_, word)) if word.eq_ignore_ascii_case("EMPTY") => {
+                self.pos += 1;
+                return Ok(true);
+            }
+            _ => return Ok(false),
+        }
+    }
+
+    /// Consumes a `,` (returning false) or a `)` (returning true).
+    fn list_end(&mut self) -> Result<bool, FormatError> {
+        let (line, found) = self.require()?;
+        match found {
+            "," => return Ok(false),
+            ")" => return Ok(true),
+            _ => return Err(parse_error(line, format!("expected `,` or `)`, found `{}`", found))),
+        }
+    }
+}
+
+/// Writes the inner faces whose payloads pass `include` as `POLYGON`s, one
+/// per line, with outer boundaries counter-clockwise and holes clockwise.
+/// For a DCEL read by `read`, `Option::is_some` leaves out the gaps.
+pub fn write<T, F, W, G>(dcel: &DCEL<Point2<T>, F>, include: G, mut writer: W) -> Result<(), FormatError>
+    where T: Scalar, W: Write, G: Fn(&F) -> bool
+{
+    for (face, _) in dcel.faces.iter() {
+        if face == dcel.outer_face || dcel.faces[face].incident_edge().is_none() || !include(dcel.face_data(face)) {
+            continue;
+        }
+        let rings: Vec<String> = dcel.face_cycles(face).into_iter().map(|start| ring(dcel, start)).collect();
+        writeln!(writer, "POLYGON ({})", rings.join(", "))?;
+    }
+    return Ok(());
+}
+
+/// The positions around a cycle, closed by repeating the first.
+fn ring<T: Scalar, F>(dcel: &DCEL<Point2<T>, F>, start: HalfEdgeId) -> String {
+    let mut positions: Vec<String> = dcel.cycle_edges(start).map(|e| position(dcel, e)).collect();
+    positions.push(position(dcel, start));
+    return format!("({})", positions.join(", "));
+}
+
+fn position<T: Scalar, F>(dcel: &DCEL<Point2<T>, F>, edge: HalfEdgeId) -> String {
+    let p = dcel.vertices[dcel.edges[edge].origin()].coord();
+    return format!("{} {}", p.x, p.y);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scalar::Rational;
+
+    #[test]
+    fn reads_polygons_and_holes() {
+        let text = "POLYGON ((0 0, 4 0, 4 4, 0 4, 0 0), (1 1, 1 3, 3 3, 3 1, 1 1))\n\
+                    MULTIPOLYGON (((4 0, 8 0, 8 4, 4 4, 4 0)), EMPTY, ((1 1, 3 1, 3 3, 1 3, 1 1)))\n\
+                    polygon empty";
+        let dcel: DCEL<Point2, _> = read(text.as_bytes()).unwrap();
+        assert_eq!(dcel.vertices.len(), 10);
+        assert_eq!(dcel.edges.len(), 22);
+        assert_eq!(dcel.faces.len(), 4);
+
+        let left = dcel.locate(&Point2::new(0.5, 0.5));
+        let island = dcel.locate(&Point2::new(2.0, 2.0));
+        assert_eq!(dcel.face_data(left), &Some(0));
+        assert_eq!(dcel.face_data(island), &Some(1));
+        assert_eq!(dcel.face_cycles(left).len(), 2);
+        assert_eq!(dcel.face_cycles(dcel.outer_face).len(), 1);
+    }
+
+    #[test]
+    fn exact_round_trip() {
+        let text = "POLYGON ((0 0, 1/3 0, 0 1/3, 0 0))\n";
+        let dcel: DCEL<Point2<Rational>, _> = read(text.as_bytes()).unwrap();
+        let mut out = Vec::new();
+        write(&dcel, Option::is_some, &mut out).unwrap();
+        let written = String::from_utf8(out).unwrap();
+        assert_eq!(written.lines().count(), 1);
+        assert!(written.contains("1/3 0") && written.contains("0 1/3"));
+    }
+
+    #[test]
+    fn leaves_out_gaps() {
+        let text = "POLYGON ((0 0, 4 0, 4 4, 0 4, 0 0), (1 1, 1 3, 3 3, 3 1, 1 1))\n";
+        let dcel: DCEL<Point2, _> = read(text.as_bytes()).unwrap();
+        assert_eq!(dcel.faces.len(), 3);
+        let mut out = Vec::new();
+        write(&dcel, Option::is_some, &mut out).unwrap();
+        let written = String::from_utf8(out).unwrap();
+        assert_eq!(written.lines().count(), 1);
+
+        let again: DCEL<Point2, _> = read(written.as_bytes()).unwrap();
+        assert_eq!(again.faces.len(), dcel.faces.len());
+        assert_eq!(again.faces.iter().filter(|f| f.1.data().is_some()).count(), 1);
+    }
+
+    #[test]
+    fn reports_errors() {
+        match read::<f64, _>("POLYGON ((0 0, 1 0, 1 1, 0 0)\n".as_bytes()) {
+            Err(FormatError::Parse { line, .. }) => assert_eq!(line, 1),
+            other => panic!("unexpected {:?}", other.map(|d| d.faces.len())),
+        }
+        assert!(read::<f64, _>("POINT (1 2)".as_bytes()).is_err());
+        assert!(read::<f64, _>("POLYGON ((0 0, x 0, 1 1, 0 0))".as_bytes()).is_err());
+    }
+}
@@ -1,15 +1,19 @@
 use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::ops::{Add, Sub, Mul, Neg};
+
+use scalar::{self, Scalar};
 
 /// Represents a point in 2-dimensional space.
 #[derive(Debug, PartialEq, Copy, Clone)]
-pub struct Point2 {
-    pub x: f64,
-    pub y: f64,
+pub struct Point2<T = f64> {
+    pub x: T,
+    pub y: T,
 }
 
 /// Custom comparison for Points. Ordinality is determined by the x component first, then with the y component.
-impl PartialOrd for Point2 {
-    fn partial_cmp(&self, other: &Point2) -> Option<Ordering> {
+impl<T: Scalar> PartialOrd for Point2<T> {
+    fn partial_cmp(&self, other: &Point2<T>) -> Option<Ordering> {
         let x_cmp = self.x.partial_cmp(&other.x);
         match x_cmp {
             Some(Ordering::Equal) => self.y.partial_cmp(&other.y),
@@ -18,31 +22,36 @@ impl PartialOrd for Point2 {
     }
 }
 
-impl Point2 {
+impl<T: Scalar> Point2<T> {
     /// Returns a new Point2 instance.
-    pub fn new(x: f64, y: f64) -> Point2 {
+    pub fn new(x: T, y: T) -> Point2<T> {
         return Point2 { x: x, y: y };
     }
 
     /// Returns the squared Euclidean distance between this point and another.
-    pub fn dist_sq(&self, other: &Point2) -> f64 {
+    pub fn dist_sq(&self, other: &Point2<T>) -> T {
         let dx = other.x - self.x;
         let dy = other.y - self.y;
         return dx * dx + dy * dy;
     }
+
+    /// Converts this point to another coordinate type, rounding through `f64`.
+    pub fn cast<U: Scalar>(&self) -> Point2<U> {
+        return Point2::new(U::from_f64(self.x.to_f64()), U::from_f64(self.y.to_f64()));
+    }
 }
 
 // for easy conversion between all possible tuples
-impl<T: Into<f64> + Copy> From<(T, T)> for Point2 {
-    fn from(coordinate: (T, T)) -> Point2 {
+impl<T: Scalar, U: Into<T> + Copy> From<(U, U)> for Point2<T> {
+    fn from(coordinate: (U, U)) -> Point2<T> {
         Point2 {
             x: coordinate.0.into(),
             y: coordinate.1.into(),
         }
     }
 }
-impl<'a, T: Into<f64> + Copy> From<&'a (T, T)> for Point2 {
-    fn from(coordinate: &'a (T, T)) -> Point2 {
+impl<'a, T: Scalar, U: Into<T> + Copy> From<&'a (U, U)> for Point2<T> {
+    fn from(coordinate: &'a (U, U)) -> Point2<T> {
         Point2 {
             x: coordinate.0.into(),
             y: coordinate.1.into(),
@@ -50,58 +59,836 @@ impl<'a, T: Into<f64> + Copy> From<&'a (T, T)> for Point2 {
     }
 }
 
+/// Represents a displacement in 2-dimensional space.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Vector2<T = f64> {
+    pub x: T,
+    pub y: T,
+}
+
+impl<T: Scalar> Vector2<T> {
+    /// Returns a new Vector2 instance.
+    pub fn new(x: T, y: T) -> Vector2<T> {
+        return Vector2 { x: x, y: y };
+    }
+
+    /// Returns the dot product of this vector and another.
+    pub fn dot(&self, other: &Vector2<T>) -> T {
+        return self.x * other.x + self.y * other.y;
+    }
+
+    /// Returns the z component of the cross product of this vector and another.
+    /// Positive when `other` is counter-clockwise from `self`.
+    pub fn cross(&self, other: &Vector2<T>) -> T {
+        return self.x * other.y - self.y * other.x;
+    }
+
+    /// Returns the squared length of this vector.
+    pub fn length_sq(&self) -> T {
+        return self.dot(self);
+    }
+
+    /// Returns the length of this vector.
+    pub fn length(&self) -> T {
+        return self.length_sq().sqrt();
+    }
+
+    /// Returns this vector rotated a quarter turn counter-clockwise.
+    pub fn perp(&self) -> Vector2<T> {
+        return Vector2::new(-self.y, self.x);
+    }
+}
+
+impl<T: Scalar> Add for Vector2<T> {
+    type Output = Vector2<T>;
+
+    fn add(self, other: Vector2<T>) -> Vector2<T> {
+        return Vector2::new(self.x + other.x, self.y + other.y);
+    }
+}
+
+impl<T: Scalar> Sub for Vector2<T> {
+    type Output = Vector2<T>;
+
+    fn sub(self, other: Vector2<T>) -> Vector2<T> {
+        return Vector2::new(self.x - other.x, self.y - other.y);
+    }
+}
+
+impl<T: Scalar> Mul<T> for Vector2<T> {
+    type Output = Vector2<T>;
+
+    fn mul(self, scale: T) -> Vector2<T> {
+        return Vector2::new(self.x * scale, self.y * scale);
+    }
+}
+
+impl<T: Scalar> Neg for Vector2<T> {
+    type Output = Vector2<T>;
+
+    fn neg(self) -> Vector2<T> {
+        return Vector2::new(-self.x, -self.y);
+    }
+}
+
+/// The displacement from one point to another.
+impl<T: Scalar> Sub for Point2<T> {
+    type Output = Vector2<T>;
+
+    fn sub(self, other: Point2<T>) -> Vector2<T> {
+        return Vector2::new(self.x - other.x, self.y - other.y);
+    }
+}
+
+impl<T: Scalar> Add<Vector2<T>> for Point2<T> {
+    type Output = Point2<T>;
+
+    fn add(self, offset: Vector2<T>) -> Point2<T> {
+        return Point2::new(self.x + offset.x, self.y + offset.y);
+    }
+}
+
+impl<T: Scalar> Sub<Vector2<T>> for Point2<T> {
+    type Output = Point2<T>;
+
+    fn sub(self, offset: Vector2<T>) -> Point2<T> {
+        return Point2::new(self.x - offset.x, self.y - offset.y);
+    }
+}
+
+/// Represents a point in 3-dimensional space.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Point3<T = f64> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+impl<T: Scalar> Point3<T> {
+    /// Returns a new Point3 instance.
+    pub fn new(x: T, y: T, z: T) -> Point3<T> {
+        return Point3 { x: x, y: y, z: z };
+    }
+
+    /// Returns the squared Euclidean distance between this point and another.
+    pub fn dist_sq(&self, other: &Point3<T>) -> T {
+        return (*other - *self).length_sq();
+    }
+
+    /// Drops the z component.
+    pub fn xy(&self) -> Point2<T> {
+        return Point2::new(self.x, self.y);
+    }
+}
+
+impl<T: Scalar, U: Into<T> + Copy> From<(U, U, U)> for Point3<T> {
+    fn from(coordinate: (U, U, U)) -> Point3<T> {
+        return Point3::new(coordinate.0.into(), coordinate.1.into(), coordinate.2.into());
+    }
+}
+
+/// Represents a displacement in 3-dimensional space.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Vector3<T = f64> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+impl<T: Scalar> Vector3<T> {
+    /// Returns a new Vector3 instance.
+    pub fn new(x: T, y: T, z: T) -> Vector3<T> {
+        return Vector3 { x: x, y: y, z: z };
+    }
+
+    pub fn dot(&self, other: &Vector3<T>) -> T {
+        return self.x * other.x + self.y * other.y + self.z * other.z;
+    }
+
+    pub fn cross(&self, other: &Vector3<T>) -> Vector3<T> {
+        return Vector3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        );
+    }
+
+    pub fn length_sq(&self) -> T {
+        return self.dot(self);
+    }
+
+    pub fn length(&self) -> T {
+        return self.length_sq().sqrt();
+    }
+}
+
+impl<T: Scalar> Add for Vector3<T> {
+    type Output = Vector3<T>;
+
+    fn add(self, other: Vector3<T>) -> Vector3<T> {
+        return Vector3::new(self.x + other.x, self.y + other.y, self.z + other.z);
+    }
+}
+
+impl<T: Scalar> Mul<T> for Vector3<T> {
+    type Output = Vector3<T>;
+
+    fn mul(self, scale: T) -> Vector3<T> {
+        return Vector3::new(self.x * scale, self.y * scale, self.z * scale);
+    }
+}
+
+impl<T: Scalar> Sub for Point3<T> {
+    type Output = Vector3<T>;
+
+    fn sub(self, other: Point3<T>) -> Vector3<T> {
+        return Vector3::new(self.x - other.x, self.y - other.y, self.z - other.z);
+    }
+}
+
+impl<T: Scalar> Add<Vector3<T>> for Point3<T> {
+    type Output = Point3<T>;
+
+    fn add(self, offset: Vector3<T>) -> Point3<T> {
+        return Point3::new(self.x + offset.x, self.y + offset.y, self.z + offset.z);
+    }
+}
+
+/// Trait for the position stored at each vertex of a DCEL. Topology code is
+/// written against this trait so that it works for planar subdivisions
+/// (`Point2`) and for surface meshes (`Point3`) alike.
+pub trait Position: Copy + Debug + PartialEq {
+    type Scalar: Scalar;
+
+    /// The number of coordinate components.
+    const DIM: usize;
+
+    /// Returns the `i`th coordinate component.
+    fn component(&self, i: usize) -> Self::Scalar;
+
+    /// Builds a position from its components, treating missing ones as zero
+    /// and ignoring extra ones.
+    fn from_components(components: &[Self::Scalar]) -> Self;
+
+    /// Returns the point halfway between this position and another.
+    fn midpoint(&self, other: &Self) -> Self;
+
+    /// Returns the squared Euclidean distance between this position and another.
+    fn dist_sq(&self, other: &Self) -> Self::Scalar;
+}
+
+impl<T: Scalar> Position for Point2<T> {
+    type Scalar = T;
+    const DIM: usize = 2;
+
+    fn component(&self, i: usize) -> T {
+        match i {
+            0 => self.x,
+            1 => self.y,
+            _ => panic!("Point2 has no component {}", i),
+        }
+    }
+
+    fn from_components(components: &[T]) -> Point2<T> {
+        let get = |i: usize| components.get(i).cloned().unwrap_or_else(T::zero);
+        return Point2::new(get(0), get(1));
+    }
+
+    fn midpoint(&self, other: &Point2<T>) -> Point2<T> {
+        return midpoint(*self, *other);
+    }
+
+    fn dist_sq(&self, other: &Point2<T>) -> T {
+        return Point2::dist_sq(self, other);
+    }
+}
+
+impl<T: Scalar> Position for Point3<T> {
+    type Scalar = T;
+    const DIM: usize = 3;
+
+    fn component(&self, i: usize) -> T {
+        match i {
+            0 => self.x,
+            1 => self.y,
+            2 => self.z,
+            _ => panic!("Point3 has no component {}", i),
+        }
+    }
+
+    fn from_components(components: &[T]) -> Point3<T> {
+        let get = |i: usize| components.get(i).cloned().unwrap_or_else(T::zero);
+        return Point3::new(get(0), get(1), get(2));
+    }
+
+    fn midpoint(&self, other: &Point3<T>) -> Point3<T> {
+        return Point3::new((self.x + other.x).half(), (self.y + other.y).half(), (self.z + other.z).half());
+    }
+
+    fn dist_sq(&self, other: &Point3<T>) -> T {
+        return Point3::dist_sq(self, other);
+    }
+}
+
+/// An axis-aligned bounding box.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Bbox2<T = f64> {
+    pub min: Point2<T>,
+    pub max: Point2<T>,
+}
+
+impl<T: Scalar> Bbox2<T> {
+    /// Returns a new Bbox2 spanning the two given corners, in any order.
+    pub fn new(a: Point2<T>, b: Point2<T>) -> Bbox2<T> {
+        return Bbox2 {
+            min: Point2::new(scalar::min(a.x, b.x), scalar::min(a.y, b.y)),
+            max: Point2::new(scalar::max(a.x, b.x), scalar::max(a.y, b.y)),
+        };
+    }
+
+    /// Returns the smallest box containing every given point, or `None` if there are none.
+    pub fn from_points<'a, I>(points: I) -> Option<Bbox2<T>>
+    where
+        I: IntoIterator<Item = &'a Point2<T>>,
+        T: 'a,
+    {
+        let mut iter = points.into_iter();
+        let first = match iter.next() {
+            Some(p) => *p,
+            None => return None,
+        };
+        let mut bbox = Bbox2::new(first, first);
+        for p in iter {
+            bbox = bbox.expand(p);
+        }
+        return Some(bbox);
+    }
+
+    /// Returns the smallest box containing both this box and the given point.
+    pub fn expand(&self, p: &Point2<T>) -> Bbox2<T> {
+        return Bbox2::new(
+            Point2::new(scalar::min(self.min.x, p.x), scalar::min(self.min.y, p.y)),
+            Point2::new(scalar::max(self.max.x, p.x), scalar::max(self.max.y, p.y)),
+        );
+    }
+
+    /// Returns the smallest box containing both boxes.
+    pub fn union(&self, other: &Bbox2<T>) -> Bbox2<T> {
+        return self.expand(&other.min).expand(&other.max);
+    }
+
+    /// Determines if the point lies inside or on the boundary of this box.
+    pub fn contains(&self, p: &Point2<T>) -> bool {
+        return self.min.x <= p.x && p.x <= self.max.x && self.min.y <= p.y && p.y <= self.max.y;
+    }
+
+    /// Determines if the two boxes share at least one point.
+    pub fn intersects(&self, other: &Bbox2<T>) -> bool {
+        return self.min.x <= other.max.x && other.min.x <= self.max.x
+            && self.min.y <= other.max.y && other.min.y <= self.max.y;
+    }
+
+    pub fn width(&self) -> T {
+        return self.max.x - self.min.x;
+    }
+
+    pub fn height(&self) -> T {
+        return self.max.y - self.min.y;
+    }
+}
 
+/// A line segment between two points.
 #[derive(Debug, PartialEq, Copy, Clone)]
-pub struct Circle {
-    pub center: Point2,
-    pub radius: f64,
+pub struct Segment2<T = f64> {
+    pub start: Point2<T>,
+    pub end: Point2<T>,
+}
+
+impl<T: Scalar> Segment2<T> {
+    /// Returns a new Segment2 instance.
+    pub fn new(start: Point2<T>, end: Point2<T>) -> Segment2<T> {
+        return Segment2 { start: start, end: end };
+    }
+
+    /// Returns the displacement from the start of this segment to its end.
+    pub fn direction(&self) -> Vector2<T> {
+        return self.end - self.start;
+    }
+
+    /// Returns the squared length of this segment.
+    pub fn length_sq(&self) -> T {
+        return self.start.dist_sq(&self.end);
+    }
+
+    /// Returns the point at parameter `t`, where 0 is `start` and 1 is `end`.
+    pub fn point_at(&self, t: T) -> Point2<T> {
+        if t == T::zero() {
+            return self.start;
+        } else if t == T::one() {
+            return self.end;
+        }
+        return self.start + self.direction() * t;
+    }
+
+    pub fn bbox(&self) -> Bbox2<T> {
+        return Bbox2::new(self.start, self.end);
+    }
+
+    /// Returns the parameter of the point on this segment closest to `p`.
+    pub fn project(&self, p: &Point2<T>) -> T {
+        let d = self.direction();
+        let len_sq = d.length_sq();
+        if len_sq == T::zero() {
+            return T::zero();
+        }
+        let t = (*p - self.start).dot(&d) / len_sq;
+        return scalar::max(T::zero(), scalar::min(t, T::one()));
+    }
+
+    /// Returns the point on this segment closest to `p`.
+    pub fn closest_point(&self, p: &Point2<T>) -> Point2<T> {
+        return self.point_at(self.project(p));
+    }
+
+    /// Returns the squared distance between this segment and `p`.
+    pub fn dist_sq(&self, p: &Point2<T>) -> T {
+        return self.closest_point(p).dist_sq(p);
+    }
+
+    /// Determines if `p` lies on this segment, endpoints included.
+    pub fn contains(&self, p: &Point2<T>) -> bool {
+        return area_of_parallelogram(&self.start, &self.end, p) == T::zero() && self.bbox().contains(p);
+    }
+}
+
+/// A half-infinite line starting at `origin` and extending along `direction`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Ray2<T = f64> {
+    pub origin: Point2<T>,
+    pub direction: Vector2<T>,
+}
+
+impl<T: Scalar> Ray2<T> {
+    /// Returns a new Ray2 instance.
+    pub fn new(origin: Point2<T>, direction: Vector2<T>) -> Ray2<T> {
+        return Ray2 { origin: origin, direction: direction };
+    }
+
+    /// Returns the point at parameter `t >= 0` along this ray.
+    pub fn point_at(&self, t: T) -> Point2<T> {
+        if t == T::zero() {
+            return self.origin;
+        }
+        return self.origin + self.direction * t;
+    }
+}
+
+/// An infinite line through `point` running along `direction`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Line2<T = f64> {
+    pub point: Point2<T>,
+    pub direction: Vector2<T>,
+}
+
+impl<T: Scalar> Line2<T> {
+    /// Returns a new Line2 instance.
+    pub fn new(point: Point2<T>, direction: Vector2<T>) -> Line2<T> {
+        return Line2 { point: point, direction: direction };
+    }
+
+    /// Returns the line through two points, directed from `a` to `b`.
+    pub fn through(a: Point2<T>, b: Point2<T>) -> Line2<T> {
+        return Line2::new(a, b - a);
+    }
+
+    /// Returns a value that is positive if `p` is left of this line, negative
+    /// if it is right of it, and zero if `p` is on it.
+    pub fn side_of(&self, p: &Point2<T>) -> T {
+        return self.direction.cross(&(*p - self.point));
+    }
+
+    /// Returns the orthogonal projection of `p` onto this line.
+    pub fn project(&self, p: &Point2<T>) -> Point2<T> {
+        let len_sq = self.direction.length_sq();
+        if len_sq == T::zero() {
+            return self.point;
+        }
+        let t = (*p - self.point).dot(&self.direction) / len_sq;
+        return self.point + self.direction * t;
+    }
+
+    /// Returns the squared distance between this line and `p`.
+    pub fn dist_sq(&self, p: &Point2<T>) -> T {
+        return self.project(p).dist_sq(p);
+    }
+}
+
+/// An affine map of the plane, taking `(x, y)` to
+/// `(a * x + b * y + tx, c * x + d * y + ty)`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Affine2<T = f64> {
+    pub a: T,
+    pub b: T,
+    pub c: T,
+    pub d: T,
+    pub tx: T,
+    pub ty: T,
+}
+
+impl<T: Scalar> Affine2<T> {
+    /// Returns a new Affine2 instance.
+    pub fn new(a: T, b: T, c: T, d: T, tx: T, ty: T) -> Affine2<T> {
+        return Affine2 { a: a, b: b, c: c, d: d, tx: tx, ty: ty };
+    }
+
+    /// Returns the map leaving every point where it is.
+    pub fn identity() -> Affine2<T> {
+        return Affine2::scaling(T::one(), T::one());
+    }
+
+    /// Returns the map moving every point by `offset`.
+    pub fn translation(offset: Vector2<T>) -> Affine2<T> {
+        return Affine2::new(T::one(), T::zero(), T::zero(), T::one(), offset.x, offset.y);
+    }
+
+    /// Returns the map rotating counter-clockwise about the origin by `angle`
+    /// radians. The sine and cosine are computed in `f64`, so they're rounded
+    /// for other coordinate types.
+    pub fn rotation(angle: f64) -> Affine2<T> {
+        let (sin, cos) = (T::from_f64(angle.sin()), T::from_f64(angle.cos()));
+        return Affine2::new(cos, -sin, sin, cos, T::zero(), T::zero());
+    }
+
+    /// Returns the map scaling about the origin by `sx` and `sy` along the
+    /// axes. A negative factor reflects.
+    pub fn scaling(sx: T, sy: T) -> Affine2<T> {
+        return Affine2::new(sx, T::zero(), T::zero(), sy, T::zero(), T::zero());
+    }
+
+    /// Returns the map shearing x by `kx` times y, and y by `ky` times x.
+    pub fn shear(kx: T, ky: T) -> Affine2<T> {
+        return Affine2::new(T::one(), kx, ky, T::one(), T::zero(), T::zero());
+    }
+
+    /// Returns the map applying this one and then `other`.
+    pub fn then(&self, other: &Affine2<T>) -> Affine2<T> {
+        return Affine2::new(other.a * self.a + other.b * self.c, other.a * self.b + other.b * self.d,
+                            other.c * self.a + other.d * self.c, other.c * self.b + other.d * self.d,
+                            other.a * self.tx + other.b * self.ty + other.tx,
+                            other.c * self.tx + other.d * self.ty + other.ty);
+    }
+
+    /// Returns the image of a point.
+    pub fn apply(&self, p: &Point2<T>) -> Point2<T> {
+        return Point2::new(self.a * p.x + self.b * p.y + self.tx, self.c * p.x + self.d * p.y + self.ty);
+    }
+
+    /// Returns the factor this map scales areas by, negative if it reflects.
+    pub fn determinant(&self) -> T {
+        return self.a * self.d - self.b * self.c;
+    }
+}
+
+/// The result of intersecting two linear objects. Parameters `t` refer to the
+/// first argument and `u` to the second, in the same units as their `point_at`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Intersection<T = f64> {
+    /// The objects do not meet.
+    None,
+    /// The objects cross at a single point interior to both.
+    Proper { point: Point2<T>, t: T, u: T },
+    /// The objects meet at a single point which is an endpoint of at least one of them.
+    Touching { point: Point2<T>, t: T, u: T },
+    /// The objects are collinear and share the segment from `start` to `end`.
+    Overlapping {
+        start: Point2<T>,
+        end: Point2<T>,
+        t: (T, T),
+        u: (T, T),
+    },
+}
+
+/// Returns `num / den` known to lie within [0, 1], snapping the boundary cases
+/// so that endpoints are reported exactly.
+fn exact_ratio<T: Scalar>(num: T, den: T) -> T {
+    if num == T::zero() {
+        return T::zero();
+    } else if num == den {
+        return T::one();
+    }
+    return num / den;
+}
+
+/// Determines whether `num / den` lies within [0, 1] without dividing.
+fn ratio_in_unit<T: Scalar>(num: T, den: T) -> bool {
+    if den > T::zero() {
+        return T::zero() <= num && num <= den;
+    } else {
+        return den <= num && num <= T::zero();
+    }
+}
+
+/// Determines whether `num / den` is non-negative without dividing.
+fn ratio_non_negative<T: Scalar>(num: T, den: T) -> bool {
+    return num == T::zero() || (num > T::zero()) == (den > T::zero());
+}
+
+/// Intersects two segments. Classification is decided from the signs of exact
+/// orientation determinants, and endpoint contacts report the endpoint itself.
+/// Parameters and crossing points are exact for exact coordinate types; with
+/// `i64` coordinates a proper crossing point is truncated onto the grid.
+pub fn segment_intersection<T: Scalar>(a: &Segment2<T>, b: &Segment2<T>) -> Intersection<T> {
+    if a.length_sq() == T::zero() {
+        return point_on_segment(a.start, b, false);
+    } else if b.length_sq() == T::zero() {
+        return point_on_segment(b.start, a, true);
+    }
+
+    let r = a.direction();
+    let s = b.direction();
+    let qp = b.start - a.start;
+    let denom = r.cross(&s);
+
+    if denom == T::zero() {
+        if qp.cross(&r) != T::zero() {
+            return Intersection::None;
+        }
+        return collinear_overlap(a, b.start, b.end);
+    }
+
+    let t_num = qp.cross(&s);
+    let u_num = qp.cross(&r);
+    if !ratio_in_unit(t_num, denom) || !ratio_in_unit(u_num, denom) {
+        return Intersection::None;
+    }
+
+    let t = exact_ratio(t_num, denom);
+    let u = exact_ratio(u_num, denom);
+    if t_num == T::zero() || t_num == denom {
+        return Intersection::Touching { point: a.point_at(t), t: t, u: u };
+    } else if u_num == T::zero() || u_num == denom {
+        return Intersection::Touching { point: b.point_at(u), t: t, u: u };
+    }
+    return Intersection::Proper { point: a.point_at(t), t: t, u: u };
+}
+
+/// Intersects a segment with a ray. `t` refers to the segment, `u` to the ray.
+/// As with `segment_intersection`, the answer is decided without dividing.
+pub fn segment_ray_intersection<T: Scalar>(a: &Segment2<T>, ray: &Ray2<T>) -> Intersection<T> {
+    let zero = T::zero();
+    let r = a.direction();
+    let s = ray.direction;
+    if s.length_sq() == zero {
+        return point_on_segment(ray.origin, a, true);
+    } else if r.length_sq() == zero {
+        let along = (a.start - ray.origin).dot(&s);
+        if (a.start - ray.origin).cross(&s) != zero || along < zero {
+            return Intersection::None;
+        }
+        return Intersection::Touching { point: a.start, t: zero, u: exact_ratio(along, s.length_sq()) };
+    }
+
+    let qp = ray.origin - a.start;
+    let denom = r.cross(&s);
+
+    if denom == zero {
+        if qp.cross(&r) != zero {
+            return Intersection::None;
+        }
+        // Clip the segment against the ray's half-line, along which the
+        // ends lie at `u` scaled by `s_len_sq`.
+        let s_len_sq = s.length_sq();
+        let d_start = (a.start - ray.origin).dot(&s);
+        let d_end = (a.end - ray.origin).dot(&s);
+        let (t_lo, lo, t_hi, hi) = if d_start <= d_end {
+            (zero, d_start, T::one(), d_end)
+        } else {
+            (T::one(), d_end, zero, d_start)
+        };
+        if hi < zero {
+            return Intersection::None;
+        }
+        // Clipped, the overlap starts at the ray's origin itself
+        let (t_lo, lo, start) = if lo < zero {
+            (a.project(&ray.origin), zero, ray.origin)
+        } else {
+            (t_lo, lo, a.point_at(t_lo))
+        };
+        let end = if t_hi == zero { a.start } else { a.end };
+        if lo == hi {
+            return Intersection::Touching { point: end, t: t_hi, u: exact_ratio(hi, s_len_sq) };
+        }
+        return Intersection::Overlapping {
+            start: start,
+            end: end,
+            t: (t_lo, t_hi),
+            u: (exact_ratio(lo, s_len_sq), exact_ratio(hi, s_len_sq)),
+        };
+    }
+
+    let t_num = qp.cross(&s);
+    let u_num = qp.cross(&r);
+    if !ratio_in_unit(t_num, denom) || !ratio_non_negative(u_num, denom) {
+        return Intersection::None;
+    }
+
+    let t = exact_ratio(t_num, denom);
+    let u = exact_ratio(u_num, denom);
+    if u_num == zero {
+        return Intersection::Touching { point: ray.origin, t: t, u: u };
+    } else if t_num == zero || t_num == denom {
+        return Intersection::Touching { point: a.point_at(t), t: t, u: u };
+    }
+    return Intersection::Proper { point: a.point_at(t), t: t, u: u };
+}
+
+/// Intersects a degenerate segment `p` with `seg`. When `point_second` is set
+/// the parameters are swapped so that `t` still refers to the first argument.
+fn point_on_segment<T: Scalar>(p: Point2<T>, seg: &Segment2<T>, point_second: bool) -> Intersection<T> {
+    if !seg.contains(&p) {
+        return Intersection::None;
+    }
+    let along = seg.project(&p);
+    if point_second {
+        return Intersection::Touching { point: p, t: along, u: T::zero() };
+    }
+    return Intersection::Touching { point: p, t: T::zero(), u: along };
+}
+
+/// Computes the shared part of `a` and the collinear segment from `c` to `d`.
+/// The overlap is classified from undivided dot products, so that it's exact
+/// even where division truncates; parameters are only divided out at the end.
+fn collinear_overlap<T: Scalar>(a: &Segment2<T>, c: Point2<T>, d: Point2<T>) -> Intersection<T> {
+    let zero = T::zero();
+    let one = T::one();
+    let r = a.direction();
+    let len_sq = r.length_sq();
+    let dc = (c - a.start).dot(&r);
+    let dd = (d - a.start).dot(&r);
+
+    // Overlap interval along `a`, scaled by `len_sq`, with the matching
+    // points of the other segment.
+    let (lo, lo_pt, lo_u) = if dc <= dd { (dc, c, zero) } else { (dd, d, one) };
+    let (hi, hi_pt, hi_u) = if dc <= dd { (dd, d, one) } else { (dc, c, zero) };
+    if hi < zero || lo > len_sq {
+        return Intersection::None;
+    }
+
+    let other = Segment2::new(c, d);
+    let (t0, p0, u0) = if lo < zero {
+        (zero, a.start, other.project(&a.start))
+    } else {
+        (exact_ratio(lo, len_sq), lo_pt, lo_u)
+    };
+    let (t1, p1, u1) = if hi > len_sq {
+        (one, a.end, other.project(&a.end))
+    } else {
+        (exact_ratio(hi, len_sq), hi_pt, hi_u)
+    };
+
+    if p0 == p1 {
+        return Intersection::Touching { point: p0, t: t0, u: u0 };
+    }
+    return Intersection::Overlapping { start: p0, end: p1, t: (t0, t1), u: (u0, u1) };
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Circle<T = f64> {
+    pub center: Point2<T>,
+    pub radius: T,
+}
+
+impl<T: Scalar> Circle<T> {
+    /// Returns a new Circle instance.
+    pub fn new(center: Point2<T>, radius: T) -> Circle<T> {
+        return Circle { center: center, radius: radius };
+    }
+
+    /// Returns the circle passing through all three points, or `None` if they are colinear.
+    pub fn circumcircle(a: &Point2<T>, b: &Point2<T>, c: &Point2<T>) -> Option<Circle<T>> {
+        let ab = *b - *a;
+        let ac = *c - *a;
+        let d = ab.cross(&ac) + ab.cross(&ac);
+        if d == T::zero() {
+            return None;
+        }
+        let ab_sq = ab.length_sq();
+        let ac_sq = ac.length_sq();
+        let offset = Vector2::new(
+            (ac.y * ab_sq - ab.y * ac_sq) / d,
+            (ab.x * ac_sq - ac.x * ab_sq) / d,
+        );
+        return Some(Circle::new(*a + offset, offset.length()));
+    }
+
+    /// Returns the largest circle inscribed in the triangle, or `None` if the points are colinear.
+    pub fn incircle(a: &Point2<T>, b: &Point2<T>, c: &Point2<T>) -> Option<Circle<T>> {
+        let area = area_of_triangle(a, b, c).abs();
+        if area == T::zero() {
+            return None;
+        }
+        let la = b.dist_sq(c).sqrt();
+        let lb = a.dist_sq(c).sqrt();
+        let lc = a.dist_sq(b).sqrt();
+        let perimeter = la + lb + lc;
+        let center = Point2::new(
+            (la * a.x + lb * b.x + lc * c.x) / perimeter,
+            (la * a.y + lb * b.y + lc * c.y) / perimeter,
+        );
+        return Some(Circle::new(center, (area + area) / perimeter));
+    }
+
+    /// Determines if the point lies inside or on this circle.
+    pub fn contains(&self, p: &Point2<T>) -> bool {
+        return self.center.dist_sq(p) <= self.radius * self.radius;
+    }
 }
 
 /// Returns the midpoint of two points along the line between them.
-pub fn midpoint(a: Point2, b: Point2) -> Point2 {
+pub fn midpoint<T: Scalar>(a: Point2<T>, b: Point2<T>) -> Point2<T> {
     return Point2 {
-        x: 0.5 * (a.x + b.x),
-        y: 0.5 * (a.y + b.y),
+        x: (a.x + b.x).half(),
+        y: (a.y + b.y).half(),
     };
 }
 
 /// Returns the area of the parallelogram formed by three points.
-pub fn area_of_parallelogram(a: &Point2, b: &Point2, c: &Point2) -> f64 {
-    return (b.x - a.x) * (c.y - a.y) * (c.x - a.x) * (b.y - a.y);
+pub fn area_of_parallelogram<T: Scalar>(a: &Point2<T>, b: &Point2<T>, c: &Point2<T>) -> T {
+    return (b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y);
 }
 
 /// Returns the area of the triangle formed by three points.
-pub fn area_of_triangle(a: &Point2, b: &Point2, c: &Point2) -> f64 {
-    return 0.5 * area_of_parallelogram(a, b, c);
+pub fn area_of_triangle<T: Scalar>(a: &Point2<T>, b: &Point2<T>, c: &Point2<T>) -> T {
+    return area_of_parallelogram(a, b, c).half();
 }
 
 /// Determines if the three points form a left-hand turn.
-pub fn is_lht(a: &Point2, b: &Point2, c: &Point2) -> bool {
-    return area_of_parallelogram(a, b, c) > 0.0;
+pub fn is_lht<T: Scalar>(a: &Point2<T>, b: &Point2<T>, c: &Point2<T>) -> bool {
+    return area_of_parallelogram(a, b, c) > T::zero();
 }
 
 /// Determines if the three points form a left-hand turn, or if they are colinear.
-pub fn is_lht_or_on(a: &Point2, b: &Point2, c: &Point2) -> bool {
-    return area_of_parallelogram(a, b, c) >= 0.0;
+pub fn is_lht_or_on<T: Scalar>(a: &Point2<T>, b: &Point2<T>, c: &Point2<T>) -> bool {
+    return area_of_parallelogram(a, b, c) >= T::zero();
 }
 
 /// Determines if the three points form a right-hand turn.
-pub fn is_rht(a: &Point2, b: &Point2, c: &Point2) -> bool {
-    return area_of_parallelogram(a, b, c) < 0.0;
+pub fn is_rht<T: Scalar>(a: &Point2<T>, b: &Point2<T>, c: &Point2<T>) -> bool {
+    return area_of_parallelogram(a, b, c) < T::zero();
 }
 
 /// Determines if the three points form a right-hand turn, or if they are colinear.
-pub fn is_rht_or_on(a: &Point2, b: &Point2, c: &Point2) -> bool {
-    return area_of_parallelogram(a, b, c) <= 0.0;
+pub fn is_rht_or_on<T: Scalar>(a: &Point2<T>, b: &Point2<T>, c: &Point2<T>) -> bool {
+    return area_of_parallelogram(a, b, c) <= T::zero();
 }
 
 /// Determines if the points C and D are on the same side of the line formed by points A and B.
-pub fn is_same_side(a: &Point2, b: &Point2, c: &Point2, d: &Point2) -> bool {
+pub fn is_same_side<T: Scalar>(a: &Point2<T>, b: &Point2<T>, c: &Point2<T>, d: &Point2<T>) -> bool {
     return is_lht(a, b, c) && is_lht(a, b, d) || is_rht(a, b, c) && is_rht(a, b, d);
 }
 
 /// Determines if the point D is contained within the circle formed by points A, B, and C.
-pub fn in_circle(a: &Point2, b: &Point2, c: &Point2, d: &Point2) -> bool {
+pub fn in_circle<T: Scalar>(a: &Point2<T>, b: &Point2<T>, c: &Point2<T>, d: &Point2<T>) -> bool {
     let adx = a.x - d.x;
     let ady = a.y - d.y;
     let bdx = b.x - d.x;
@@ -116,5 +903,206 @@ pub fn in_circle(a: &Point2, b: &Point2, c: &Point2, d: &Point2) -> bool {
     let blift = bdx * bdx + bdy * bdy;
     let clift = cdx * cdx + cdy * cdy;
 
-    return (alift * bcdet + blift * cadet + clift * abdet) > 0.0;
-}
\ No newline at end of file
+    return (alift * bcdet + blift * cadet + clift * abdet) > T::zero();
+}
+
+/// Returns the corners of the convex hull of a set of points, counter-clockwise
+/// starting from the least in `PartialOrd` order (leftmost, then lowest), using
+/// Andrew's monotone chain. Points in the middle of a hull edge are left out, as are duplicates.
+/// Points that can't be compared, such as those with NaN coordinates, are ignored.
+pub fn convex_hull<T: Scalar>(points: &[Point2<T>]) -> Vec<Point2<T>> {
+    let mut sorted: Vec<Point2<T>> = points.iter().cloned().filter(|p| p.partial_cmp(p).is_some()).collect();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    // Lower hull left to right, then upper hull right to left
+    let mut hull: Vec<Point2<T>> = Vec::with_capacity(sorted.len() + 1);
+    hull_chain(&mut hull, sorted.iter());
+    hull_chain(&mut hull, sorted.iter().rev());
+    return hull;
+}
+
+/// Appends one chain of a monotone chain hull, keeping only left turns. The
+/// last point is dropped, as it starts the next chain.
+fn hull_chain<'a, T: Scalar + 'a, I: Iterator<Item = &'a Point2<T>>>(hull: &mut Vec<Point2<T>>, points: I) {
+    let start = hull.len();
+    for p in points {
+        while hull.len() >= start + 2 && !is_lht(&hull[hull.len() - 2], &hull[hull.len() - 1], p) {
+            hull.pop();
+        }
+        hull.push(*p);
+    }
+    hull.pop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scalar::Rational;
+
+    fn seg(a: (f64, f64), b: (f64, f64)) -> Segment2 {
+        return Segment2::new(Point2::from(a), Point2::from(b));
+    }
+
+    #[test]
+    fn turns() {
+        let a = Point2::new(0.0, 0.0);
+        let b = Point2::new(1.0, 0.0);
+        assert!(is_lht(&a, &b, &Point2::new(1.0, 1.0)));
+        assert!(is_rht(&a, &b, &Point2::new(1.0, -1.0)));
+        assert!(is_lht_or_on(&a, &b, &Point2::new(2.0, 0.0)));
+        assert!(!is_lht(&a, &b, &Point2::new(2.0, 0.0)));
+    }
+
+    #[test]
+    fn proper_intersection() {
+        let i = segment_intersection(&seg((0.0, 0.0), (2.0, 2.0)), &seg((0.0, 2.0), (2.0, 0.0)));
+        assert_eq!(i, Intersection::Proper { point: Point2::new(1.0, 1.0), t: 0.5, u: 0.5 });
+    }
+
+    #[test]
+    fn touching_intersection() {
+        let i = segment_intersection(&seg((0.0, 0.0), (2.0, 0.0)), &seg((1.0, 0.0), (1.0, 3.0)));
+        assert_eq!(i, Intersection::Touching { point: Point2::new(1.0, 0.0), t: 0.5, u: 0.0 });
+
+        let i = segment_intersection(&seg((0.0, 0.0), (1.0, 0.0)), &seg((1.0, 0.0), (3.0, 0.0)));
+        assert_eq!(i, Intersection::Touching { point: Point2::new(1.0, 0.0), t: 1.0, u: 0.0 });
+    }
+
+    #[test]
+    fn overlapping_intersection() {
+        let i = segment_intersection(&seg((0.0, 0.0), (4.0, 0.0)), &seg((3.0, 0.0), (1.0, 0.0)));
+        assert_eq!(i, Intersection::Overlapping {
+            start: Point2::new(1.0, 0.0),
+            end: Point2::new(3.0, 0.0),
+            t: (0.25, 0.75),
+            u: (1.0, 0.0),
+        });
+    }
+
+    #[test]
+    fn disjoint_segments() {
+        assert_eq!(segment_intersection(&seg((0.0, 0.0), (1.0, 0.0)), &seg((0.0, 1.0), (1.0, 1.0))),
+                   Intersection::None);
+        assert_eq!(segment_intersection(&seg((0.0, 0.0), (1.0, 0.0)), &seg((2.0, 0.0), (3.0, 0.0))),
+                   Intersection::None);
+    }
+
+    #[test]
+    fn ray_intersection() {
+        let ray = Ray2::new(Point2::new(1.0, -1.0), Vector2::new(0.0, 1.0));
+        let i = segment_ray_intersection(&seg((0.0, 0.0), (2.0, 0.0)), &ray);
+        assert_eq!(i, Intersection::Proper { point: Point2::new(1.0, 0.0), t: 0.5, u: 1.0 });
+
+        let away = Ray2::new(Point2::new(1.0, -1.0), Vector2::new(0.0, -1.0));
+        assert_eq!(segment_ray_intersection(&seg((0.0, 0.0), (2.0, 0.0)), &away), Intersection::None);
+
+        let along = Ray2::new(Point2::new(1.0, 0.0), Vector2::new(1.0, 0.0));
+        let i = segment_ray_intersection(&seg((0.0, 0.0), (2.0, 0.0)), &along);
+        assert_eq!(i, Intersection::Overlapping {
+            start: Point2::new(1.0, 0.0),
+            end: Point2::new(2.0, 0.0),
+            t: (0.5, 1.0),
+            u: (0.0, 1.0),
+        });
+    }
+
+    #[test]
+    fn point_segment_distance() {
+        let s = seg((0.0, 0.0), (4.0, 0.0));
+        assert_eq!(s.project(&Point2::new(1.0, 3.0)), 0.25);
+        assert_eq!(s.closest_point(&Point2::new(-2.0, 1.0)), Point2::new(0.0, 0.0));
+        assert_eq!(s.dist_sq(&Point2::new(2.0, -3.0)), 9.0);
+    }
+
+    #[test]
+    fn circles() {
+        let a = Point2::new(0.0, 0.0);
+        let b = Point2::new(4.0, 0.0);
+        let c = Point2::new(0.0, 3.0);
+        let circum = Circle::circumcircle(&a, &b, &c).unwrap();
+        assert_eq!(circum, Circle::new(Point2::new(2.0, 1.5), 2.5));
+
+        let inc = Circle::incircle(&a, &b, &c).unwrap();
+        assert_eq!(inc, Circle::new(Point2::new(1.0, 1.0), 1.0));
+
+        assert!(Circle::circumcircle(&a, &b, &Point2::new(8.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn exact_intersection() {
+        let r = |n, d| Rational::new(n, d);
+        let a = Segment2::new(Point2::<Rational>::from((0, 0)), Point2::from((3, 1)));
+        let b = Segment2::new(Point2::<Rational>::from((0, 1)), Point2::from((3, 0)));
+        assert_eq!(segment_intersection(&a, &b), Intersection::Proper {
+            point: Point2::new(r(3, 2), r(1, 2)),
+            t: r(1, 2),
+            u: r(1, 2),
+        });
+    }
+
+    #[test]
+    fn affine_maps() {
+        let p = Point2::new(1.0, 2.0);
+        let shift = Affine2::translation(Vector2::new(1.0, -1.0));
+        let flip = Affine2::scaling(-1.0, 1.0);
+        assert_eq!(shift.then(&flip).apply(&p), Point2::new(-2.0, 1.0));
+        assert_eq!(flip.then(&shift).apply(&p), Point2::new(0.0, 1.0));
+        assert_eq!(Affine2::shear(2.0, 0.0).apply(&p), Point2::new(5.0, 2.0));
+        assert_eq!(Affine2::identity().apply(&p), p);
+        assert!(flip.determinant() < 0.0);
+
+        let quarter = Affine2::<f64>::rotation(::std::f64::consts::FRAC_PI_2);
+        assert!(quarter.apply(&p).dist_sq(&Point2::new(-2.0, 1.0)) < 1e-12);
+        assert_eq!(Affine2::<i64>::rotation(::std::f64::consts::PI).apply(&Point2::new(1, 2)), Point2::new(-1, -2));
+    }
+
+    #[test]
+    fn integer_coordinates() {
+        let a = Point2::<i64>::new(0, 0);
+        let b = Point2::<i64>::new(4, 0);
+        assert!(is_lht(&a, &b, &Point2::new(2, 1)));
+        assert_eq!(midpoint(a, b), Point2::new(2, 0));
+        assert_eq!(Point2::<f32>::from((1i16, 2i16)).cast::<i64>(), Point2::new(1, 2));
+
+        // Collinear segments are told apart without truncating division
+        let seg = Segment2::new(a, Point2::new(3, 0));
+        let left = Segment2::new(Point2::new(-1, 0), Point2::new(-2, 0));
+        assert_eq!(segment_intersection(&seg, &left), Intersection::None);
+        assert_eq!(segment_intersection(&seg, &Segment2::new(Point2::new(4, 0), Point2::new(5, 0))), Intersection::None);
+        match segment_intersection(&seg, &Segment2::new(Point2::new(2, 0), Point2::new(5, 0))) {
+            Intersection::Overlapping { start, end, .. } => assert_eq!((start, end), (Point2::new(2, 0), Point2::new(3, 0))),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // And so are segments along a ray, including one behind it
+        let ray = Ray2::new(a, Vector2::new(2, 0));
+        assert_eq!(segment_ray_intersection(&Segment2::new(Point2::new(-3, 0), Point2::new(-1, 0)), &ray), Intersection::None);
+        match segment_ray_intersection(&Segment2::new(Point2::new(-3, 0), Point2::new(1, 0)), &ray) {
+            Intersection::Overlapping { start, end, .. } => assert_eq!((start, end), (a, Point2::new(1, 0))),
+            other => panic!("unexpected {:?}", other),
+        }
+        let dot = Segment2::new(Point2::new(-1, 0), Point2::new(-1, 0));
+        assert_eq!(segment_ray_intersection(&dot, &ray), Intersection::None);
+
+        // A crossing near an end isn't mistaken for a touch at it
+        let crossing = Segment2::new(Point2::new(1, -1), Point2::new(1, 2));
+        match segment_ray_intersection(&crossing, &ray) {
+            Intersection::Proper { .. } => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn hull_of_points() {
+        let points: Vec<Point2<i64>> = [(2, 2), (0, 0), (4, 0), (2, 0), (4, 4), (0, 4), (1, 3), (4, 4), (0, 2)]
+            .iter().map(Point2::from).collect();
+        assert_eq!(convex_hull(&points), vec![Point2::new(0, 0), Point2::new(4, 0), Point2::new(4, 4), Point2::new(0, 4)]);
+        assert_eq!(convex_hull(&points[..4]), vec![Point2::new(0, 0), Point2::new(4, 0), Point2::new(2, 2)]);
+        assert_eq!(convex_hull(&[Point2::new(1.0, 1.0), Point2::new(1.0, 1.0)]), vec![Point2::new(1.0, 1.0)]);
+        assert_eq!(convex_hull(&[Point2::new(0.0, 0.0), Point2::new(1.0, 1.0), Point2::new(2.0, 2.0)]).len(), 2);
+    }
+}
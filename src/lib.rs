@@ -1,7 +1,22 @@
 //! Crate for working with Doubly Linked Edge Lists (DCELs)
 
+#![allow(clippy::needless_return, clippy::redundant_field_names, clippy::manual_div_ceil, clippy::manual_is_multiple_of)]
+
+pub mod scalar;
 pub mod geometry; 
 //pub mod api;
 #[macro_use]
 pub mod arena;
 pub mod dcel;
+pub mod observe;
+pub mod compare;
+pub mod combine;
+pub mod transform;
+pub mod weld;
+pub mod snap;
+pub mod build;
+pub mod triangulate;
+pub mod overlay;
+pub mod oplog;
+pub mod session;
+pub mod formats;
@@ -0,0 +1,180 @@
+//! Change notification for DCELs. Observers registered on a DCEL are told
+//! about each element it adds or removes and each higher-level edit it
+//! makes, so that derived data such as spatial indexes, render buffers or
+//! cached areas can be kept up to date instead of being rebuilt.
+
+use std::fmt;
+
+use dcel::*;
+
+/// A vertex, half-edge or face of a DCEL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Element {
+    Vertex(VertexId),
+    HalfEdge(HalfEdgeId),
+    Face(FaceId),
+}
+
+/// A change made to a DCEL. Higher-level edits are reported after the
+/// additions and removals they're made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    VertexAdded(VertexId),
+    HalfEdgeAdded(HalfEdgeId),
+    FaceAdded(FaceId),
+    /// An element was removed, and its id may be reused.
+    ElementRemoved(Element),
+    /// The vertex was given new coordinates.
+    VertexMoved(VertexId),
+    /// Every cycle was reversed, as after a reflection: each half-edge now
+    /// starts where it used to end.
+    OrientationReversed,
+    /// `old` was split in two, keeping its origin; `new` runs on from the
+    /// new vertex to `old`'s former destination.
+    EdgeSplit { old: HalfEdgeId, new: HalfEdgeId },
+    /// The edge was flipped, and its twin with it.
+    EdgeFlipped(HalfEdgeId),
+    /// The edge was collapsed, merging the `removed` vertex into `kept`.
+    EdgeCollapsed { kept: VertexId, removed: VertexId },
+    /// The `removed` vertex was welded onto `kept`, which took over its edges.
+    VerticesWelded { kept: VertexId, removed: VertexId },
+    /// `removed` was merged into `kept`, which now covers both.
+    FacesMerged { kept: FaceId, removed: FaceId },
+    /// Part of `old` was split off into the new face `new`.
+    FaceSplit { old: FaceId, new: FaceId },
+    /// The DCEL was rebuilt from scratch, as by snap rounding, and every id
+    /// may now refer to a different element.
+    Rebuilt,
+    /// The DCEL was restored to an earlier state wholesale, as when an
+    /// operation is rolled back or undone, so anything derived from it
+    /// should be rebuilt.
+    Restored,
+}
+
+/// Identifies an observer registered with `DCEL::add_observer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObserverId(usize);
+
+/// A registered observer.
+type Observer = Box<dyn FnMut(&Event) + Send>;
+
+/// The observers registered on a DCEL. Observers belong to one DCEL, so a
+/// clone starts with none.
+#[derive(Default)]
+pub(crate) struct Observers {
+    next_id: usize,
+    list: Vec<(ObserverId, Observer)>,
+    /// The number of events sent so far.
+    pub(crate) sent: usize,
+}
+
+impl Clone for Observers {
+    fn clone(&self) -> Observers {
+        return Observers::default();
+    }
+}
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{} observers", self.list.len());
+    }
+}
+
+impl<P, F> DCEL<P, F> {
+    /// Registers a function to be called with every change made to this
+    /// DCEL from now on. Observers must be `Send`, like the DCEL itself.
+    pub fn add_observer<O: FnMut(&Event) + Send + 'static>(&mut self, observer: O) -> ObserverId {
+        let id = ObserverId(self.observers.next_id);
+        self.observers.next_id += 1;
+        self.observers.list.push((id, Box::new(observer)));
+        return id;
+    }
+
+    /// Unregisters an observer, returning false if it wasn't registered.
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        let before = self.observers.list.len();
+        self.observers.list.retain(|&(other, _)| other != id);
+        return self.observers.list.len() < before;
+    }
+
+    /// Tells every observer about a change.
+    pub(crate) fn notify(&mut self, event: Event) {
+        self.observers.sent += 1;
+        for &mut (_, ref mut observer) in &mut self.observers.list {
+            observer(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geometry::Point2;
+    use oplog::Op;
+    use session::EditSession;
+    use std::sync::{Arc, Mutex};
+
+    fn record(dcel: &mut DCEL) -> Arc<Mutex<Vec<Event>>> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        dcel.add_observer(move |event| sink.lock().unwrap().push(*event));
+        return events;
+    }
+
+    #[test]
+    fn reports_edits() {
+        let points = [Point2::new(0.0, 0.0), Point2::new(1.0, 0.0), Point2::new(1.0, 1.0), Point2::new(0.0, 1.0)];
+        let mut dcel = DCEL::from_polygons(&points, &[&[0, 1, 2], &[0, 2, 3]]).unwrap();
+        let events = record(&mut dcel);
+        let diagonal = dcel.vertex_edges(VertexId::from(0))
+            .find(|&e| dcel.get_destination(e) == VertexId::from(2)).unwrap();
+        let twin = dcel.edges[diagonal].twin().unwrap();
+
+        dcel.flip_edge(diagonal).unwrap();
+        dcel.flip_edge(diagonal).unwrap();
+        assert_eq!(events.lock().unwrap().drain(..).collect::<Vec<_>>(),
+                   vec![Event::EdgeFlipped(diagonal), Event::EdgeFlipped(diagonal)]);
+
+        let mid = dcel.split_edge_in_half(diagonal);
+        let new = dcel.edges[diagonal].next().unwrap();
+        assert_eq!(events.lock().unwrap().drain(..).collect::<Vec<_>>(), vec![
+            Event::VertexAdded(mid),
+            Event::HalfEdgeAdded(HalfEdgeId::from(10)),
+            Event::HalfEdgeAdded(HalfEdgeId::from(11)),
+            Event::EdgeSplit { old: diagonal, new: new },
+        ]);
+
+        // Merging away both halves of the split diagonal
+        let (kept, removed) = (dcel.edges[diagonal].face(), dcel.edges[twin].face());
+        dcel.remove_inner_edge(diagonal);
+        assert_eq!(events.lock().unwrap().drain(..).collect::<Vec<_>>(), vec![
+            Event::ElementRemoved(Element::HalfEdge(diagonal)),
+            Event::ElementRemoved(Element::HalfEdge(HalfEdgeId::from(11))),
+            Event::ElementRemoved(Element::Face(removed)),
+            Event::FacesMerged { kept: kept, removed: removed },
+        ]);
+        dcel.remove_edge(twin);
+        assert_eq!(events.lock().unwrap().drain(..).collect::<Vec<_>>(), vec![
+            Event::ElementRemoved(Element::Vertex(mid)),
+            Event::ElementRemoved(Element::HalfEdge(twin)),
+            Event::ElementRemoved(Element::HalfEdge(HalfEdgeId::from(10))),
+        ]);
+        dcel.validate().unwrap();
+
+        // Clones start unobserved
+        let mut copy = dcel.clone();
+        copy.add_vertex(Point2::new(2.0, 2.0));
+        assert!(events.lock().unwrap().is_empty());
+
+        // Observed DCELs can be handed to other threads
+        let mut dcel = ::std::thread::spawn(move || dcel).join().unwrap();
+
+        // Failed operations change nothing, and undo restores wholesale
+        assert!(dcel.apply(&Op::FlipEdge(HalfEdgeId::from(0))).is_err());
+        assert!(events.lock().unwrap().is_empty());
+        let mut session = EditSession::new(dcel);
+        let v = session.edit(|dcel| dcel.add_vertex(Point2::new(2.0, 2.0)));
+        session.undo();
+        assert_eq!(events.lock().unwrap().drain(..).collect::<Vec<_>>(), vec![Event::VertexAdded(v), Event::Restored]);
+    }
+}
@@ -0,0 +1,465 @@
+//! A log of whole, transactional edits to a planar DCEL, after the
+//! "serialized log" design in `doc/usage_thoughts_v0.1.md`. Each operation
+//! either applies completely or leaves the DCEL as it was, and replaying a
+//! log onto the DCEL it started from rebuilds the same DCEL, ids included.
+//!
+//! Logs also have a line-based text form, for persistence and audit trails:
+//!
+//! ```text
+//! dcel-log 1
+//! add 0 0 1 0 1 1           # corners of a polygon, as x y pairs
+//! split-edge e4
+//! flip e4
+//! split-face e3 e7
+//! merge f1 f2               # kept face, then the face merged into it
+//! remove e5
+//! collapse e5
+//! ```
+//!
+//! As in the text dump of a DCEL, face payloads aren't stored; polygons
+//! read back from text carry the default payload.
+
+use std::error::Error;
+use std::fmt;
+use std::fmt::Write as FmtWrite;
+use std::mem;
+
+use build::BuildError;
+use dcel::*;
+use formats::{parse_error, parse_token, FormatError};
+use formats::text::parse_ref;
+use geometry::*;
+use observe::Event;
+use scalar::Scalar;
+
+/// The header line, naming the format version.
+const HEADER: &str = "dcel-log 1";
+
+/// A whole edit of a planar DCEL.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op<T = f64, F = ()> {
+    /// Adds a polygon carrying a payload as a new face; see
+    /// `DCEL::add_polygon`.
+    AddPolygon(Vec<Point2<T>>, F),
+    /// Splits an edge at its midpoint; see `DCEL::split_edge_in_half`.
+    SplitEdge(HalfEdgeId),
+    /// Flips an edge between two inner faces; see `DCEL::flip_edge`.
+    FlipEdge(HalfEdgeId),
+    /// Splits a face with an edge from the origin of the first half-edge to
+    /// the origin of the second; see `DCEL::split_face`.
+    SplitFace(HalfEdgeId, HalfEdgeId),
+    /// Merges the second face into the first by removing every edge between
+    /// them.
+    MergeFaces(FaceId, FaceId),
+    /// Removes an edge; see `DCEL::remove_edge`.
+    RemoveEdge(HalfEdgeId),
+    /// Collapses an edge into its destination; see `DCEL::collapse_edge`.
+    CollapseEdge(HalfEdgeId),
+}
+
+/// What a successful operation created or kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Applied {
+    /// The face added by `AddPolygon`, or kept by `MergeFaces`.
+    Face(FaceId),
+    /// The vertex added by `SplitEdge`, or kept by `CollapseEdge`.
+    Vertex(VertexId),
+    /// The half-edge added by `SplitFace`.
+    HalfEdge(HalfEdgeId),
+    /// `FlipEdge` and `RemoveEdge` create nothing.
+    Nothing,
+}
+
+/// Reasons an operation can't be applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpError {
+    /// The operation refers to a vertex, half-edge or face that doesn't
+    /// exist. Holds the kind and index of the missing element.
+    MissingElement(&'static str, usize),
+    /// The faces to merge are the same face, or the outer face, or don't
+    /// share an edge.
+    NotAdjacent(FaceId, FaceId),
+    /// The half-edges to split a face between aren't distinct corners of the
+    /// same face.
+    BadDiagonal(HalfEdgeId, HalfEdgeId),
+    Topology(TopologyError),
+    Build(BuildError),
+}
+
+impl fmt::Display for OpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OpError::MissingElement(kind, index) => write!(f, "reference to missing {} {}", kind, index),
+            OpError::NotAdjacent(a, b) => write!(f, "faces {:?} and {:?} can't be merged", a, b),
+            OpError::BadDiagonal(a, b) => write!(f, "half-edges {:?} and {:?} can't be joined", a, b),
+            OpError::Topology(ref err) => write!(f, "{}", err),
+            OpError::Build(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for OpError {}
+
+impl From<TopologyError> for OpError {
+    fn from(err: TopologyError) -> OpError {
+        return OpError::Topology(err);
+    }
+}
+
+impl From<BuildError> for OpError {
+    fn from(err: BuildError) -> OpError {
+        return OpError::Build(err);
+    }
+}
+
+impl<T: Scalar, F: Clone> DCEL<Point2<T>, F> {
+    /// Applies an operation as a transaction: if it fails, the DCEL is
+    /// restored to its state beforehand, and observers told of any changes
+    /// made in the meantime are sent `Event::Restored`.
+    pub fn apply(&mut self, op: &Op<T, F>) -> Result<Applied, OpError> {
+        let mut before = self.clone();
+        let sent = self.observers.sent;
+        let result = self.apply_unchecked(op);
+        if result.is_err() {
+            before.observers = mem::take(&mut self.observers);
+            *self = before;
+            if self.observers.sent != sent {
+                self.notify(Event::Restored);
+            }
+        }
+        return result;
+    }
+
+    fn apply_unchecked(&mut self, op: &Op<T, F>) -> Result<Applied, OpError> {
+        match *op {
+            Op::AddPolygon(ref points, ref data) => return Ok(Applied::Face(self.add_polygon(points, data.clone())?)),
+            Op::SplitEdge(e) => {
+                self.check_edge(e)?;
+                return Ok(Applied::Vertex(self.split_edge_in_half(e)));
+            }
+            Op::FlipEdge(e) => {
+                self.check_edge(e)?;
+                self.flip_edge(e)?;
+                return Ok(Applied::Nothing);
+            }
+            Op::SplitFace(a, b) => {
+                self.check_edge(a)?;
+                self.check_edge(b)?;
+                if !self.is_diagonal(a, b) {
+                    return Err(OpError::BadDiagonal(a, b));
+                }
+                return Ok(Applied::HalfEdge(self.split_face(a, b)));
+            }
+            Op::MergeFaces(keep, other) => {
+                self.check_face(keep)?;
+                self.check_face(other)?;
+                let shared: Vec<HalfEdgeId> = self.edges.iter()
+                    .filter(|&(_, edge)| edge.face() == keep && self.edges[edge.twin().unwrap()].face() == other)
+                    .map(|(e, _)| e)
+                    .collect();
+                if keep == other || keep == self.outer_face || other == self.outer_face || shared.is_empty() {
+                    return Err(OpError::NotAdjacent(keep, other));
+                }
+                // Once the faces are one, the rest of the shared edges lie
+                // inside it
+                self.remove_inner_edge(shared[0]);
+                for &e in &shared[1..] {
+                    if self.edges.contains(e) {
+                        self.remove_edge(e);
+                    }
+                }
+                return Ok(Applied::Face(keep));
+            }
+            Op::RemoveEdge(e) => {
+                self.check_edge(e)?;
+                self.remove_edge(e);
+                return Ok(Applied::Nothing);
+            }
+            Op::CollapseEdge(e) => {
+                self.check_edge(e)?;
+                return Ok(Applied::Vertex(self.collapse_edge(e)?));
+            }
+        }
+    }
+
+    /// Determines whether an edge from the origin of `a` to the origin of `b`
+    /// would be a diagonal of their face: not a side of it already, leaving
+    /// both corners into the face, and meeting its boundary only at its ends.
+    fn is_diagonal(&self, a: HalfEdgeId, b: HalfEdgeId) -> bool {
+        let (edge_a, edge_b) = (&self.edges[a], &self.edges[b]);
+        if edge_a.face() != edge_b.face() || edge_a.origin() == edge_b.origin()
+            || edge_a.next() == Some(b) || edge_b.next() == Some(a) {
+            return false;
+        }
+        let (p, q) = (self.position(a), self.position(b));
+        if !self.corner_contains(a, &q) || !self.corner_contains(b, &p) {
+            return false;
+        }
+        let diagonal = Segment2::new(p, q);
+        return self.face_cycles(edge_a.face()).into_iter().flat_map(|start| self.cycle_edges(start)).all(|e| {
+            let side = Segment2::new(self.position(e), self.position(self.edges[e].next().unwrap()));
+            match segment_intersection(&diagonal, &side) {
+                Intersection::None => return true,
+                Intersection::Touching { point, .. } => return point == p || point == q,
+                _ => return false,
+            }
+        });
+    }
+
+    fn check_edge(&self, e: HalfEdgeId) -> Result<(), OpError> {
+        if !self.edges.contains(e) {
+            return Err(OpError::MissingElement("half-edge", e.into()));
+        }
+        return Ok(());
+    }
+
+    fn check_face(&self, f: FaceId) -> Result<(), OpError> {
+        if !self.faces.contains(f) {
+            return Err(OpError::MissingElement("face", f.into()));
+        }
+        return Ok(());
+    }
+}
+
+/// An append-only log of the operations successfully applied to a DCEL.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpLog<T = f64, F = ()> {
+    ops: Vec<Op<T, F>>,
+}
+
+impl<T: Scalar, F: Clone> Default for OpLog<T, F> {
+    fn default() -> OpLog<T, F> {
+        return OpLog::new();
+    }
+}
+
+impl<T: Scalar, F: Clone> OpLog<T, F> {
+    pub fn new() -> OpLog<T, F> {
+        return OpLog { ops: Vec::new() };
+    }
+
+    /// The operations logged so far, oldest first.
+    pub fn ops(&self) -> &[Op<T, F>] {
+        return &self.ops;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.ops.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.ops.is_empty();
+    }
+
+    /// Applies an operation to `dcel` as a transaction, logging it if it
+    /// succeeds.
+    pub fn apply(&mut self, dcel: &mut DCEL<Point2<T>, F>, op: Op<T, F>) -> Result<Applied, OpError> {
+        let applied = dcel.apply(&op)?;
+        self.ops.push(op);
+        return Ok(applied);
+    }
+
+    /// Applies every logged operation to `dcel` in order. Stops at the first
+    /// that fails, returning its index along with the error; the operations
+    /// before it stay applied.
+    pub fn replay_onto(&self, dcel: &mut DCEL<Point2<T>, F>) -> Result<(), (usize, OpError)> {
+        for (i, op) in self.ops.iter().enumerate() {
+            dcel.apply(op).map_err(|err| (i, err))?;
+        }
+        return Ok(());
+    }
+
+    /// Returns the text form of this log.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        writeln!(text, "{}", HEADER).unwrap();
+        for op in &self.ops {
+            match *op {
+                Op::AddPolygon(ref points, _) => {
+                    let coords: Vec<String> = points.iter().map(|p| format!("{} {}", p.x, p.y)).collect();
+                    writeln!(text, "add {}", coords.join(" ")).unwrap();
+                }
+                Op::SplitEdge(e) => writeln!(text, "split-edge e{}", usize::from(e)).unwrap(),
+                Op::FlipEdge(e) => writeln!(text, "flip e{}", usize::from(e)).unwrap(),
+                Op::SplitFace(a, b) => writeln!(text, "split-face e{} e{}", usize::from(a), usize::from(b)).unwrap(),
+                Op::MergeFaces(a, b) => writeln!(text, "merge f{} f{}", usize::from(a), usize::from(b)).unwrap(),
+                Op::RemoveEdge(e) => writeln!(text, "remove e{}", usize::from(e)).unwrap(),
+                Op::CollapseEdge(e) => writeln!(text, "collapse e{}", usize::from(e)).unwrap(),
+            }
+        }
+        return text;
+    }
+}
+
+impl<T: Scalar, F: Clone + Default> OpLog<T, F> {
+    /// Replays the log onto an empty DCEL.
+    pub fn replay(&self) -> Result<DCEL<Point2<T>, F>, (usize, OpError)> {
+        let mut dcel = DCEL::default();
+        self.replay_onto(&mut dcel)?;
+        return Ok(dcel);
+    }
+
+    /// Parses the text form of a log, as written by `to_text`.
+    pub fn from_text(text: &str) -> Result<OpLog<T, F>, FormatError> {
+        let mut ops = Vec::new();
+        let mut seen_header = false;
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let tokens: Vec<&str> = line.split('#').next().unwrap().split_whitespace().collect();
+            if tokens.is_empty() {
+                continue;
+            } else if !seen_header {
+                if tokens.join(" ") != HEADER {
+                    return Err(parse_error(line_no, format!("expected `{}` header", HEADER)));
+                }
+                seen_header = true;
+                continue;
+            }
+
+            let arity = |n: usize| if tokens.len() == n {
+                Ok(())
+            } else {
+                Err(parse_error(line_no, format!("expected {} fields, found {}", n, tokens.len())))
+            };
+            let op = match tokens[0] {
+                "add" => {
+                    if tokens.len() % 2 == 0 {
+                        return Err(parse_error(line_no, "expected x y pairs"));
+                    }
+                    let mut points = Vec::new();
+                    for pair in tokens[1..].chunks(2) {
+                        points.push(Point2::new(parse_token(pair[0], line_no)?, parse_token(pair[1], line_no)?));
+                    }
+                    Op::AddPolygon(points, F::default())
+                }
+                "split-edge" => {
+                    arity(2)?;
+                    Op::SplitEdge(parse_ref(tokens[1], "e", line_no)?)
+                }
+                "flip" => {
+                    arity(2)?;
+                    Op::FlipEdge(parse_ref(tokens[1], "e", line_no)?)
+                }
+                "split-face" => {
+                    arity(3)?;
+                    Op::SplitFace(parse_ref(tokens[1], "e", line_no)?, parse_ref(tokens[2], "e", line_no)?)
+                }
+                "merge" => {
+                    arity(3)?;
+                    Op::MergeFaces(parse_ref(tokens[1], "f", line_no)?, parse_ref(tokens[2], "f", line_no)?)
+                }
+                "remove" => {
+                    arity(2)?;
+                    Op::RemoveEdge(parse_ref(tokens[1], "e", line_no)?)
+                }
+                "collapse" => {
+                    arity(2)?;
+                    Op::CollapseEdge(parse_ref(tokens[1], "e", line_no)?)
+                }
+                other => return Err(parse_error(line_no, format!("unknown operation `{}`", other))),
+            };
+            ops.push(op);
+        }
+        if !seen_header {
+            return Err(parse_error(1, format!("expected `{}` header", HEADER)));
+        }
+        return Ok(OpLog { ops: ops });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ring(corners: &[(f64, f64)]) -> Vec<Point2> {
+        return corners.iter().map(Point2::from).collect();
+    }
+
+    fn edge_between(dcel: &DCEL<Point2, u32>, a: (f64, f64), b: (f64, f64)) -> HalfEdgeId {
+        let (a, b) = (Point2::from(a), Point2::from(b));
+        return dcel.edges.iter()
+            .find(|&(e, edge)| dcel.vertices[edge.origin()].coord() == a && dcel.vertices[dcel.get_destination(e)].coord() == b)
+            .unwrap().0;
+    }
+
+    #[test]
+    fn refuses_diagonals_outside_the_face() {
+        // A notched square, whose top corners can't see each other, and a
+        // square whose hole blocks the way between opposite corners
+        let mut dcel: DCEL<Point2, u32> = DCEL::default();
+        dcel.apply(&Op::AddPolygon(ring(&[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (2.0, 1.0), (0.0, 4.0)]), 1)).unwrap();
+        let (left, right) = (edge_between(&dcel, (0.0, 4.0), (0.0, 0.0)), edge_between(&dcel, (4.0, 4.0), (2.0, 1.0)));
+        assert_eq!(dcel.apply(&Op::SplitFace(left, right)), Err(OpError::BadDiagonal(left, right)));
+        let bottom = edge_between(&dcel, (4.0, 0.0), (4.0, 4.0));
+        let notch = edge_between(&dcel, (2.0, 1.0), (0.0, 4.0));
+        assert!(dcel.apply(&Op::SplitFace(bottom, notch)).is_ok());
+
+        let square = ring(&[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)]);
+        let hole = ring(&[(1.0, 1.0), (1.0, 3.0), (3.0, 3.0), (3.0, 1.0)]);
+        let mut dcel: DCEL<Point2, u32> = DCEL::from_polygon_rings(&[vec![square, hole]], |i| i.map_or(0, |i| i as u32 + 1)).unwrap();
+        let before = dcel.to_text();
+        let (a, b) = (edge_between(&dcel, (0.0, 0.0), (4.0, 0.0)), edge_between(&dcel, (4.0, 4.0), (0.0, 4.0)));
+        assert_eq!(dcel.apply(&Op::SplitFace(a, b)), Err(OpError::BadDiagonal(a, b)));
+        assert_eq!(dcel.to_text(), before);
+        let corner = edge_between(&dcel, (1.0, 1.0), (1.0, 3.0));
+        assert!(dcel.apply(&Op::SplitFace(a, corner)).is_ok());
+        dcel.validate().unwrap();
+    }
+
+    #[test]
+    fn replays_and_rolls_back() {
+        // The three triangles of the design doc, merging the first two
+        let mut dcel: DCEL<Point2, u32> = DCEL::default();
+        let mut log = OpLog::new();
+        let f1 = log.apply(&mut dcel, Op::AddPolygon(ring(&[(0.0, 0.0), (1.0, 1.0), (1.0, 0.0)]), 1)).unwrap();
+        let f2 = log.apply(&mut dcel, Op::AddPolygon(ring(&[(0.0, 0.0), (1.0, 1.0), (0.0, 1.0)]), 2)).unwrap();
+        log.apply(&mut dcel, Op::AddPolygon(ring(&[(0.0, 1.0), (0.0, 2.0), (1.0, 1.0)]), 3)).unwrap();
+        let (f1, f2) = match (f1, f2) {
+            (Applied::Face(f1), Applied::Face(f2)) => (f1, f2),
+            _ => panic!("expected faces"),
+        };
+        assert_eq!(log.apply(&mut dcel, Op::MergeFaces(f1, f2)), Ok(Applied::Face(f1)));
+        assert_eq!(dcel.face_area(f1), 1.0);
+        let top = edge_between(&dcel, (0.0, 1.0), (1.0, 1.0));
+        log.apply(&mut dcel, Op::SplitEdge(top)).unwrap();
+        let diagonal = edge_between(&dcel, (1.0, 0.0), (1.0, 1.0));
+        let middle = edge_between(&dcel, (0.5, 1.0), (0.0, 1.0));
+        assert_eq!(log.apply(&mut dcel, Op::SplitFace(diagonal, diagonal)), Err(OpError::BadDiagonal(diagonal, diagonal)));
+        let next = dcel.edges[diagonal].next().unwrap();
+        assert_eq!(log.apply(&mut dcel, Op::SplitFace(diagonal, next)), Err(OpError::BadDiagonal(diagonal, next)));
+        assert_eq!(log.apply(&mut dcel, Op::SplitFace(next, diagonal)), Err(OpError::BadDiagonal(next, diagonal)));
+        log.apply(&mut dcel, Op::SplitFace(diagonal, middle)).unwrap();
+        dcel.validate().unwrap();
+        assert_eq!(dcel.faces.len(), 4);
+        assert_eq!(log.len(), 6);
+
+        // Failed operations change nothing and aren't logged
+        let before = dcel.to_text();
+        let outer = dcel.outer_face;
+        assert_eq!(log.apply(&mut dcel, Op::MergeFaces(f1, outer)), Err(OpError::NotAdjacent(f1, outer)));
+        assert_eq!(log.apply(&mut dcel, Op::FlipEdge(HalfEdgeId::from(99))),
+                   Err(OpError::MissingElement("half-edge", 99)));
+        assert_eq!(log.apply(&mut dcel, Op::AddPolygon(ring(&[(0.5, 0.5), (3.0, 0.5), (3.0, 3.0)]), 4)),
+                   Err(OpError::Build(BuildError::Occupied)));
+        assert_eq!(dcel.to_text(), before);
+        assert_eq!(log.len(), 6);
+
+        // Replaying rebuilds the same DCEL, ids and payloads included
+        let replayed = log.replay().unwrap();
+        assert_eq!(replayed.to_text(), before);
+        let payloads = |d: &DCEL<Point2, u32>| d.faces.iter().map(|(_, f)| *f.data()).collect::<Vec<_>>();
+        assert_eq!(payloads(&replayed), payloads(&dcel));
+
+        // And so does replaying the text form, bar payloads
+        let text = log.to_text();
+        let parsed: OpLog<f64, u32> = OpLog::from_text(&text).unwrap();
+        assert_eq!(parsed.to_text(), text);
+        assert_eq!(parsed.replay().unwrap().to_text(), before);
+        assert!(OpLog::<f64, u32>::from_text("dcel-log 1\nflip f2\n").is_err());
+
+        // Replay stops at the first operation that doesn't apply
+        let mut broken = parsed.clone();
+        broken.ops.insert(1, Op::CollapseEdge(HalfEdgeId::from(40)));
+        assert_eq!(broken.replay().unwrap_err(), (1, OpError::MissingElement("half-edge", 40)));
+    }
+}
@@ -0,0 +1,372 @@
+//! Overlay of two planar subdivisions: the subdivision formed by the edges
+//! of both, with each face recording the faces of the inputs it lies in.
+//! Boolean operations on the faces of two subdivisions are built on it.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::collections::hash_map::Entry;
+
+use dcel::*;
+use geometry::*;
+use scalar::Scalar;
+
+/// The faces of the two inputs an overlay face lies in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Provenance {
+    /// The face of the first input.
+    pub a: FaceId,
+    /// The face of the second input.
+    pub b: FaceId,
+}
+
+/// An edge of one of the inputs, with the faces on either side of it.
+struct InputEdge<T> {
+    segment: Segment2<T>,
+    left: FaceId,
+    right: FaceId,
+    from_b: bool,
+}
+
+fn input_edges<T: Scalar, F>(dcel: &DCEL<Point2<T>, F>, from_b: bool) -> Vec<InputEdge<T>> {
+    let mut edges = Vec::new();
+    for (e, edge) in dcel.edges.iter() {
+        let twin = edge.twin().unwrap();
+        if e < twin {
+            let start = dcel.vertices[edge.origin()].coord();
+            let end = dcel.vertices[dcel.edges[twin].origin()].coord();
+            edges.push(InputEdge {
+                segment: Segment2::new(start, end),
+                left: edge.face(),
+                right: dcel.edges[twin].face(),
+                from_b: from_b,
+            });
+        }
+    }
+    return edges;
+}
+
+/// Computes the overlay of two planar subdivisions. Edges are split
+/// wherever they cross or touch an edge of the other input, and stretches
+/// where edges of both inputs overlap become a single edge. Intersection
+/// points are computed once and shared by both edges, so they are exact
+/// for exact scalars such as `Rational` or integers on a fine enough grid.
+///
+/// Every face of the result, including the outer face, carries the faces of
+/// `a` and `b` it lies in. Isolated vertices of the inputs are dropped.
+pub fn overlay<T: Scalar, A, B>(a: &DCEL<Point2<T>, A>, b: &DCEL<Point2<T>, B>) -> DCEL<Point2<T>, Provenance> {
+    let mut edges = input_edges(a, false);
+    edges.extend(input_edges(b, true));
+
+    // Each edge is cut at its ends and wherever it meets the other input,
+    // testing only pairs whose boxes overlap
+    let mut cuts: Vec<Vec<Point2<T>>> = edges.iter().map(|e| vec![e.segment.start, e.segment.end]).collect();
+    let boxes: Vec<Bbox2<T>> = edges.iter().map(|e| e.segment.bbox()).collect();
+    let mut order: Vec<usize> = (0..edges.len()).collect();
+    order.sort_by(|&i, &j| boxes[i].min.x.partial_cmp(&boxes[j].min.x).unwrap_or(Ordering::Equal));
+    for (k, &i) in order.iter().enumerate() {
+        for &j in &order[k + 1..] {
+            if boxes[j].min.x > boxes[i].max.x {
+                break;
+            }
+            if edges[i].from_b == edges[j].from_b || !boxes[i].intersects(&boxes[j]) {
+                continue;
+            }
+            match segment_intersection(&edges[i].segment, &edges[j].segment) {
+                Intersection::None => {}
+                Intersection::Proper { point, .. } | Intersection::Touching { point, .. } => {
+                    cuts[i].push(point);
+                    cuts[j].push(point);
+                }
+                Intersection::Overlapping { start, end, .. } => {
+                    cuts[i].extend_from_slice(&[start, end]);
+                    cuts[j].extend_from_slice(&[start, end]);
+                }
+            }
+        }
+    }
+
+    let mut points: Vec<Point2<T>> = cuts.iter().flat_map(|c| c.iter().cloned()).collect();
+    points.sort_by(|p, q| p.partial_cmp(q).unwrap_or(Ordering::Equal));
+    points.dedup();
+    let index = |p: &Point2<T>| points.binary_search_by(|q| q.partial_cmp(p).unwrap_or(Ordering::Equal)).unwrap();
+
+    // Split the edges into pieces, recording the input faces on each side
+    // of every directed piece; pieces from both inputs are merged
+    let mut sides: HashMap<(usize, usize), (Option<FaceId>, Option<FaceId>)> = HashMap::new();
+    let mut pieces: Vec<(usize, usize)> = Vec::new();
+    for (edge, cut) in edges.iter().zip(cuts.iter_mut()) {
+        let start = edge.segment.start;
+        cut.sort_by(|p, q| start.dist_sq(p).partial_cmp(&start.dist_sq(q)).unwrap_or(Ordering::Equal));
+        let mut ids: Vec<usize> = cut.iter().map(&index).collect();
+        ids.dedup();
+        for w in ids.windows(2) {
+            let (u, v) = (w[0], w[1]);
+            if !sides.contains_key(&(u, v)) {
+                pieces.push((u.min(v), u.max(v)));
+            }
+            for &(key, face) in &[((u, v), edge.left), ((v, u), edge.right)] {
+                let side = sides.entry(key).or_insert((None, None));
+                if edge.from_b {
+                    side.1 = Some(face);
+                } else {
+                    side.0 = Some(face);
+                }
+            }
+        }
+    }
+
+    let (mut dcel, half_edges) = DCEL::from_edges(&points, &pieces, None, |_| (), |_| None);
+    let mut side_a = HashMap::new();
+    let mut side_b = HashMap::new();
+    for (key, &(fa, fb)) in &sides {
+        let e = half_edges[key];
+        if let Some(f) = fa {
+            side_a.insert(e, f);
+        }
+        if let Some(f) = fb {
+            side_b.insert(e, f);
+        }
+    }
+    let in_a = spread(&dcel, &side_a, a.outer_face);
+    let in_b = spread(&dcel, &side_b, b.outer_face);
+    let faces: Vec<FaceId> = dcel.faces.iter().map(|(f, _)| f).collect();
+    for f in faces {
+        *dcel.face_data_mut(f) = Some(Provenance { a: in_a[&f], b: in_b[&f] });
+    }
+    return dcel.map_face_data(|p| p.unwrap());
+}
+
+/// A boolean operation on the regions covered by two sets of faces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BooleanOp {
+    /// Everything covered by either input.
+    Union,
+    /// Everything covered by both inputs.
+    Intersection,
+    /// Everything covered by the first input but not the second.
+    Difference,
+    /// Everything covered by exactly one of the inputs.
+    Xor,
+}
+
+impl BooleanOp {
+    /// Determines if a point goes in the result, given whether each input covers it.
+    pub fn keeps(self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            BooleanOp::Union => return in_a || in_b,
+            BooleanOp::Intersection => return in_a && in_b,
+            BooleanOp::Difference => return in_a && !in_b,
+            BooleanOp::Xor => return in_a != in_b,
+        }
+    }
+}
+
+/// Applies a boolean operation to the inner faces of two subdivisions, as
+/// `boolean_op_by` with every inner face selected.
+pub fn boolean_op<T: Scalar, A, B>(a: &DCEL<Point2<T>, A>, b: &DCEL<Point2<T>, B>, op: BooleanOp)
+                                   -> DCEL<Point2<T>, Option<Provenance>> {
+    return boolean_op_by(a, b, op, |_| true, |_| true);
+}
+
+/// Applies a boolean operation to the regions covered by the inner faces of
+/// `a` and `b` picked by `select_a` and `select_b`, such as all faces but
+/// the gaps between polygons.
+///
+/// The result is built from the overlay of the two. Each of its faces in
+/// the result records the selected faces it lies in, with the outer face of
+/// an input standing in where it lies in none. Adjacent faces recording the
+/// same faces are merged by removing the edges between them, as are all the
+/// faces outside the result, which end up in the outer face or, where they
+/// are enclosed by the result, in gap faces. Outside faces carry `None`.
+pub fn boolean_op_by<T, A, B, SA, SB>(a: &DCEL<Point2<T>, A>, b: &DCEL<Point2<T>, B>, op: BooleanOp,
+                                      mut select_a: SA, mut select_b: SB) -> DCEL<Point2<T>, Option<Provenance>>
+    where T: Scalar, SA: FnMut(FaceId) -> bool, SB: FnMut(FaceId) -> bool
+{
+    let (outer_a, outer_b) = (a.outer_face, b.outer_face);
+    let mut result = overlay(a, b).map_face_data(|p| {
+        let in_a = p.a != outer_a && select_a(p.a);
+        let in_b = p.b != outer_b && select_b(p.b);
+        if !op.keeps(in_a, in_b) {
+            return None;
+        }
+        return Some(Provenance {
+            a: if in_a { p.a } else { outer_a },
+            b: if in_b { p.b } else { outer_b },
+        });
+    });
+    let outer_face = result.outer_face;
+    *result.face_data_mut(outer_face) = None;
+
+    let redundant: Vec<HalfEdgeId> = result.edges.iter()
+        .filter(|&(e, edge)| {
+            let twin = edge.twin().unwrap();
+            e < twin && result.face_data(edge.face()) == result.face_data(result.edges[twin].face())
+        })
+        .map(|(e, _)| e)
+        .collect();
+    for e in redundant {
+        result.remove_edge(e);
+    }
+    return result;
+}
+
+/// Works out which input face each face of the overlay lies in, given the
+/// input face to the left of each half-edge that came from that input.
+/// Crossing any other edge stays within the same input face, so the answer
+/// spreads from the faces touching the input's edges to their neighbours.
+/// Faces it can't reach, as when the input has no edges, get `default`.
+fn spread<T: Scalar>(dcel: &DCEL<Point2<T>, Option<Provenance>>, sides: &HashMap<HalfEdgeId, FaceId>,
+                     default: FaceId) -> HashMap<FaceId, FaceId> {
+    let mut found: HashMap<FaceId, FaceId> = HashMap::new();
+    let mut queue = VecDeque::new();
+    for (e, edge) in dcel.edges.iter() {
+        if let Some(&f) = sides.get(&e) {
+            if let Entry::Vacant(entry) = found.entry(edge.face()) {
+                entry.insert(f);
+                queue.push_back(edge.face());
+            }
+        }
+    }
+    while let Some(face) = queue.pop_front() {
+        for start in dcel.face_cycles(face) {
+            for e in dcel.cycle_edges(start) {
+                let other = dcel.edges[dcel.edges[e].twin().unwrap()].face();
+                let input_face = found[&face];
+                if sides.contains_key(&e) {
+                    continue;
+                }
+                if let Entry::Vacant(entry) = found.entry(other) {
+                    entry.insert(input_face);
+                    queue.push_back(other);
+                }
+            }
+        }
+    }
+    for (f, _) in dcel.faces.iter() {
+        found.entry(f).or_insert(default);
+    }
+    return found;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scalar::Rational;
+
+    fn square<T: Scalar>(x: T, y: T, size: T) -> DCEL<Point2<T>, Option<usize>> {
+        let ring = vec![Point2::new(x, y), Point2::new(x + size, y), Point2::new(x + size, y + size), Point2::new(x, y + size)];
+        return DCEL::from_polygon_rings(&[vec![ring]], |i| i).unwrap();
+    }
+
+    /// Returns the area of the overlay lying in face `a` of the first input
+    /// and face `b` of the second.
+    fn area_in<T: Scalar>(dcel: &DCEL<Point2<T>, Provenance>, a: usize, b: usize) -> T {
+        let mut area = T::zero();
+        for (f, face) in dcel.faces.iter() {
+            if *face.data() == (Provenance { a: FaceId::from(a), b: FaceId::from(b) }) && f != dcel.outer_face {
+                area = area + dcel.face_area(f);
+            }
+        }
+        return area;
+    }
+
+    #[test]
+    fn overlays_crossing_squares() {
+        let result = overlay(&square(0.0, 0.0, 2.0), &square(1.0, 1.0, 2.0));
+        result.validate().unwrap();
+        assert_eq!(result.vertices.len(), 10);
+        assert_eq!(result.faces.len(), 4);
+        assert_eq!(area_in(&result, 1, 0), 3.0);
+        assert_eq!(area_in(&result, 1, 1), 1.0);
+        assert_eq!(area_in(&result, 0, 1), 3.0);
+        let outer = result.faces[result.outer_face].data();
+        assert_eq!((outer.a, outer.b), (FaceId::from(0), FaceId::from(0)));
+    }
+
+    #[test]
+    fn overlays_shared_edges_and_islands() {
+        // Side by side squares share an edge, which appears once
+        let result = overlay(&square(0, 0, 2), &square(2, 0, 2));
+        result.validate().unwrap();
+        assert_eq!(result.vertices.len(), 6);
+        assert_eq!(result.edges.len(), 14);
+        assert_eq!(area_in(&result, 1, 0), 4);
+        assert_eq!(area_in(&result, 0, 1), 4);
+
+        // A square inside another touches none of its edges, so it becomes
+        // a hole whose provenance comes from its surroundings
+        let result = overlay(&square(0, 0, 4), &square(1, 1, 1));
+        result.validate().unwrap();
+        assert_eq!(result.faces.len(), 3);
+        assert_eq!(area_in(&result, 1, 0), 15);
+        assert_eq!(area_in(&result, 1, 1), 1);
+    }
+
+    /// Returns the areas of the result's faces, smallest first.
+    fn result_areas(dcel: &DCEL<Point2, Option<Provenance>>) -> Vec<f64> {
+        dcel.validate().unwrap();
+        let mut areas: Vec<f64> = dcel.faces.iter()
+            .filter(|&(_, face)| face.data().is_some())
+            .map(|(f, _)| dcel.face_area(f))
+            .collect();
+        areas.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        return areas;
+    }
+
+    #[test]
+    fn applies_boolean_ops() {
+        let (a, b) = (square(0.0, 0.0, 2.0), square(1.0, 1.0, 2.0));
+        let union = boolean_op(&a, &b, BooleanOp::Union);
+        assert_eq!(result_areas(&union), vec![1.0, 3.0, 3.0]);
+        assert_eq!(union.faces.len(), 4);
+
+        let intersection = boolean_op(&a, &b, BooleanOp::Intersection);
+        assert_eq!(result_areas(&intersection), vec![1.0]);
+        assert_eq!(intersection.vertices.len(), 4);
+        assert_eq!(intersection.faces.len(), 2);
+        let lens = intersection.faces.iter().find(|&(f, _)| f != intersection.outer_face).unwrap().1;
+        assert_eq!(*lens.data(), Some(Provenance { a: FaceId::from(1), b: FaceId::from(1) }));
+
+        let difference = boolean_op(&a, &b, BooleanOp::Difference);
+        assert_eq!(result_areas(&difference), vec![3.0]);
+        assert_eq!(difference.vertices.len(), 6);
+        assert_eq!(result_areas(&boolean_op(&a, &b, BooleanOp::Xor)), vec![3.0, 3.0]);
+
+        // A hole punched by the difference is a gap face
+        let punched = boolean_op(&square(0.0, 0.0, 4.0), &square(1.0, 1.0, 1.0), BooleanOp::Difference);
+        assert_eq!(result_areas(&punched), vec![15.0]);
+        assert_eq!(punched.faces.len(), 3);
+    }
+
+    #[test]
+    fn merges_pieces_of_the_same_faces() {
+        // Two strips across a rectangle, of which only the first is subtracted
+        let a = square(0.0, 0.0, 4.0);
+        let strip = |x: f64| vec![Point2::new(x, -1.0), Point2::new(x + 1.0, -1.0), Point2::new(x + 1.0, 5.0), Point2::new(x, 5.0)];
+        let b: DCEL<Point2, Option<usize>> = DCEL::from_polygon_rings(&[vec![strip(1.0)], vec![strip(2.0)]], |i| i).unwrap();
+        let first = FaceId::from(1);
+        assert_eq!(*b.face_data(first), Some(0));
+
+        let result = boolean_op_by(&a, &b, BooleanOp::Difference, |_| true, |f| f == first);
+        assert_eq!(result_areas(&result), vec![4.0, 8.0]);
+        assert_eq!(result.faces.len(), 3);
+    }
+
+    #[test]
+    fn intersects_exactly() {
+        // The sides of b cross the long side of a at (2/3, 1/3) and (1/3, 2/3)
+        let third = Rational::new(1, 3);
+        let r = |n: i128| Rational::from_integer(n);
+        let a: DCEL<Point2<Rational>, Option<usize>> = DCEL::from_polygon_rings(
+            &[vec![vec![Point2::new(r(0), r(0)), Point2::new(r(1), r(0)), Point2::new(r(0), r(1))]]], |i| i).unwrap();
+        let b: DCEL<Point2<Rational>, Option<usize>> = DCEL::from_polygon_rings(
+            &[vec![vec![Point2::new(r(0), r(0)), Point2::new(r(2), r(1)), Point2::new(r(1), r(2))]]], |i| i).unwrap();
+        let result = overlay(&a, &b);
+        result.validate().unwrap();
+        let points: Vec<_> = result.vertices.iter().map(|(_, v)| v.coord()).collect();
+        assert!(points.contains(&Point2::new(third + third, third)));
+        assert!(points.contains(&Point2::new(third, third + third)));
+        assert_eq!(area_in(&result, 1, 1) + area_in(&result, 1, 0), r(1) / r(2));
+        assert_eq!(area_in(&result, 1, 1), r(1) / r(6));
+    }
+}
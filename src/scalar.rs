@@ -0,0 +1,425 @@
+//! Coordinate types that geometry and DCELs can be built over.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::{Debug, Display};
+use std::ops::{Add, Sub, Mul, Div, Neg};
+use std::str::FromStr;
+
+/// Trait for the numeric type used for coordinates. Implemented for `f32` and
+/// `f64`, for `i64` integer grids (where division truncates), and for the
+/// exact `Rational` type below.
+pub trait Scalar:
+    Copy
+    + Debug
+    + Display
+    + FromStr
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    fn zero() -> Self;
+    fn one() -> Self;
+
+    /// Converts from an `f64`, rounding if the value isn't representable.
+    fn from_f64(value: f64) -> Self;
+
+    /// Converts to an `f64`, rounding if the value isn't representable.
+    fn to_f64(self) -> f64;
+
+    fn abs(self) -> Self {
+        if self < Self::zero() {
+            return -self;
+        }
+        return self;
+    }
+
+    /// Returns the square root, computed through `f64` unless overridden.
+    fn sqrt(self) -> Self {
+        return Self::from_f64(self.to_f64().sqrt());
+    }
+
+    /// Returns half of this value.
+    fn half(self) -> Self {
+        return self / (Self::one() + Self::one());
+    }
+}
+
+/// Returns the smaller of two values, preferring `a` when they are unordered.
+pub fn min<T: PartialOrd>(a: T, b: T) -> T {
+    if b < a {
+        return b;
+    }
+    return a;
+}
+
+/// Returns the larger of two values, preferring `a` when they are unordered.
+pub fn max<T: PartialOrd>(a: T, b: T) -> T {
+    if b > a {
+        return b;
+    }
+    return a;
+}
+
+macro_rules! float_scalar {
+    ($T:ident) => {
+        impl Scalar for $T {
+            fn zero() -> $T {
+                return 0.0;
+            }
+
+            fn one() -> $T {
+                return 1.0;
+            }
+
+            fn from_f64(value: f64) -> $T {
+                return value as $T;
+            }
+
+            fn to_f64(self) -> f64 {
+                return self as f64;
+            }
+
+            fn abs(self) -> $T {
+                return $T::abs(self);
+            }
+
+            fn sqrt(self) -> $T {
+                return $T::sqrt(self);
+            }
+        }
+    }
+}
+
+float_scalar!(f32);
+float_scalar!(f64);
+
+impl Scalar for i64 {
+    fn zero() -> i64 {
+        return 0;
+    }
+
+    fn one() -> i64 {
+        return 1;
+    }
+
+    fn from_f64(value: f64) -> i64 {
+        return value.round() as i64;
+    }
+
+    fn to_f64(self) -> f64 {
+        return self as f64;
+    }
+
+    fn abs(self) -> i64 {
+        return i64::abs(self);
+    }
+}
+
+fn gcd(mut a: i128, mut b: i128) -> i128 {
+    a = a.abs();
+    b = b.abs();
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    return a;
+}
+
+/// Multiplies, panicking on overflow even where release builds would wrap.
+fn checked_mul(a: i128, b: i128) -> i128 {
+    return a.checked_mul(b).expect("Rational arithmetic overflowed!");
+}
+
+/// Adds, panicking on overflow even where release builds would wrap.
+fn checked_add(a: i128, b: i128) -> i128 {
+    return a.checked_add(b).expect("Rational arithmetic overflowed!");
+}
+
+/// An exact fraction of two `i128`s, always kept in lowest terms with a
+/// positive denominator. Arithmetic panics on overflow rather than rounding.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rational {
+    num: i128,
+    den: i128,
+}
+
+impl Rational {
+    /// Returns the fraction `num / den`. Panics if `den` is zero.
+    pub fn new(num: i128, den: i128) -> Rational {
+        if den == 0 {
+            panic!("Rational with a zero denominator!");
+        }
+        let g = gcd(num, den);
+        let sign = if den < 0 { -1 } else { 1 };
+        return Rational {
+            num: checked_mul(sign, num / g.max(1)),
+            den: checked_mul(sign, den / g.max(1)),
+        };
+    }
+
+    pub fn from_integer(value: i128) -> Rational {
+        return Rational { num: value, den: 1 };
+    }
+
+    pub fn numerator(&self) -> i128 {
+        return self.num;
+    }
+
+    pub fn denominator(&self) -> i128 {
+        return self.den;
+    }
+
+    pub fn is_integer(&self) -> bool {
+        return self.den == 1;
+    }
+
+    /// Returns the largest integer not greater than this value.
+    pub fn floor(&self) -> i128 {
+        return self.num.div_euclid(self.den);
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Rational) -> Ordering {
+        return checked_mul(self.num, other.den).cmp(&checked_mul(other.num, self.den));
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl Add for Rational {
+    type Output = Rational;
+
+    fn add(self, other: Rational) -> Rational {
+        let g = gcd(self.den, other.den);
+        let num = checked_add(checked_mul(self.num, other.den / g), checked_mul(other.num, self.den / g));
+        return Rational::new(num, checked_mul(self.den / g, other.den));
+    }
+}
+
+impl Sub for Rational {
+    type Output = Rational;
+
+    fn sub(self, other: Rational) -> Rational {
+        return self + (-other);
+    }
+}
+
+impl Mul for Rational {
+    type Output = Rational;
+
+    fn mul(self, other: Rational) -> Rational {
+        // Cross-reduce first to keep the intermediate products small.
+        let g1 = gcd(self.num, other.den).max(1);
+        let g2 = gcd(other.num, self.den).max(1);
+        return Rational::new(checked_mul(self.num / g1, other.num / g2), checked_mul(self.den / g2, other.den / g1));
+    }
+}
+
+impl Div for Rational {
+    type Output = Rational;
+
+    fn div(self, other: Rational) -> Rational {
+        if other.num == 0 {
+            panic!("Rational division by zero!");
+        }
+        return self * Rational::new(other.den, other.num);
+    }
+}
+
+impl Neg for Rational {
+    type Output = Rational;
+
+    fn neg(self) -> Rational {
+        return Rational { num: checked_mul(-1, self.num), den: self.den };
+    }
+}
+
+impl From<i32> for Rational {
+    fn from(value: i32) -> Rational {
+        return Rational::from_integer(value as i128);
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(value: i64) -> Rational {
+        return Rational::from_integer(value as i128);
+    }
+}
+
+impl From<u32> for Rational {
+    fn from(value: u32) -> Rational {
+        return Rational::from_integer(value as i128);
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.den == 1 {
+            return write!(f, "{}", self.num);
+        }
+        return write!(f, "{}/{}", self.num, self.den);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRationalError;
+
+impl Display for ParseRationalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "invalid rational literal");
+    }
+}
+
+/// Parses integers (`-3`), fractions (`7/4`) and decimals (`1.25`) exactly.
+impl FromStr for Rational {
+    type Err = ParseRationalError;
+
+    fn from_str(s: &str) -> Result<Rational, ParseRationalError> {
+        let s = s.trim();
+        if let Some(slash) = s.find('/') {
+            let num = s[..slash].parse::<i128>().map_err(|_| ParseRationalError)?;
+            let den = s[slash + 1..].parse::<i128>().map_err(|_| ParseRationalError)?;
+            if den == 0 {
+                return Err(ParseRationalError);
+            }
+            return Ok(Rational::new(num, den));
+        }
+
+        let (int_part, frac_part) = match s.find('.') {
+            Some(dot) => (&s[..dot], &s[dot + 1..]),
+            None => (s, ""),
+        };
+        if !frac_part.chars().all(|c| c.is_ascii_digit()) || frac_part.len() > 30 {
+            return Err(ParseRationalError);
+        }
+        let negative = int_part.starts_with('-');
+        let int_digits = int_part.strip_prefix(['-', '+']).unwrap_or(int_part);
+        if (int_digits.is_empty() && frac_part.is_empty()) || !int_digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(ParseRationalError);
+        }
+        let int_value = if int_digits.is_empty() {
+            0
+        } else {
+            int_digits.parse::<i128>().map_err(|_| ParseRationalError)?
+        };
+        let den = 10i128.pow(frac_part.len() as u32);
+        let frac_value = if frac_part.is_empty() { 0 } else { frac_part.parse::<i128>().unwrap() };
+        let magnitude = int_value.checked_mul(den).and_then(|v| v.checked_add(frac_value))
+            .ok_or(ParseRationalError)?;
+        return Ok(Rational::new(if negative { -magnitude } else { magnitude }, den));
+    }
+}
+
+impl Scalar for Rational {
+    fn zero() -> Rational {
+        return Rational::from_integer(0);
+    }
+
+    fn one() -> Rational {
+        return Rational::from_integer(1);
+    }
+
+    /// Converts exactly, except for precisions beyond `i128`, which are
+    /// rounded. Panics on magnitudes beyond `i128`, and returns zero for
+    /// infinities and NaN.
+    fn from_f64(value: f64) -> Rational {
+        if value == 0.0 || !value.is_finite() {
+            return Rational::zero();
+        }
+        let bits = value.to_bits();
+        let negative = bits >> 63 == 1;
+        let exponent = ((bits >> 52) & 0x7ff) as i32;
+        let mut mantissa = (bits & 0xf_ffff_ffff_ffff) as i128;
+        let mut exponent = if exponent == 0 {
+            -1074
+        } else {
+            mantissa |= 1 << 52;
+            exponent - 1075
+        };
+        while exponent < -126 && mantissa != 0 {
+            mantissa >>= 1;
+            exponent += 1;
+        }
+        let signed = if negative { -mantissa } else { mantissa };
+        if exponent >= 0 {
+            let shift = exponent as u32;
+            return match signed.checked_shl(shift).filter(|shifted| shifted >> shift == signed) {
+                Some(integer) => Rational::from_integer(integer),
+                None => panic!("{} is too large for a Rational!", value),
+            };
+        }
+        return Rational::new(signed, 1i128 << -exponent);
+    }
+
+    fn to_f64(self) -> f64 {
+        return self.num as f64 / self.den as f64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rational_arithmetic() {
+        let a = Rational::new(1, 3);
+        let b = Rational::new(1, 6);
+        assert_eq!(a + b, Rational::new(1, 2));
+        assert_eq!(a - b, Rational::new(1, 6));
+        assert_eq!(a * b, Rational::new(1, 18));
+        assert_eq!(a / b, Rational::from_integer(2));
+        assert_eq!(Rational::new(2, -4), Rational::new(-1, 2));
+        assert!(Rational::new(-1, 2) < Rational::new(1, 3));
+    }
+
+    #[test]
+    fn rational_parsing() {
+        assert_eq!("7/4".parse::<Rational>(), Ok(Rational::new(7, 4)));
+        assert_eq!("-1.25".parse::<Rational>(), Ok(Rational::new(-5, 4)));
+        assert_eq!("3".parse::<Rational>(), Ok(Rational::from_integer(3)));
+        assert!("1/0".parse::<Rational>().is_err());
+        assert_eq!("+.5".parse::<Rational>(), Ok(Rational::new(1, 2)));
+        assert!("--3".parse::<Rational>().is_err());
+        assert!("+-3".parse::<Rational>().is_err());
+        assert!("99999999999999999999.999999999999999999999".parse::<Rational>().is_err());
+        assert_eq!(Rational::new(-5, 4).to_string(), "-5/4");
+    }
+
+    #[test]
+    fn rational_from_f64() {
+        assert_eq!(Rational::from_f64(0.375), Rational::new(3, 8));
+        assert_eq!(Rational::from_f64(-6.0), Rational::from_integer(-6));
+        assert_eq!(Rational::from_f64(0.1).to_f64(), 0.1);
+        assert_eq!(Rational::from_f64(2e37).to_f64(), 2e37);
+        assert_eq!(Rational::from_f64(-2f64.powi(126)), Rational::from_integer(-(1 << 126)));
+    }
+
+    #[test]
+    #[should_panic(expected = "too large for a Rational")]
+    fn rational_from_huge_f64() {
+        Rational::from_f64(2e38);
+    }
+
+    #[test]
+    #[should_panic(expected = "Rational arithmetic overflowed")]
+    fn rational_overflow() {
+        let big = Rational::new(1 << 100, 3);
+        let _ = big * big;
+    }
+
+    #[test]
+    #[should_panic(expected = "Rational arithmetic overflowed")]
+    fn rational_comparison_overflow() {
+        let _ = Rational::new(1 << 125, 3) < Rational::new(1 << 125, 7);
+    }
+}
@@ -0,0 +1,225 @@
+//! Unlimited undo and redo of DCEL edits. An `EditSession` owns a DCEL and
+//! records, for each edit, the arena slots it changed as they were before
+//! and after, so stepping back and forth restores every id exactly,
+//! including the order deleted ids will be reused in.
+
+use arena::ArenaDiff;
+use dcel::*;
+use geometry::*;
+use observe::{Event, ObserverId};
+use oplog::{Applied, Op, OpError};
+use scalar::Scalar;
+
+/// The changes one edit made to each arena.
+#[derive(Debug, Clone)]
+struct Edit<P, F> {
+    vertices: ArenaDiff<Vertex<P>, VertexId>,
+    edges: ArenaDiff<HalfEdge, HalfEdgeId>,
+    faces: ArenaDiff<Face<F>, FaceId>,
+    outer_face: (FaceId, FaceId),
+}
+
+/// A DCEL together with the history of edits made to it.
+#[derive(Debug, Clone)]
+pub struct EditSession<P = Point2, F = ()> {
+    dcel: DCEL<P, F>,
+    undo: Vec<Edit<P, F>>,
+    redo: Vec<Edit<P, F>>,
+    /// Named points in the history, as the number of edits to undo to
+    /// reach the start.
+    checkpoints: Vec<(String, usize)>,
+}
+
+impl<P: Position, F: Clone + PartialEq> EditSession<P, F> {
+    /// Starts a session with an empty history.
+    pub fn new(dcel: DCEL<P, F>) -> EditSession<P, F> {
+        return EditSession {
+            dcel: dcel,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            checkpoints: Vec::new(),
+        };
+    }
+
+    /// The DCEL in its current state.
+    pub fn dcel(&self) -> &DCEL<P, F> {
+        return &self.dcel;
+    }
+
+    /// Registers an observer on the DCEL; see `DCEL::add_observer`. Undo
+    /// and redo are reported as `Event::Restored`.
+    pub fn add_observer<O: FnMut(&Event) + Send + 'static>(&mut self, observer: O) -> ObserverId {
+        return self.dcel.add_observer(observer);
+    }
+
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        return self.dcel.remove_observer(id);
+    }
+
+    /// Ends the session, returning the DCEL in its current state.
+    pub fn into_dcel(self) -> DCEL<P, F> {
+        return self.dcel;
+    }
+
+    /// Makes an arbitrary change to the DCEL as a single undoable edit,
+    /// returning whatever `change` does. Edits that change nothing aren't
+    /// recorded. Any edit discards the redo history.
+    pub fn edit<R, C: FnOnce(&mut DCEL<P, F>) -> R>(&mut self, change: C) -> R {
+        let before = self.dcel.clone();
+        let result = change(&mut self.dcel);
+        let edit = Edit {
+            vertices: before.vertices.diff(&self.dcel.vertices),
+            edges: before.edges.diff(&self.dcel.edges),
+            faces: before.faces.diff(&self.dcel.faces),
+            outer_face: (before.outer_face, self.dcel.outer_face),
+        };
+        if edit.vertices.is_empty() && edit.edges.is_empty() && edit.faces.is_empty()
+            && edit.outer_face.0 == edit.outer_face.1 {
+            return result;
+        }
+
+        self.redo.clear();
+        let depth = self.undo.len();
+        self.checkpoints.retain(|&(_, at)| at <= depth);
+        self.undo.push(edit);
+        return result;
+    }
+
+    /// Undoes the most recent edit, if there is one to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.undo.pop() {
+            Some(edit) => {
+                self.step(&edit, false);
+                self.redo.push(edit);
+                return true;
+            }
+            None => return false,
+        }
+    }
+
+    /// Redoes the most recently undone edit, if there is one to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.redo.pop() {
+            Some(edit) => {
+                self.step(&edit, true);
+                self.undo.push(edit);
+                return true;
+            }
+            None => return false,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        return !self.undo.is_empty();
+    }
+
+    pub fn can_redo(&self) -> bool {
+        return !self.redo.is_empty();
+    }
+
+    /// Names the current point in the history, replacing any earlier
+    /// checkpoint of the same name.
+    pub fn checkpoint(&mut self, name: &str) {
+        self.checkpoints.retain(|(n, _)| n != name);
+        self.checkpoints.push((name.to_string(), self.undo.len()));
+    }
+
+    /// Undoes or redoes edits until the DCEL is back at the checkpoint
+    /// called `name`. Returns false, changing nothing, if there is no such
+    /// checkpoint; checkpoints ahead of the current point are dropped once
+    /// a new edit discards the redo history.
+    pub fn restore(&mut self, name: &str) -> bool {
+        let at = match self.checkpoints.iter().find(|&(n, _)| n == name) {
+            Some(&(_, at)) => at,
+            None => return false,
+        };
+        while self.undo.len() > at {
+            self.undo();
+        }
+        while self.undo.len() < at {
+            self.redo();
+        }
+        return true;
+    }
+
+    fn step(&mut self, edit: &Edit<P, F>, forwards: bool) {
+        self.dcel.vertices.step(&edit.vertices, forwards);
+        self.dcel.edges.step(&edit.edges, forwards);
+        self.dcel.faces.step(&edit.faces, forwards);
+        self.dcel.outer_face = if forwards { edit.outer_face.1 } else { edit.outer_face.0 };
+        self.dcel.notify(Event::Restored);
+    }
+}
+
+impl<T: Scalar, F: Clone + PartialEq> EditSession<Point2<T>, F> {
+    /// Applies an operation as a single undoable edit; see `DCEL::apply`.
+    /// Failed operations change nothing and aren't recorded.
+    pub fn apply(&mut self, op: &Op<T, F>) -> Result<Applied, OpError> {
+        return self.edit(|dcel| dcel.apply(op));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: f64, y: f64) -> Vec<Point2> {
+        return vec![Point2::new(x, y), Point2::new(x + 1.0, y), Point2::new(x + 1.0, y + 1.0), Point2::new(x, y + 1.0)];
+    }
+
+    #[test]
+    fn undoes_and_redoes_exactly() {
+        let mut session: EditSession<Point2, u32> = EditSession::new(DCEL::default());
+        let mut states = vec![session.dcel().to_text()];
+        for i in 0..3 {
+            session.apply(&Op::AddPolygon(square(i as f64, 0.0), i)).unwrap();
+            states.push(session.dcel().to_text());
+        }
+        session.checkpoint("strip");
+
+        // Deletions fill the reuse stacks, so undoing them must restore ids
+        // and the order they'll be reused in
+        let e = session.dcel().edges.iter()
+            .find(|&(_, edge)| session.dcel().vertices[edge.origin()].coord() == Point2::new(1.0, 0.0)
+                  && edge.face() != session.dcel().outer_face)
+            .unwrap().0;
+        session.apply(&Op::RemoveEdge(e)).unwrap();
+        states.push(session.dcel().to_text());
+        assert!(session.apply(&Op::FlipEdge(e)).is_err());
+        assert_eq!(session.dcel().edges.reuse_stack().len(), 2);
+        let face = session.edit(|dcel| dcel.add_polygon(&square(5.0, 5.0), 9).unwrap());
+        states.push(session.dcel().to_text());
+        assert!(session.dcel().edges.reuse_stack().is_empty());
+        assert!(session.dcel().faces.reuse_stack().is_empty());
+        assert_eq!(*session.dcel().face_data(face), 9);
+        session.dcel().validate().unwrap();
+
+        for state in states.iter().rev().skip(1) {
+            assert!(session.undo());
+            assert_eq!(&session.dcel().to_text(), state);
+        }
+        assert!(!session.undo());
+        for state in states.iter().skip(1) {
+            assert!(session.redo());
+            assert_eq!(&session.dcel().to_text(), state);
+        }
+        assert!(!session.redo());
+
+        assert!(session.restore("strip"));
+        assert_eq!(session.dcel().to_text(), states[3]);
+        assert_eq!(session.dcel().faces.iter().map(|(_, f)| *f.data()).collect::<Vec<_>>(), vec![0, 0, 1, 2]);
+
+        // A new edit drops the redo history and the checkpoints in it
+        session.undo();
+        session.checkpoint("two");
+        session.redo();
+        session.checkpoint("three");
+        session.undo();
+        session.edit(|dcel| *dcel.face_data_mut(FaceId::from(1)) = 7);
+        assert!(!session.can_redo());
+        assert!(!session.restore("three"));
+        assert!(session.restore("two"));
+        assert_eq!(session.dcel().to_text(), states[2]);
+        assert_eq!(*session.dcel().face_data(FaceId::from(1)), 0);
+    }
+}
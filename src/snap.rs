@@ -0,0 +1,268 @@
+//! Snap rounding of a DCEL onto a grid, after Hobby and Guibas–Marimont.
+//! Rounding each vertex on its own can push it across a nearby edge; snap
+//! rounding also bends every edge through the centre of each "hot" pixel
+//! it passes, which keeps the result a planar subdivision.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+use dcel::*;
+use geometry::*;
+use observe::Event;
+use scalar::Scalar;
+
+/// Returns the indices of the grid pixel containing `p`. Pixels are centred
+/// on multiples of `grid_size`, and the rounding is done through `f64`.
+fn pixel_of<T: Scalar>(p: &Point2<T>, grid_size: T) -> (i64, i64) {
+    let g = grid_size.to_f64();
+    return ((p.x.to_f64() / g).round() as i64, (p.y.to_f64() / g).round() as i64);
+}
+
+/// Returns the centre of the pixel with the given indices.
+fn pixel_centre<T: Scalar>(pixel: (i64, i64), grid_size: T) -> Point2<T> {
+    return Point2::new(T::from_f64(pixel.0 as f64) * grid_size, T::from_f64(pixel.1 as f64) * grid_size);
+}
+
+/// Returns the pixels whose squares the segment might meet: those over each
+/// column of pixels it crosses, widened by one all round. The ranges are
+/// worked out in `f64`; `passes_through` has the final say.
+fn nearby_pixels<T: Scalar>(segment: &Segment2<T>, grid_size: T) -> Vec<(i64, i64)> {
+    let g = grid_size.to_f64();
+    let (ax, ay) = (segment.start.x.to_f64() / g, segment.start.y.to_f64() / g);
+    let (bx, by) = (segment.end.x.to_f64() / g, segment.end.y.to_f64() / g);
+    let (min_x, max_x) = (ax.min(bx), ax.max(bx));
+    let y_at = |x: f64| if ax == bx { ay } else { ay + (by - ay) * (x - ax) / (bx - ax) };
+    let mut pixels = Vec::new();
+    for px in min_x.round() as i64 - 1..=max_x.round() as i64 + 1 {
+        let x0 = (px as f64 - 0.5).max(min_x).min(max_x);
+        let x1 = (px as f64 + 0.5).max(min_x).min(max_x);
+        let (y0, y1) = if ax == bx { (ay, by) } else { (y_at(x0), y_at(x1)) };
+        for py in y0.min(y1).round() as i64 - 1..=y0.max(y1).round() as i64 + 1 {
+            pixels.push((px, py));
+        }
+    }
+    return pixels;
+}
+
+/// Returns the hot pixels whose squares the segment might meet. Walking the
+/// pixels along a segment takes time in proportion to its length, so where
+/// fewer hot pixels lie in the columns of its bounding box, those are
+/// looked up in `by_column`, the hot pixels sorted by column, instead.
+fn hot_pixels_near<T: Scalar>(segment: &Segment2<T>, grid_size: T, hot: &HashMap<(i64, i64), usize>,
+                              by_column: &[((i64, i64), usize)]) -> Vec<usize> {
+    let bbox = segment.bbox();
+    let (lo, hi) = (pixel_of(&bbox.min, grid_size), pixel_of(&bbox.max, grid_size));
+    let (first_x, last_x) = (lo.0.saturating_sub(1), hi.0.saturating_add(1));
+    let (first_y, last_y) = (lo.1.saturating_sub(1), hi.1.saturating_add(1));
+    let start = by_column.partition_point(|&(pixel, _)| pixel.0 < first_x);
+    let end = by_column.partition_point(|&(pixel, _)| pixel.0 <= last_x);
+
+    // The walk visits three pixels or so per column, plus one per row
+    let walk = 3 * (last_x as i128 - first_x as i128 + 1) + (last_y as i128 - first_y as i128 + 1);
+    if walk > (end - start) as i128 {
+        return by_column[start..end].iter()
+            .filter(|&&(pixel, _)| first_y <= pixel.1 && pixel.1 <= last_y)
+            .map(|&(_, i)| i)
+            .collect();
+    }
+    return nearby_pixels(segment, grid_size).into_iter().filter_map(|pixel| hot.get(&pixel).cloned()).collect();
+}
+
+/// Determines whether the segment meets the closed square of side
+/// `grid_size` around `centre`. Coordinates are doubled rather than the side
+/// halved, so the test stays exact for integers.
+fn passes_through<T: Scalar>(segment: &Segment2<T>, centre: Point2<T>, grid_size: T) -> bool {
+    let double = |p: Point2<T>| Point2::new(p.x + p.x, p.y + p.y);
+    let segment = Segment2::new(double(segment.start), double(segment.end));
+    let centre = double(centre);
+    let square = Bbox2::new(Point2::new(centre.x - grid_size, centre.y - grid_size),
+                            Point2::new(centre.x + grid_size, centre.y + grid_size));
+    if !square.intersects(&segment.bbox()) {
+        return false;
+    } else if square.contains(&segment.start) || square.contains(&segment.end) {
+        return true;
+    }
+    let corners = [square.min, Point2::new(square.max.x, square.min.y), square.max, Point2::new(square.min.x, square.max.y)];
+    return (0..4).any(|i| {
+        let side = Segment2::new(corners[i], corners[(i + 1) % 4]);
+        return segment_intersection(&segment, &side) != Intersection::None;
+    });
+}
+
+impl<T: Scalar, F: Clone> DCEL<Point2<T>, F> {
+    /// Snap rounds the DCEL onto a grid of the given spacing: every vertex
+    /// moves to the centre of its pixel, and every edge is rerouted through
+    /// the centre of each such hot pixel it passes through. Edges snapped
+    /// onto each other are merged, and faces that collapse are dropped; the
+    /// rest keep their payloads. The DCEL is rebuilt, so every id changes,
+    /// and observers are sent `Event::Rebuilt`.
+    ///
+    /// Edges are expected to meet only at vertices, as in a valid DCEL.
+    /// Panics if `grid_size` isn't positive.
+    pub fn snap_round(&mut self, grid_size: T) {
+        assert!(grid_size > T::zero(), "grid size must be positive");
+
+        // Every vertex makes its pixel hot
+        let mut points: Vec<Point2<T>> = Vec::new();
+        let mut hot: HashMap<(i64, i64), usize> = HashMap::new();
+        for (_, vertex) in self.vertices.iter() {
+            let pixel = pixel_of(&vertex.coord(), grid_size);
+            hot.entry(pixel).or_insert_with(|| {
+                points.push(pixel_centre(pixel, grid_size));
+                return points.len() - 1;
+            });
+        }
+
+        let mut by_column: Vec<((i64, i64), usize)> = hot.iter().map(|(&pixel, &i)| (pixel, i)).collect();
+        by_column.sort();
+
+        // Reroute each edge through the hot pixels along it, in order, and
+        // note the faces on either side of each piece
+        let mut pieces: Vec<(usize, usize)> = Vec::new();
+        let mut seen: HashSet<(usize, usize)> = HashSet::new();
+        let mut sides: HashMap<(usize, usize), (Vec<FaceId>, Vec<FaceId>)> = HashMap::new();
+        for (e, edge) in self.edges.iter() {
+            let twin = edge.twin().unwrap();
+            if twin < e {
+                continue;
+            }
+            let segment = Segment2::new(self.vertices[edge.origin()].coord(), self.vertices[self.get_destination(e)].coord());
+            let first = hot[&pixel_of(&segment.start, grid_size)];
+            let last = hot[&pixel_of(&segment.end, grid_size)];
+            let direction = segment.direction();
+            let mut passed: Vec<(T, usize)> = hot_pixels_near(&segment, grid_size, &hot, &by_column).into_iter()
+                .filter(|&i| i != first && i != last && passes_through(&segment, points[i], grid_size))
+                .map(|i| ((points[i] - segment.start).dot(&direction), i))
+                .collect();
+            passed.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+            let mut path = vec![first];
+            path.extend(passed.into_iter().map(|(_, i)| i));
+            path.push(last);
+            let (left, right) = (edge.face(), self.edges[twin].face());
+            for pair in path.windows(2) {
+                let (a, b) = (pair[0], pair[1]);
+                if a == b {
+                    continue;
+                }
+                if !seen.contains(&(a, b)) && !seen.contains(&(b, a)) {
+                    seen.insert((a, b));
+                    pieces.push((a, b));
+                }
+                let forward = sides.entry((a, b)).or_insert_with(|| (Vec::new(), Vec::new()));
+                forward.0.push(left);
+                forward.1.push(right);
+                let backward = sides.entry((b, a)).or_insert_with(|| (Vec::new(), Vec::new()));
+                backward.0.push(right);
+                backward.1.push(left);
+            }
+        }
+
+        // Where edges were snapped together, a piece's face is one that only
+        // ever lay to its left; the others were squeezed out between them
+        let label = |key: (usize, usize)| -> FaceId {
+            let (ref lefts, ref rights) = sides[&key];
+            return lefts.iter().filter(|f| !rights.contains(f)).min()
+                .or_else(|| lefts.iter().min())
+                .cloned().unwrap();
+        };
+        let outer = self.faces[self.outer_face].data().clone();
+        let (mut snapped, _) = {
+            let faces = &self.faces;
+            DCEL::from_edges(&points, &pieces, outer, label, |f| faces[f].data().clone())
+        };
+        snapped.observers = mem::take(&mut self.observers);
+        *self = snapped;
+        self.notify(Event::Rebuilt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn apex_faces(dcel: &DCEL<Point2, Option<usize>>, apex: Point2) -> HashSet<FaceId> {
+        return dcel.edges.iter()
+            .filter(|&(_, edge)| dcel.vertices[edge.origin()].coord() == apex)
+            .map(|(_, edge)| edge.face())
+            .collect();
+    }
+
+    #[test]
+    fn snap_rounds_without_crossings() {
+        // The triangle's apex sits just below the rectangle. Rounding alone
+        // would put it on the rectangle's lower edge, and the sliver would
+        // vanish into a single grid point.
+        let build = || {
+            let rectangle = vec![vec![Point2::new(0.0, 0.4), Point2::new(10.0, 0.4), Point2::new(10.0, 5.0), Point2::new(0.0, 5.0)]];
+            let triangle = vec![vec![Point2::new(3.0, -3.0), Point2::new(7.0, -3.0), Point2::new(5.0, 0.3)]];
+            let sliver = vec![vec![Point2::new(20.0, 0.0), Point2::new(20.1, 0.0), Point2::new(20.0, 0.1)]];
+            return DCEL::from_polygon_rings(&[rectangle, triangle, sliver], |i| i).unwrap();
+        };
+        let mut dcel: DCEL<Point2, Option<usize>> = build();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        dcel.add_observer(move |event| sink.lock().unwrap().push(*event));
+
+        dcel.snap_round(1.0);
+        dcel.validate().unwrap();
+        assert_eq!(*events.lock().unwrap(), vec![Event::Rebuilt]);
+        for (_, vertex) in dcel.vertices.iter() {
+            let p = vertex.coord();
+            assert_eq!((p.x.round(), p.y.round()), (p.x, p.y));
+        }
+
+        // The rectangle's lower edge now bends through the apex
+        assert_eq!(dcel.vertices.len(), 8);
+        assert_eq!(dcel.faces.len(), 3);
+        let rectangle = dcel.locate(&Point2::new(1.0, 1.0));
+        let triangle = dcel.locate(&Point2::new(5.0, -1.0));
+        assert_eq!(*dcel.face_data(rectangle), Some(0));
+        assert_eq!(*dcel.face_data(triangle), Some(1));
+        assert_eq!(dcel.face_area(rectangle), 50.0);
+        assert_eq!(dcel.face_area(triangle), 6.0);
+        let faces = apex_faces(&dcel, Point2::new(5.0, 0.0));
+        assert!(faces.contains(&rectangle) && faces.contains(&triangle));
+
+        // A finer grid keeps them apart
+        let mut fine = build();
+        fine.snap_round(0.25);
+        fine.validate().unwrap();
+        assert_eq!(fine.vertices.len(), 8);
+        let rectangle = fine.locate(&Point2::new(1.0, 1.0));
+        assert_eq!(fine.face_area(rectangle), 45.0);
+        assert!(!apex_faces(&fine, Point2::new(5.0, 0.25)).contains(&rectangle));
+    }
+
+    #[test]
+    fn snaps_long_edges_on_fine_grids() {
+        // Each side spans millions of pixels but passes only three hot ones
+        let rings = vec![vec![vec![Point2::new(0.0, 0.0), Point2::new(1.0, 0.0), Point2::new(0.0, 1.0)]]];
+        let mut dcel: DCEL<Point2, Option<usize>> = DCEL::from_polygon_rings(&rings, |i| i).unwrap();
+        dcel.snap_round(1e-7);
+        dcel.validate().unwrap();
+        assert_eq!(dcel.vertices.len(), 3);
+        assert_eq!(dcel.faces.len(), 2);
+    }
+
+    #[test]
+    fn snap_rounds_integers() {
+        // On a grid of 3 the side from (2, -1) to (1, 2) passes (1.4, 0.8),
+        // inside the pixel around the other triangle's corner at the origin
+        let rings = vec![
+            vec![vec![Point2::new(1, 2), Point2::new(2, -1), Point2::new(8, 2)]],
+            vec![vec![Point2::new(0, 0), Point2::new(-6, 3), Point2::new(-6, -6)]],
+        ];
+        let mut dcel: DCEL<Point2<i64>, Option<usize>> = DCEL::from_polygon_rings(&rings, |i| i).unwrap();
+        dcel.snap_round(3);
+        dcel.validate().unwrap();
+        assert_eq!(dcel.vertices.len(), 6);
+        let right = dcel.locate(&Point2::new(4, 2));
+        let left = dcel.locate(&Point2::new(-4, -1));
+        assert_eq!((*dcel.face_data(right), *dcel.face_data(left)), (Some(0), Some(1)));
+        assert_eq!(dcel.face_area(right), 18);
+        assert_eq!(dcel.face_area(left), 27);
+    }
+}
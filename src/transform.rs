@@ -0,0 +1,125 @@
+//! Moving every vertex of a DCEL at once: affine transforms and arbitrary
+//! coordinate maps such as map projections. Maps that reflect the plane
+//! would leave faces running clockwise, so the DCEL's cycles are reversed
+//! after them.
+
+use dcel::*;
+use geometry::*;
+use observe::Event;
+use scalar::Scalar;
+
+impl<P: Position, F: Clone> DCEL<P, F> {
+    /// Reverses every cycle, so that each half-edge starts where it used to
+    /// end and keeps its face. Faces running clockwise then run
+    /// counter-clockwise, as needed after a reflection.
+    pub fn reverse_orientation(&mut self) {
+        let edges: Vec<(HalfEdgeId, VertexId, HalfEdgeId)> = self.edges.iter()
+            .map(|(e, edge)| (e, self.get_destination(e), edge.prev().unwrap()))
+            .collect();
+        for &(e, destination, prev) in &edges {
+            self.edges[e].set_origin(destination);
+            self.make_next(e, prev);
+        }
+        for v in self.vertices.ids() {
+            if let Some(outgoing) = self.vertices[v].outgoing_edge() {
+                let twin = self.edges[outgoing].twin().unwrap();
+                self.vertices[v].set_outgoing_edge(twin);
+            }
+        }
+        self.notify(Event::OrientationReversed);
+    }
+}
+
+impl<T: Scalar, F: Clone> DCEL<Point2<T>, F> {
+    /// Applies an affine map to every vertex, reversing every cycle if the
+    /// map reflects. Maps that collapse the plane onto a line aren't checked
+    /// for, and leave every face degenerate.
+    pub fn transform(&mut self, map: &Affine2<T>) {
+        for v in self.vertices.ids() {
+            let p = map.apply(&self.vertices[v].coord());
+            self.set_vertex_coord(v, p);
+        }
+        if map.determinant() < T::zero() {
+            self.reverse_orientation();
+        }
+    }
+
+    /// Moves every vertex to its image under `f`, reversing every cycle if
+    /// that turned faces clockwise. The map should be continuous and
+    /// one-to-one over the subdivision, like a map projection, or edges may
+    /// cross.
+    pub fn map_points<M: FnMut(Point2<T>) -> Point2<T>>(&mut self, mut f: M) {
+        let before = self.orientation();
+        for v in self.vertices.ids() {
+            let p = f(self.vertices[v].coord());
+            self.set_vertex_coord(v, p);
+        }
+        let after = self.orientation();
+        if (before > T::zero() && after < T::zero()) || (before < T::zero() && after > T::zero()) {
+            self.reverse_orientation();
+        }
+    }
+
+    /// Returns twice the signed area enclosed by the outer boundaries of the
+    /// inner faces, or by the outer face's boundaries if there are none:
+    /// positive when the DCEL is oriented as it should be.
+    fn orientation(&self) -> T {
+        let mut area = T::zero();
+        for (_, face) in self.faces.iter() {
+            if let Some(e) = face.incident_edge() {
+                area = area + self.cycle_area2(e);
+            }
+        }
+        if area == T::zero() {
+            for &e in self.faces[self.outer_face].inner_components() {
+                area = area - self.cycle_area2(e);
+            }
+        }
+        return area;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn transforms_keep_faces_counter_clockwise() {
+        let points = [Point2::new(0.0, 0.0), Point2::new(2.0, 0.0), Point2::new(2.0, 1.0), Point2::new(0.0, 1.0)];
+        let mut dcel = DCEL::from_polygons(&points, &[&[0, 1, 2], &[0, 2, 3]]).unwrap();
+        let faces: Vec<FaceId> = dcel.faces.ids().into_iter().filter(|&f| f != dcel.outer_face).collect();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        dcel.add_observer(move |event| sink.lock().unwrap().push(*event));
+
+        let map = Affine2::rotation(0.5).then(&Affine2::scaling(3.0, 1.0))
+            .then(&Affine2::translation(Vector2::new(5.0, -2.0)));
+        dcel.transform(&map);
+        dcel.validate().unwrap();
+        assert!((dcel.face_area(faces[0]) - 3.0).abs() < 1e-9);
+        assert_eq!(events.lock().unwrap().len(), 4);
+        assert_eq!(events.lock().unwrap()[0], Event::VertexMoved(VertexId::from(0)));
+
+        // Reflecting turns every cycle around, keeping ids and payloads
+        dcel.transform(&Affine2::scaling(-1.0, 1.0));
+        dcel.validate().unwrap();
+        assert_eq!(*events.lock().unwrap().last().unwrap(), Event::OrientationReversed);
+        for &f in &faces {
+            assert!((dcel.face_area(f) - 3.0).abs() < 1e-9);
+        }
+        let full = map.then(&Affine2::scaling(-1.0, 1.0));
+        let (a, b) = (full.apply(&Point2::new(1.5, 0.25)), full.apply(&Point2::new(0.5, 0.75)));
+        assert!(dcel.locate(&a) != dcel.outer_face && dcel.locate(&b) != dcel.outer_face);
+        assert_ne!(dcel.locate(&a), dcel.locate(&b));
+
+        // Arbitrary maps are checked for reflection by area
+        let mut flipped = DCEL::from_polygons(&points, &[&[0, 1, 2], &[0, 2, 3]]).unwrap();
+        flipped.map_points(|p| Point2::new(p.x * p.x, -p.y));
+        flipped.validate().unwrap();
+        assert!(flipped.faces.ids().into_iter().filter(|&f| f != flipped.outer_face).all(|f| flipped.face_area(f) > 0.0));
+        for &e in flipped.faces[flipped.outer_face].inner_components() {
+            assert!(flipped.cycle_area2(e) < 0.0);
+        }
+    }
+}
@@ -0,0 +1,785 @@
+//! Triangulation of the faces of a planar DCEL by inserting diagonals, and
+//! Delaunay refinement of the result by edge flips.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use build::angle_order;
+use dcel::*;
+use geometry::*;
+use observe::Event;
+use scalar::Scalar;
+
+/// Faces without holes and with at most this many corners are ear-clipped
+/// by `triangulate_face`; the rest go through the monotone sweep.
+const EAR_CLIPPING_LIMIT: usize = 32;
+
+/// The algorithm used to triangulate a face.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Triangulation {
+    /// Repeatedly cuts off a convex corner containing no other vertex.
+    /// Holes are first bridged to the outer boundary. O(n²), plus O(n²) to
+    /// bridge each hole, but with small constants.
+    EarClipping,
+    /// Partitions the face into y-monotone pieces with a plane sweep, then
+    /// triangulates each piece in linear time: O(n log n) overall.
+    Monotone,
+}
+
+/// The role of a corner in the monotone sweep, which runs from top to bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Corner {
+    Start,
+    Split,
+    End,
+    Merge,
+    Regular,
+}
+
+/// Orders points from top to bottom, breaking ties from left to right.
+fn sweep_order<T: Scalar>(a: &Point2<T>, b: &Point2<T>) -> Ordering {
+    if a.y > b.y || (a.y == b.y && a.x < b.x) {
+        return Ordering::Less;
+    } else if a == b {
+        return Ordering::Equal;
+    }
+    return Ordering::Greater;
+}
+
+/// Determines if `a` comes before `b` in the sweep.
+fn above<T: Scalar>(a: &Point2<T>, b: &Point2<T>) -> bool {
+    return sweep_order(a, b) == Ordering::Less;
+}
+
+/// The edges crossing the sweep line, from left to right, kept in a treap
+/// so that each step of the sweep takes logarithmic time. Their order depends
+/// on where the sweep line is, so each operation is guided by a predicate
+/// that holds for the edges before some point and fails for the rest.
+struct SweepStatus {
+    nodes: Vec<StatusNode>,
+    root: Option<usize>,
+    seed: u64,
+}
+
+struct StatusNode {
+    edge: HalfEdgeId,
+    priority: u64,
+    left: Option<usize>,
+    right: Option<usize>,
+}
+
+impl SweepStatus {
+    fn new() -> SweepStatus {
+        return SweepStatus { nodes: Vec::new(), root: None, seed: 0x9e37_79b9_7f4a_7c15 };
+    }
+
+    /// Returns the last edge for which `before` holds.
+    fn last_before<B: Fn(HalfEdgeId) -> bool>(&self, before: B) -> Option<HalfEdgeId> {
+        let mut node = self.root;
+        let mut found = None;
+        while let Some(i) = node {
+            if before(self.nodes[i].edge) {
+                found = Some(self.nodes[i].edge);
+                node = self.nodes[i].right;
+            } else {
+                node = self.nodes[i].left;
+            }
+        }
+        return found;
+    }
+
+    /// Inserts `edge` after every edge for which `before` holds.
+    fn insert<B: Fn(HalfEdgeId) -> bool>(&mut self, edge: HalfEdgeId, before: B) {
+        // Xorshift priorities keep the treap balanced, and the sweep repeatable
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.nodes.push(StatusNode { edge: edge, priority: self.seed, left: None, right: None });
+        let node = Some(self.nodes.len() - 1);
+        let (left, right) = self.split(self.root, &before);
+        let left = self.merge(left, node);
+        self.root = self.merge(left, right);
+    }
+
+    /// Removes and returns the first edge for which `before` fails.
+    fn remove_first<B: Fn(HalfEdgeId) -> bool>(&mut self, before: B) -> Option<HalfEdgeId> {
+        let (left, right) = self.split(self.root, &before);
+        let (removed, right) = self.pop_first(right);
+        self.root = self.merge(left, right);
+        return removed;
+    }
+
+    /// Splits a subtree into the edges for which `before` holds and the rest.
+    fn split<B: Fn(HalfEdgeId) -> bool>(&mut self, node: Option<usize>, before: &B) -> (Option<usize>, Option<usize>) {
+        let i = match node {
+            Some(i) => i,
+            None => return (None, None),
+        };
+        if before(self.nodes[i].edge) {
+            let (left, right) = self.split(self.nodes[i].right, before);
+            self.nodes[i].right = left;
+            return (Some(i), right);
+        }
+        let (left, right) = self.split(self.nodes[i].left, before);
+        self.nodes[i].left = right;
+        return (left, Some(i));
+    }
+
+    /// Joins two subtrees, every edge of `a` coming before every edge of `b`.
+    fn merge(&mut self, a: Option<usize>, b: Option<usize>) -> Option<usize> {
+        let (i, j) = match (a, b) {
+            (Some(i), Some(j)) => (i, j),
+            _ => return a.or(b),
+        };
+        if self.nodes[i].priority > self.nodes[j].priority {
+            self.nodes[i].right = self.merge(self.nodes[i].right, b);
+            return a;
+        }
+        self.nodes[j].left = self.merge(a, self.nodes[j].left);
+        return b;
+    }
+
+    /// Removes the first edge of a subtree, returning it and what's left.
+    fn pop_first(&mut self, node: Option<usize>) -> (Option<HalfEdgeId>, Option<usize>) {
+        let i = match node {
+            Some(i) => i,
+            None => return (None, None),
+        };
+        if self.nodes[i].left.is_none() {
+            return (Some(self.nodes[i].edge), self.nodes[i].right);
+        }
+        let (removed, left) = self.pop_first(self.nodes[i].left);
+        self.nodes[i].left = left;
+        return (removed, node);
+    }
+}
+
+impl<T: Scalar, F: Clone> DCEL<Point2<T>, F> {
+    /// Inserts an edge from the origin of `a` to the origin of `b`, two
+    /// half-edges of the same face, returning the new half-edge that runs in
+    /// that direction; it is followed by `b`.
+    ///
+    /// If `a` and `b` lie on different boundary cycles, the cycles are joined
+    /// into one. Otherwise the face is split and the new part gets a copy of
+    /// its payload: the part containing `b` when splitting the outer
+    /// boundary, or the part enclosed by the new edge when splitting a hole.
+    /// Holes are handed to whichever part now surrounds them.
+    pub fn split_face(&mut self, a: HalfEdgeId, b: HalfEdgeId) -> HalfEdgeId {
+        let face = self.edges[a].face();
+        let prev_a = self.edges[a].prev().unwrap();
+        let prev_b = self.edges[b].prev().unwrap();
+
+        // Work out which cycles `a` and `b` are on before relinking
+        let same_cycle = self.cycle_edges(a).any(|e| e == b);
+        let is_outer = |e: HalfEdgeId| match self.faces[face].incident_edge() {
+            Some(start) => self.cycle_edges(start).any(|c| c == e),
+            None => false,
+        };
+        let hole_of = |e: HalfEdgeId| {
+            self.faces[face].inner_components().iter().cloned().find(|&h| self.cycle_edges(h).any(|c| c == e))
+        };
+        let (a_outer, b_outer) = (is_outer(a), is_outer(b));
+        let (a_hole, b_hole) = (hole_of(a), hole_of(b));
+
+        let va = self.edges[a].origin();
+        let vb = self.edges[b].origin();
+        let edge = self.add_half_edge(va, face);
+        let twin = self.add_half_edge(vb, face);
+        self.make_twins(edge, twin);
+        self.make_next(prev_a, edge);
+        self.make_next(edge, b);
+        self.make_next(prev_b, twin);
+        self.make_next(twin, a);
+
+        if !same_cycle {
+            // One cycle is left; forget the hole that was joined to the other
+            let joined = if b_outer { a_hole } else { b_hole };
+            if let Some(hole) = joined {
+                self.faces[face].replace_edge(hole, None);
+            }
+            return edge;
+        }
+
+        let (kept, split_off) = if a_outer || self.cycle_area2(edge) > T::zero() {
+            (twin, edge)
+        } else {
+            (edge, twin)
+        };
+        if a_outer {
+            self.faces[face].set_incident_edge(Some(kept));
+        } else if let Some(hole) = a_hole {
+            self.faces[face].replace_edge(hole, Some(kept));
+        }
+
+        let data = self.faces[face].data().clone();
+        let new_face = self.add_face_with(data);
+        self.faces[new_face].set_incident_edge(Some(split_off));
+        for e in self.get_cycle_from(split_off) {
+            self.edges[e].set_face(new_face);
+        }
+        for hole in self.faces[face].inner_components().to_vec() {
+            if hole != kept && self.cycle_encloses(split_off, hole) {
+                self.faces[face].replace_edge(hole, None);
+                self.add_inner_component(new_face, hole);
+                for e in self.get_cycle_from(hole) {
+                    self.edges[e].set_face(new_face);
+                }
+            }
+        }
+        self.notify(Event::FaceSplit { old: face, new: new_face });
+        return edge;
+    }
+
+    /// Splits a face into triangles by inserting diagonals, each new face
+    /// getting a copy of its payload, and returns one half-edge of each
+    /// diagonal. Faces with holes work too, as the diagonals join the holes
+    /// to the outer boundary. Small faces without holes are ear-clipped and
+    /// the rest partitioned into monotone pieces. The outer face is left
+    /// alone.
+    ///
+    /// Faces too degenerate to triangulate, such as those whose boundary
+    /// crosses itself, are reported as `DegenerateFace` and may be left
+    /// partly split.
+    pub fn triangulate_face(&mut self, face: FaceId) -> Result<Vec<HalfEdgeId>, TopologyError> {
+        let method = if self.faces[face].inner_components().is_empty()
+            && self.face_edges(face).count() <= EAR_CLIPPING_LIMIT {
+            Triangulation::EarClipping
+        } else {
+            Triangulation::Monotone
+        };
+        return self.triangulate_face_with(face, method);
+    }
+
+    /// Triangulates a face with the given algorithm, as `triangulate_face`.
+    pub fn triangulate_face_with(&mut self, face: FaceId, method: Triangulation)
+                                 -> Result<Vec<HalfEdgeId>, TopologyError> {
+        let mut faces = vec![face];
+        let mut diagonals = Vec::new();
+        if face == self.outer_face || self.faces[face].incident_edge().is_none() {
+            return Ok(diagonals);
+        }
+
+        if method == Triangulation::Monotone {
+            let partition = self.monotone_partition(face)?;
+            self.insert_diagonals(face, &partition, &mut faces, &mut diagonals);
+            for piece in faces.clone() {
+                if self.faces[piece].inner_components().is_empty() {
+                    let start = self.faces[piece].incident_edge().unwrap();
+                    let fan = self.triangulate_monotone(start);
+                    self.insert_diagonals(piece, &fan, &mut faces, &mut diagonals);
+                }
+            }
+        }
+
+        // Ear clipping also mops up whatever degenerate input left behind
+        for piece in faces.clone() {
+            if !self.faces[piece].inner_components().is_empty() || self.face_edges(piece).count() > 3 {
+                self.ear_clip(piece, &mut faces, &mut diagonals)?;
+            }
+        }
+        return Ok(diagonals);
+    }
+
+    /// Triangulates every inner face, returning one half-edge of each
+    /// diagonal inserted.
+    pub fn triangulate_all(&mut self) -> Result<Vec<HalfEdgeId>, TopologyError> {
+        let faces: Vec<FaceId> = self.faces.iter().map(|(f, _)| f).filter(|&f| f != self.outer_face).collect();
+        let mut diagonals = Vec::new();
+        for face in faces {
+            diagonals.extend(self.triangulate_face(face)?);
+        }
+        return Ok(diagonals);
+    }
+
+    /// Flips the given edges, typically the diagonals returned by a
+    /// triangulation, until each of them is locally Delaunay. Every other
+    /// edge is kept as a constraint, so the result is a constrained Delaunay
+    /// triangulation. Returns the number of flips made.
+    pub fn flip_to_delaunay(&mut self, edges: &[HalfEdgeId]) -> usize {
+        let mut flippable = HashSet::new();
+        for &e in edges {
+            flippable.insert(e);
+            flippable.insert(self.edges[e].twin().unwrap());
+        }
+
+        let mut stack = edges.to_vec();
+        let mut flips = 0;
+        while let Some(e) = stack.pop() {
+            if !self.edges.contains(e) || !self.is_illegal(e) {
+                continue;
+            }
+            let twin = self.edges[e].twin().unwrap();
+            let around = [self.edges[e].next().unwrap(), self.edges[e].prev().unwrap(),
+                          self.edges[twin].next().unwrap(), self.edges[twin].prev().unwrap()];
+            if self.flip_edge(e).is_ok() {
+                flips += 1;
+                stack.extend(around.iter().filter(|e| flippable.contains(e)));
+            }
+        }
+        return flips;
+    }
+
+    /// Determines if the edge between two triangles fails the Delaunay test
+    /// and flipping it leaves two proper triangles.
+    fn is_illegal(&self, e: HalfEdgeId) -> bool {
+        let twin = self.edges[e].twin().unwrap();
+        for &side in &[e, twin] {
+            let face = self.edges[side].face();
+            if face == self.outer_face || self.cycle_edges(side).count() != 3 {
+                return false;
+            }
+        }
+        let a = self.position(e);
+        let b = self.position(twin);
+        let c = self.position(self.edges[self.edges[e].next().unwrap()].next().unwrap());
+        let d = self.position(self.edges[self.edges[twin].next().unwrap()].next().unwrap());
+        return in_circle(&a, &b, &c, &d) && is_lht(&a, &d, &c) && is_lht(&d, &b, &c);
+    }
+
+    /// Returns the position of the origin of a half-edge.
+    pub(crate) fn position(&self, e: HalfEdgeId) -> Point2<T> {
+        return self.vertices[self.edges[e].origin()].coord();
+    }
+
+    /// Determines if the direction from the origin of `e` towards `q` points
+    /// into the face, within the corner between `e` and its predecessor.
+    pub(crate) fn corner_contains(&self, e: HalfEdgeId, q: &Point2<T>) -> bool {
+        let u = self.position(self.edges[e].prev().unwrap());
+        let v = self.position(e);
+        let w = self.position(self.edges[e].next().unwrap());
+        if is_lht_or_on(&u, &v, &w) {
+            return is_lht(&u, &v, q) && is_lht(&v, &w, q);
+        }
+        return is_lht(&u, &v, q) || is_lht(&v, &w, q);
+    }
+
+    /// Inserts non-crossing diagonals into a face all at once, each between
+    /// the origins of two of its half-edges, through the corners they close.
+    /// The face keeps the part containing its outer boundary, and each other
+    /// part becomes a new face with a copy of its payload. Holes the
+    /// diagonals don't reach stay with the face.
+    ///
+    /// Unlike inserting diagonals one by one with `split_face`, this takes
+    /// O(n log n) time however many there are.
+    fn insert_diagonals(&mut self, face: FaceId, corners: &[(HalfEdgeId, HalfEdgeId)], faces: &mut Vec<FaceId>,
+                        diagonals: &mut Vec<HalfEdgeId>) {
+        if corners.is_empty() {
+            return;
+        }
+        let mut leaving: HashMap<HalfEdgeId, Vec<HalfEdgeId>> = HashMap::new();
+        let mut added = Vec::with_capacity(2 * corners.len());
+        for &(a, b) in corners {
+            let edge = self.add_half_edge(self.edges[a].origin(), face);
+            let twin = self.add_half_edge(self.edges[b].origin(), face);
+            self.make_twins(edge, twin);
+            leaving.entry(a).or_default().push(edge);
+            leaving.entry(b).or_default().push(twin);
+            diagonals.push(edge);
+            added.push(edge);
+            added.push(twin);
+        }
+
+        // Within each corner, an incoming edge continues with the outgoing
+        // edge just clockwise of its twin, as in `from_edges`
+        for (corner, mut out) in leaving {
+            let origin = self.position(corner);
+            let base = self.position(self.edges[corner].next().unwrap()) - origin;
+            let frame = |d: Vector2<T>| Vector2::new(base.dot(&d), base.cross(&d));
+            out.sort_by(|&a, &b| {
+                let a = self.position(self.edges[a].twin().unwrap()) - origin;
+                let b = self.position(self.edges[b].twin().unwrap()) - origin;
+                return angle_order(frame(a), frame(b));
+            });
+            let prev = self.edges[corner].prev().unwrap();
+            self.make_next(prev, *out.last().unwrap());
+            for i in 0..out.len() {
+                let incoming = self.edges[out[i]].twin().unwrap();
+                self.make_next(incoming, if i == 0 { corner } else { out[i - 1] });
+            }
+        }
+
+        // Every cycle but the one through the outer boundary is a new face
+        let boundary = self.faces[face].incident_edge().unwrap();
+        let mut seen: HashSet<HalfEdgeId> = self.get_cycle_from(boundary).into_iter().collect();
+        let data = self.faces[face].data().clone();
+        for start in added {
+            if seen.contains(&start) {
+                continue;
+            }
+            let cycle = self.get_cycle_from(start);
+            let new_face = self.add_face_with(data.clone());
+            self.faces[new_face].set_incident_edge(Some(start));
+            for &e in &cycle {
+                self.edges[e].set_face(new_face);
+            }
+            seen.extend(cycle);
+            faces.push(new_face);
+            self.notify(Event::FaceSplit { old: face, new: new_face });
+        }
+        let holes = self.faces[face].inner_components().iter().cloned().filter(|h| !seen.contains(h)).collect();
+        self.faces[face].set_inner_components(holes);
+    }
+
+    /// Sweeps a face from top to bottom, returning the diagonals that split
+    /// it into y-monotone pieces and join its holes to its outer boundary,
+    /// each as the two corners it closes.
+    fn monotone_partition(&self, face: FaceId) -> Result<Vec<(HalfEdgeId, HalfEdgeId)>, TopologyError> {
+        let mut corners: Vec<HalfEdgeId> = self.face_cycles(face).into_iter()
+            .flat_map(|start| self.get_cycle_from(start))
+            .collect();
+        corners.sort_by(|&a, &b| sweep_order(&self.position(a), &self.position(b)));
+
+        // Edges crossing the sweep line with the face to their right, from
+        // left to right, each with its helper and whether that's a merge corner
+        let mut status = SweepStatus::new();
+        let mut helper: HashMap<HalfEdgeId, (HalfEdgeId, bool)> = HashMap::new();
+        let mut diagonals = Vec::new();
+
+        for &e in &corners {
+            let prev = self.edges[e].prev().unwrap();
+            let u = self.position(prev);
+            let v = self.position(e);
+            let w = self.position(self.edges[e].next().unwrap());
+            let kind = match (above(&v, &u), above(&v, &w)) {
+                (true, true) if is_lht(&u, &v, &w) => Corner::Start,
+                (true, true) => Corner::Split,
+                (false, false) if is_lht(&u, &v, &w) => Corner::End,
+                (false, false) => Corner::Merge,
+                _ => Corner::Regular,
+            };
+
+            let fix_up = |edge: HalfEdgeId, diagonals: &mut Vec<(HalfEdgeId, HalfEdgeId)>| {
+                if let Some(&(h, true)) = helper.get(&edge) {
+                    diagonals.push((e, h));
+                }
+            };
+            let left_of = |status: &SweepStatus| {
+                return status.last_before(|s| self.is_left_of(s, &v)).ok_or(TopologyError::DegenerateFace(face));
+            };
+            // `prev` ends here, so it's the first edge not left of the rest
+            let remove_prev = |status: &mut SweepStatus| {
+                match status.remove_first(|s| s != prev && self.is_left_of(s, &v)) {
+                    Some(removed) if removed == prev => return Ok(()),
+                    _ => return Err(TopologyError::DegenerateFace(face)),
+                }
+            };
+
+            match kind {
+                Corner::Start => {}
+                Corner::End => fix_up(prev, &mut diagonals),
+                Corner::Split => {
+                    let left = left_of(&status)?;
+                    diagonals.push((e, helper[&left].0));
+                    helper.insert(left, (e, false));
+                }
+                Corner::Merge => {
+                    fix_up(prev, &mut diagonals);
+                    remove_prev(&mut status)?;
+                    let left = left_of(&status)?;
+                    fix_up(left, &mut diagonals);
+                    helper.insert(left, (e, true));
+                }
+                Corner::Regular if above(&u, &v) => fix_up(prev, &mut diagonals),
+                Corner::Regular => {
+                    let left = left_of(&status)?;
+                    fix_up(left, &mut diagonals);
+                    helper.insert(left, (e, false));
+                }
+            }
+
+            // Edges leaving the sweep line, then those entering it
+            if kind == Corner::End || (kind == Corner::Regular && above(&u, &v)) {
+                remove_prev(&mut status)?;
+            }
+            if kind == Corner::Start || kind == Corner::Split || (kind == Corner::Regular && above(&u, &v)) {
+                status.insert(e, |s| self.is_left_of(s, &v));
+                helper.insert(e, (e, false));
+            }
+        }
+        return Ok(diagonals);
+    }
+
+    /// Determines if the half-edge `e`, which runs downwards in the sweep,
+    /// passes to the left of `p`.
+    fn is_left_of(&self, e: HalfEdgeId, p: &Point2<T>) -> bool {
+        let a = self.position(e);
+        let b = self.position(self.edges[e].next().unwrap());
+        let side = area_of_parallelogram(&a, &b, p);
+        if side == T::zero() {
+            let right = if a.x > b.x { a.x } else { b.x };
+            return right <= p.x;
+        }
+        return side > T::zero();
+    }
+
+    /// Returns the diagonals triangulating the y-monotone polygon bounded by
+    /// the cycle through `start`, each as the two corners it closes.
+    fn triangulate_monotone(&self, start: HalfEdgeId) -> Vec<(HalfEdgeId, HalfEdgeId)> {
+        let cycle = self.get_cycle_from(start);
+        let n = cycle.len();
+        let mut diagonals = Vec::new();
+        if n <= 3 {
+            return diagonals;
+        }
+
+        // Going forwards from the top runs down the left chain
+        let top = (0..n).min_by(|&i, &j| sweep_order(&self.position(cycle[i]), &self.position(cycle[j]))).unwrap();
+        let bottom = (0..n).max_by(|&i, &j| sweep_order(&self.position(cycle[i]), &self.position(cycle[j]))).unwrap();
+        let mut corners: Vec<(HalfEdgeId, Point2<T>, bool)> = Vec::with_capacity(n);
+        let mut i = top;
+        let mut left = true;
+        for _ in 0..n {
+            if i == bottom {
+                left = false;
+            }
+            corners.push((cycle[i], self.position(cycle[i]), left));
+            i = (i + 1) % n;
+        }
+        corners.sort_by(|a, b| sweep_order(&a.1, &b.1));
+
+        let mut stack = vec![corners[0], corners[1]];
+        for j in 2..n - 1 {
+            let u = corners[j];
+            let mut last = stack.pop().unwrap();
+            if u.2 != last.2 {
+                // Opposite chains: fan out to everything on the stack
+                diagonals.push((u.0, last.0));
+                while stack.len() > 1 {
+                    diagonals.push((u.0, stack.pop().unwrap().0));
+                }
+                stack.clear();
+                stack.push(corners[j - 1]);
+            } else {
+                // Same chain: cut off corners for as long as they're convex
+                while let Some(&s) = stack.last() {
+                    let inside = if u.2 { is_lht(&s.1, &last.1, &u.1) } else { is_lht(&u.1, &last.1, &s.1) };
+                    if !inside {
+                        break;
+                    }
+                    last = stack.pop().unwrap();
+                    diagonals.push((u.0, last.0));
+                }
+                stack.push(last);
+            }
+            stack.push(u);
+        }
+
+        let bottom = corners[n - 1];
+        stack.pop();
+        while stack.len() > 1 {
+            diagonals.push((bottom.0, stack.pop().unwrap().0));
+        }
+        return diagonals;
+    }
+
+    /// Triangulates a face by ear clipping, first bridging each of its holes
+    /// to its outer boundary.
+    fn ear_clip(&mut self, face: FaceId, faces: &mut Vec<FaceId>,
+                diagonals: &mut Vec<HalfEdgeId>) -> Result<(), TopologyError> {
+        // Bridge the holes from right to left, each from its rightmost vertex
+        while !self.faces[face].inner_components().is_empty() {
+            let rightmost = |dcel: &Self, hole: HalfEdgeId| {
+                dcel.cycle_edges(hole).max_by(|&a, &b| sweep_order(&dcel.position(b), &dcel.position(a))).unwrap()
+            };
+            let h = self.faces[face].inner_components().iter()
+                .map(|&hole| rightmost(self, hole))
+                .max_by(|&a, &b| sweep_order(&self.position(b), &self.position(a)))
+                .unwrap();
+            let target = self.bridge_target(face, h).ok_or(TopologyError::DegenerateFace(face))?;
+            diagonals.push(self.split_face(h, target));
+        }
+
+        // The corners left form a ring linked through `prev` and `next`. Only
+        // a corner that isn't convex can lie in the triangle an ear cuts off,
+        // and clipping an ear only changes its neighbours, so each clip takes
+        // a walk to the next ear plus a few checks against `reflex`.
+        let mut corners = self.get_cycle_from(self.faces[face].incident_edge().unwrap());
+        let n = corners.len();
+        let mut prev: Vec<usize> = (0..n).map(|i| (i + n - 1) % n).collect();
+        let mut next: Vec<usize> = (0..n).map(|i| (i + 1) % n).collect();
+        let mut reflex: BTreeSet<usize> = (0..n).filter(|&i| !self.is_convex(&corners, prev[i], i, next[i])).collect();
+        let mut ear: Vec<bool> = (0..n).map(|i| self.is_ear(&corners, &reflex, prev[i], i, next[i])).collect();
+        let mut i = 0;
+        for remaining in (4..n + 1).rev() {
+            let mut steps = 0;
+            while !ear[i] {
+                i = next[i];
+                steps += 1;
+                if steps > remaining {
+                    return Err(TopologyError::DegenerateFace(face));
+                }
+            }
+            let (p, q) = (prev[i], next[i]);
+            let edge = self.split_face(corners[q], corners[p]);
+            faces.push(self.edges[edge].face());
+            diagonals.push(edge);
+            corners[p] = self.edges[edge].twin().unwrap();
+            next[p] = q;
+            prev[q] = p;
+            for &j in &[p, q] {
+                if self.is_convex(&corners, prev[j], j, next[j]) {
+                    reflex.remove(&j);
+                }
+            }
+            for &j in &[p, q] {
+                ear[j] = self.is_ear(&corners, &reflex, prev[j], j, next[j]);
+            }
+            i = p;
+        }
+        return Ok(());
+    }
+
+    fn is_convex(&self, corners: &[HalfEdgeId], prev: usize, i: usize, next: usize) -> bool {
+        return is_lht(&self.position(corners[prev]), &self.position(corners[i]), &self.position(corners[next]));
+    }
+
+    /// Determines if the corner at `corners[i]` is an ear: convex, with none
+    /// of the `reflex` corners inside or on the triangle it cuts off.
+    fn is_ear(&self, corners: &[HalfEdgeId], reflex: &BTreeSet<usize>, prev: usize, i: usize, next: usize) -> bool {
+        if !self.is_convex(corners, prev, i, next) {
+            return false;
+        }
+        let a = self.position(corners[prev]);
+        let b = self.position(corners[i]);
+        let c = self.position(corners[next]);
+        return reflex.iter().map(|&j| self.position(corners[j])).all(|p| {
+            p == a || p == b || p == c || is_rht(&a, &b, &p) || is_rht(&b, &c, &p) || is_rht(&c, &a, &p)
+        });
+    }
+
+    /// Finds the nearest corner of the outer boundary of `face` that the
+    /// corner at `h` sees, through the face, for a bridge to a hole.
+    fn bridge_target(&self, face: FaceId, h: HalfEdgeId) -> Option<HalfEdgeId> {
+        let p = self.position(h);
+        let outer = self.faces[face].incident_edge()?;
+        let edges: Vec<HalfEdgeId> = self.face_cycles(face).into_iter()
+            .flat_map(|start| self.get_cycle_from(start))
+            .collect();
+        let mut best: Option<(HalfEdgeId, T)> = None;
+        for e in self.cycle_edges(outer) {
+            let q = self.position(e);
+            let dist = p.dist_sq(&q);
+            if best.is_some_and(|(_, d)| d <= dist) || !self.corner_contains(e, &p) || !self.corner_contains(h, &q) {
+                continue;
+            }
+            let bridge = Segment2::new(p, q);
+            let blocked = edges.iter().any(|&f| {
+                let edge = Segment2::new(self.position(f), self.position(self.edges[f].next().unwrap()));
+                match segment_intersection(&bridge, &edge) {
+                    Intersection::None => false,
+                    Intersection::Touching { point, .. } => point != p && point != q,
+                    _ => true,
+                }
+            });
+            if !blocked {
+                best = Some((e, dist));
+            }
+        }
+        return best.map(|(e, _)| e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ring(points: &[(i32, i32)]) -> Vec<Point2> {
+        return points.iter().map(|&(x, y)| Point2::new(x as f64, y as f64)).collect();
+    }
+
+    /// Checks that every face from polygon 0 is a triangle, and that
+    /// together they cover `area`.
+    fn check_triangles(dcel: &DCEL<Point2, Option<usize>>, area: f64) {
+        dcel.validate().unwrap();
+        let mut total = 0.0;
+        for (face, f) in dcel.faces.iter() {
+            if *f.data() == Some(0) {
+                assert_eq!(dcel.face_edges(face).count(), 3);
+                assert!(f.inner_components().is_empty());
+                assert!(dcel.face_area(face) > 0.0);
+                total += dcel.face_area(face);
+            }
+        }
+        assert_eq!(total, area);
+    }
+
+    #[test]
+    fn triangulates_concave_polygons() {
+        // A comb with three teeth pointing up
+        let comb = ring(&[(0, 0), (7, 0), (7, 3), (6, 3), (6, 1), (4, 1), (4, 3), (3, 3), (3, 1), (1, 1), (1, 3), (0, 3)]);
+        for &method in &[Triangulation::EarClipping, Triangulation::Monotone] {
+            let mut dcel: DCEL<Point2, Option<usize>> = DCEL::from_polygon_rings(&[vec![comb.clone()]], |i| i).unwrap();
+            let face = FaceId::from(1);
+            let area = dcel.face_area(face);
+            let diagonals = dcel.triangulate_face_with(face, method).unwrap();
+            assert_eq!(diagonals.len(), 9);
+            assert_eq!(dcel.faces.len(), 11);
+            check_triangles(&dcel, area);
+            assert!(dcel.faces.iter().all(|(f, face)| f == dcel.outer_face || *face.data() == Some(0)));
+        }
+    }
+
+    #[test]
+    fn triangulates_long_combs() {
+        // Teeth pointing up make merge corners, and pointing down split ones
+        let teeth = 100;
+        let mut up = vec![(0, 0), (2 * teeth + 1, 0)];
+        for i in (0..teeth).rev() {
+            up.extend_from_slice(&[(2 * i + 2, 3), (2 * i + 1, 3), (2 * i + 1, 1)]);
+        }
+        up.push((0, 3));
+        let down: Vec<(i32, i32)> = up.iter().rev().map(|&(x, y)| (x, -y)).collect();
+        for comb in &[up, down] {
+            let mut dcel: DCEL<Point2, Option<usize>> = DCEL::from_polygon_rings(&[vec![ring(comb)]], |i| i).unwrap();
+            let face = FaceId::from(1);
+            let area = dcel.face_area(face);
+            let diagonals = dcel.triangulate_face_with(face, Triangulation::Monotone).unwrap();
+            assert_eq!(diagonals.len(), comb.len() - 3);
+            check_triangles(&dcel, area);
+        }
+    }
+
+    #[test]
+    fn triangulates_faces_with_holes() {
+        let outer = ring(&[(0, 0), (6, 0), (6, 6), (0, 6)]);
+        let holes = [ring(&[(1, 1), (2, 1), (2, 2), (1, 2)]), ring(&[(4, 3), (5, 4), (4, 5), (3, 4)])];
+        for &method in &[Triangulation::EarClipping, Triangulation::Monotone] {
+            let rings = vec![outer.clone(), holes[0].clone(), holes[1].clone()];
+            let mut dcel: DCEL<Point2, Option<usize>> = DCEL::from_polygon_rings(&[rings], |i| i).unwrap();
+            let face = FaceId::from(1);
+            assert_eq!(dcel.faces[face].inner_components().len(), 2);
+            let diagonals = dcel.triangulate_face_with(face, method).unwrap();
+            // n + 2h - 2 triangles, for n corners and h holes
+            assert_eq!(diagonals.len(), 12 + 4 - 2 + 2 - 1);
+            check_triangles(&dcel, 33.0);
+        }
+
+        // The gap faces inside the holes get triangulated as well
+        let rings = vec![outer, holes[0].clone(), holes[1].clone()];
+        let mut dcel: DCEL<Point2, Option<usize>> = DCEL::from_polygon_rings(&[rings], |i| i).unwrap();
+        dcel.triangulate_all().unwrap();
+        check_triangles(&dcel, 33.0);
+        assert_eq!(dcel.faces.len(), 1 + 14 + 2 + 2);
+        assert!(dcel.faces.iter().all(|(f, _)| f == dcel.outer_face || dcel.face_edges(f).count() == 3));
+    }
+
+    #[test]
+    fn flips_to_delaunay() {
+        // A flat quadrilateral, split along its long diagonal
+        let quad = ring(&[(0, 0), (2, -1), (4, 0), (2, 1)]);
+        let mut dcel: DCEL<Point2, Option<usize>> = DCEL::from_polygon_rings(&[vec![quad]], |i| i).unwrap();
+        let diagonals = dcel.triangulate_all().unwrap();
+        assert_eq!(diagonals.len(), 1);
+        let length = |dcel: &DCEL<Point2, Option<usize>>, e: HalfEdgeId| {
+            dcel.position(e).dist_sq(&dcel.position(dcel.edges[e].twin().unwrap()))
+        };
+        if length(&dcel, diagonals[0]) != 16.0 {
+            dcel.flip_edge(diagonals[0]).unwrap();
+        }
+
+        assert_eq!(dcel.flip_to_delaunay(&diagonals), 1);
+        assert_eq!(length(&dcel, diagonals[0]), 4.0);
+        assert_eq!(dcel.flip_to_delaunay(&diagonals), 0);
+        check_triangles(&dcel, 4.0);
+    }
+}
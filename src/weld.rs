@@ -0,0 +1,293 @@
+//! Welding of near-duplicate vertices, as found in imported data. Vertices
+//! closer together than a tolerance are merged, and the zero-length edges,
+//! doubled edges and collapsed faces this leaves behind are removed.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use build::angle_order;
+use dcel::*;
+use geometry::*;
+use observe::Event;
+use scalar::Scalar;
+
+/// What `DCEL::weld_vertices` changed. Ids are listed in increasing order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WeldReport {
+    /// Each vertex merged away, with the vertex it was merged into.
+    pub merged: Vec<(VertexId, VertexId)>,
+    /// Half-edges removed for having no length or for doubling another edge.
+    pub removed_edges: Vec<HalfEdgeId>,
+    /// Faces removed for having collapsed onto their edges.
+    pub removed_faces: Vec<FaceId>,
+    /// Faces split off where welding pinched a face in two.
+    pub added_faces: Vec<FaceId>,
+}
+
+impl WeldReport {
+    /// Determines if welding changed nothing.
+    pub fn is_empty(&self) -> bool {
+        return self.merged.is_empty();
+    }
+}
+
+/// Returns the root of `i` in a union-find forest, compressing the path.
+fn find_root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    return i;
+}
+
+/// Groups points closer than `epsilon` to one another, chaining through
+/// intermediate points. Returns, for each point, the index of the first
+/// point in its group.
+pub(crate) fn cluster_points<P: Position>(points: &[P], epsilon: P::Scalar) -> Vec<usize> {
+    let mut parent: Vec<usize> = (0..points.len()).collect();
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by(|&a, &b| points[a].component(0).partial_cmp(&points[b].component(0)).unwrap_or(Ordering::Equal));
+    let limit = epsilon * epsilon;
+    for (i, &a) in order.iter().enumerate() {
+        for &b in &order[i + 1..] {
+            if points[b].component(0) - points[a].component(0) >= epsilon {
+                break;
+            }
+            if points[a].dist_sq(&points[b]) < limit {
+                // The smaller root wins, so each root is the first of its group
+                let (ra, rb) = (find_root(&mut parent, a), find_root(&mut parent, b));
+                if ra < rb {
+                    parent[rb] = ra;
+                } else {
+                    parent[ra] = rb;
+                }
+            }
+        }
+    }
+    return (0..points.len()).map(|i| find_root(&mut parent, i)).collect();
+}
+
+impl<T: Scalar, F: Clone> DCEL<Point2<T>, F> {
+    /// Merges vertices closer than `epsilon` to one another, as measured by
+    /// `Point2::dist_sq`. Each group of vertices, chained through any
+    /// within `epsilon` of each other, is merged into its first vertex,
+    /// which stays where it is. Edges between merged vertices are removed;
+    /// edges left running between the same two vertices are merged into
+    /// one, removing the faces squashed between them. Where welding pinches
+    /// a face in two, the new part gets a copy of its payload.
+    ///
+    /// The subdivision should be planar beforehand. Gaps that welding
+    /// encloses stay part of the outer face.
+    pub fn weld_vertices(&mut self, epsilon: T) -> WeldReport {
+        let mut report = WeldReport::default();
+        let ids = self.vertices.ids();
+        let points: Vec<Point2<T>> = ids.iter().map(|&v| self.vertices[v].coord()).collect();
+        let mut kept: HashMap<VertexId, VertexId> = HashMap::new();
+        for (i, root) in cluster_points(&points, epsilon).into_iter().enumerate() {
+            if root != i {
+                kept.insert(ids[i], ids[root]);
+                report.merged.push((ids[i], ids[root]));
+            }
+        }
+        if kept.is_empty() {
+            return report;
+        }
+
+        // Where each edge used to run decides how edges that end up on top
+        // of each other are ordered
+        let original: HashMap<HalfEdgeId, (Point2<T>, Point2<T>)> = self.edges.iter()
+            .map(|(e, edge)| (e, (self.vertices[edge.origin()].coord(), self.vertices[self.get_destination(e)].coord())))
+            .collect();
+        let mut relink: HashSet<VertexId> = kept.values().cloned().collect();
+        let mut touched: HashSet<FaceId> = HashSet::new();
+
+        for e in self.edges.ids() {
+            if let Some(&keep) = kept.get(&self.edges[e].origin()) {
+                self.edges[e].set_origin(keep);
+            }
+        }
+        for &(removed, keep) in &report.merged {
+            self.delete_vertex(removed);
+            self.notify(Event::VerticesWelded { kept: keep, removed: removed });
+        }
+        for e in self.edges.ids() {
+            if self.edges.contains(e) && self.edges[e].origin() == self.get_destination(e) {
+                let twin = self.edges[e].twin().unwrap();
+                for &h in &[e, twin] {
+                    touched.insert(self.edges[h].face());
+                    self.delete_edge(h);
+                    report.removed_edges.push(h);
+                }
+            }
+        }
+
+        // Of edges doubled up between the same two vertices, the lowest keeps
+        // the face on its right and the highest the face on its left, and the
+        // slivers between them go
+        let mut between: HashMap<(VertexId, VertexId), Vec<HalfEdgeId>> = HashMap::new();
+        for (e, edge) in self.edges.iter() {
+            let (from, to) = (edge.origin(), self.get_destination(e));
+            if from < to && (relink.contains(&from) || relink.contains(&to)) {
+                between.entry((from, to)).or_default().push(e);
+            }
+        }
+        let mut doubled: Vec<((VertexId, VertexId), Vec<HalfEdgeId>)> = between.into_iter()
+            .filter(|(_, edges)| edges.len() > 1)
+            .collect();
+        doubled.sort();
+        for ((from, to), mut edges) in doubled {
+            let (a, b) = (self.vertices[from].coord(), self.vertices[to].coord());
+            let height = |e: &HalfEdgeId| (b - a).cross(&(midpoint(original[e].0, original[e].1) - a));
+            edges.sort_by(|x, y| height(x).partial_cmp(&height(y)).unwrap_or(Ordering::Equal));
+            let top = edges[edges.len() - 1];
+            let bottom = self.edges[edges[0]].twin().unwrap();
+            let mut removed: Vec<HalfEdgeId> = edges[..edges.len() - 1].to_vec();
+            removed.extend(edges[1..].iter().map(|&e| self.edges[e].twin().unwrap()));
+            for e in removed {
+                touched.insert(self.edges[e].face());
+                self.delete_edge(e);
+                report.removed_edges.push(e);
+            }
+            self.make_twins(top, bottom);
+            relink.insert(from);
+            relink.insert(to);
+        }
+
+        // Edges around the vertices affected are relinked in angular order,
+        // as when building from edges
+        let mut outgoing: HashMap<VertexId, Vec<HalfEdgeId>> = relink.iter().map(|&v| (v, Vec::new())).collect();
+        for (e, edge) in self.edges.iter() {
+            if let Some(out) = outgoing.get_mut(&edge.origin()) {
+                out.push(e);
+            }
+        }
+        for (v, mut out) in outgoing {
+            let p = self.vertices[v].coord();
+            let direction = |e: HalfEdgeId| self.vertices[self.get_destination(e)].coord() - p;
+            let was = |e: HalfEdgeId| original[&e].1 - original[&e].0;
+            out.sort_by(|&x, &y| angle_order(direction(x), direction(y)).then_with(|| angle_order(was(x), was(y))));
+            for j in 0..out.len() {
+                let incoming = self.edges[out[j]].twin().unwrap();
+                self.make_next(incoming, out[(j + out.len() - 1) % out.len()]);
+                touched.insert(self.edges[out[j]].face());
+                touched.insert(self.edges[incoming].face());
+            }
+            match out.first() {
+                Some(&e) => self.vertices[v].set_outgoing_edge(e),
+                None => self.vertices[v].clear_outgoing_edge(),
+            }
+        }
+
+        self.rebuild_faces(touched, &mut report);
+        report.removed_edges.sort();
+        report.removed_faces.sort();
+        return report;
+    }
+
+    /// Recomputes the boundary of each face given from the cycles now
+    /// running around it. Faces left without any are removed, and faces
+    /// with several counter-clockwise cycles are split into one face each.
+    fn rebuild_faces(&mut self, faces: HashSet<FaceId>, report: &mut WeldReport) {
+        let mut cycles: HashMap<FaceId, Vec<HalfEdgeId>> = faces.into_iter()
+            .filter(|&f| self.faces.contains(f))
+            .map(|f| (f, Vec::new()))
+            .collect();
+        let mut seen = HashSet::new();
+        for e in self.edges.ids() {
+            let face = self.edges[e].face();
+            if cycles.contains_key(&face) && !seen.contains(&e) {
+                seen.extend(self.get_cycle_from(e));
+                cycles.get_mut(&face).unwrap().push(e);
+            }
+        }
+        let mut cycles: Vec<(FaceId, Vec<HalfEdgeId>)> = cycles.into_iter().collect();
+        cycles.sort();
+
+        for (face, starts) in cycles {
+            if face == self.outer_face {
+                self.faces[face].set_incident_edge(None);
+                self.faces[face].set_inner_components(starts);
+                continue;
+            } else if starts.is_empty() {
+                self.delete_face(face);
+                report.removed_faces.push(face);
+                continue;
+            }
+
+            // Each counter-clockwise cycle bounds a face of its own, and a
+            // face squashed flat keeps its largest cycle as its boundary
+            let areas: Vec<T> = starts.iter().map(|&e| self.cycle_area2(e)).collect();
+            let by_area = |&i: &usize, &j: &usize| areas[i].partial_cmp(&areas[j]).unwrap_or(Ordering::Equal);
+            let mut bounds: Vec<usize> = (0..starts.len()).filter(|&i| areas[i] > T::zero()).collect();
+            if bounds.is_empty() {
+                bounds.push((0..starts.len()).max_by(by_area).unwrap());
+            }
+            let mut owners = vec![face];
+            for _ in 1..bounds.len() {
+                let data = self.faces[face].data().clone();
+                let new = self.add_face_with(data);
+                report.added_faces.push(new);
+                owners.push(new);
+            }
+
+            // Holes go to the smallest bounding cycle around them
+            let mut holes: Vec<Vec<HalfEdgeId>> = vec![Vec::new(); bounds.len()];
+            for i in (0..starts.len()).filter(|i| !bounds.contains(i)) {
+                let owner = (0..bounds.len())
+                    .filter(|&k| self.cycle_encloses(starts[bounds[k]], starts[i]))
+                    .min_by(|&k, &l| by_area(&bounds[k], &bounds[l]))
+                    .unwrap_or(0);
+                holes[owner].push(starts[i]);
+            }
+            for (k, &i) in bounds.iter().enumerate() {
+                for &start in Some(&starts[i]).into_iter().chain(&holes[k]) {
+                    for e in self.get_cycle_from(start) {
+                        self.edges[e].set_face(owners[k]);
+                    }
+                }
+                self.faces[owners[k]].set_incident_edge(Some(starts[i]));
+                self.faces[owners[k]].set_inner_components(holes[k].clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use compare::topologically_equal;
+
+    #[test]
+    fn welds_near_duplicates() {
+        // Two triangles meant to share their diagonal, built apart
+        let d = 1e-9;
+        let points = [Point2::new(0.0, 0.0), Point2::new(1.0, 0.0), Point2::new(1.0, 1.0),
+                      Point2::new(-d, d), Point2::new(1.0 - d, 1.0 + d), Point2::new(0.0, 1.0)];
+        let mut dcel = DCEL::from_polygons(&points, &[&[0, 1, 2], &[3, 4, 5]]).unwrap();
+        assert!(dcel.weld_vertices(1e-12).is_empty());
+        let report = dcel.weld_vertices(1e-6);
+        dcel.validate().unwrap();
+        let v = |i: usize| VertexId::from(i);
+        assert_eq!(report.merged, vec![(v(3), v(0)), (v(4), v(2))]);
+        assert_eq!(report.removed_edges.len(), 2);
+        assert!(report.removed_faces.is_empty() && report.added_faces.is_empty());
+        let square = DCEL::from_polygons(&points[..3].iter().cloned().chain(Some(points[5])).collect::<Vec<_>>(),
+                                         &[&[0, 1, 2], &[0, 2, 3]]).unwrap();
+        assert!(topologically_equal(&dcel, &square));
+        assert_eq!(dcel.faces[dcel.outer_face].inner_components().len(), 1);
+
+        // A sliver triangle collapses onto its neighbour's edge, and a
+        // zero-length edge disappears
+        let points = [Point2::new(0.0, 0.0), Point2::new(2.0, 0.0), Point2::new(1.0, 1.0), Point2::new(-d, d),
+                      Point2::new(2.0, d)];
+        let mut dcel = DCEL::from_polygons(&points, &[&[0, 1, 4, 2][..], &[0, 2, 3]]).unwrap();
+        let sliver = FaceId::from(2);
+        let report = dcel.weld_vertices(1e-6);
+        dcel.validate().unwrap();
+        assert_eq!(report.merged, vec![(v(3), v(0)), (v(4), v(1))]);
+        assert_eq!(report.removed_faces, vec![sliver]);
+        assert_eq!(report.removed_edges.len(), 6);
+        assert_eq!((dcel.vertices.len(), dcel.edges.len(), dcel.faces.len()), (3, 6, 2));
+        assert!((dcel.face_area(FaceId::from(1)) - 1.0).abs() < 1e-6);
+    }
+}